@@ -0,0 +1,661 @@
+//! Measurements of a maze's structure: connectivity, loops, branching.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Index;
+use crate::{Dir, KdTree, LabelMap, Maze, Point};
+
+/// Distances from one cell to all the others. Cells that can't be reached
+/// from the root have no distance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistanceMap {
+  pub(crate) root: Point,
+  pub(crate) distances: LabelMap<Option<usize>>,
+}
+
+/// A few numbers for comparing the texture of mazes; see Maze::stats
+#[derive(Debug, Clone, PartialEq)]
+pub struct MazeStats {
+  pub dead_ends: usize,
+  pub passages: usize,
+  pub average_degree: f64,
+}
+
+/// Why a maze isn't perfect
+#[derive(Debug, PartialEq)]
+pub enum ValidationError {
+  /// How many cells can't be reached from the top left corner, or from a
+  /// masked maze's first cell
+  Disconnected { unreachable: usize },
+  /// How many passages there are beyond the cells - 1 a tree would have
+  Loops { extra_passages: usize },
+}
+
+impl Maze {
+  /// Flood-fills from a cell, returning which cells (in row-major order) it
+  /// can reach: none at all from a point outside the maze
+  pub fn reachable_from(&self, from: Point) -> Vec<bool> {
+    let mut reached = vec![false; self.width * self.height];
+    if !self.valid(from) { return reached }
+    let mut stack = vec![from];
+    reached[self.index(from)] = true;
+
+    while let Some(pt) = stack.pop() {
+      for n in self.open_neighbors(pt) {
+        if !reached[self.index(n)] {
+          reached[self.index(n)] = true;
+          stack.push(n);
+        }
+      }
+    }
+    reached
+  }
+
+  /// `matrix[i][j]` is whether cell i can reach cell j, with cells numbered in
+  /// row-major order. This runs a flood fill from every cell, so it's O(V^2)
+  /// in both time and memory: fine for a 10x10 maze, hopeless for a 1000x1000
+  /// one. To just check connectivity use are_all_cells_mutually_reachable.
+  pub fn reachability_matrix(&self) -> Vec<Vec<bool>> {
+    self.iter().map(|pt| self.reachable_from(pt)).collect()
+  }
+
+  /// Passages are two-way, so one flood fill from any cell is enough
+  pub fn are_all_cells_mutually_reachable(&self) -> bool {
+    self.unreachable_count() == 0
+  }
+
+  // How many of the maze's cells the first one can't get to, leaving out
+  // any a mask has off
+  fn unreachable_count(&self) -> usize {
+    let reached = self.reachable_from(self.first_cell());
+    self.iter().filter(|&pt| self.in_maze(pt) && !reached[self.index(pt)]).count()
+  }
+
+  // The first of the maze's cells in row-major order: the top left corner,
+  // unless a mask has that off
+  pub(crate) fn first_cell(&self) -> Point {
+    self.iter().find(|&pt| self.in_maze(pt)).expect("")
+  }
+
+  // How many cells the maze has, leaving out any a mask has off
+  pub(crate) fn cell_count(&self) -> usize {
+    self.mask.as_ref().map_or(self.width * self.height, |mask| mask.count())
+  }
+
+  /// Splits the cells matching a predicate into groups connected to each
+  /// other by passages (only stepping through matching cells), in row-major
+  /// order of each group's first cell.
+  pub fn groups(&self, include: impl Fn(Point) -> bool) -> Vec<Vec<Point>> {
+    let mut seen = vec![false; self.width * self.height];
+    let mut groups = Vec::new();
+
+    for start in self.iter().filter(|&pt| include(pt)) {
+      if seen[self.index(start)] { continue }
+      seen[self.index(start)] = true;
+      let mut group = vec![start];
+      let mut stack = vec![start];
+
+      while let Some(pt) = stack.pop() {
+        for n in self.open_neighbors(pt) {
+          if include(n) && !seen[self.index(n)] {
+            seen[self.index(n)] = true;
+            group.push(n);
+            stack.push(n);
+          }
+        }
+      }
+      groups.push(group)
+    }
+    groups
+  }
+
+  /// The biggest connected patch of cells with three or more passages each
+  /// (or two or more, if no cell has three), in row-major order. Handy for
+  /// finding somewhere to put an arena or treasure room.
+  pub fn largest_room(&self) -> Vec<Point> {
+    let min_degree = if self.iter().any(|pt| self.degree(pt) >= 3) { 3 } else { 2 };
+    let mut room = self.groups(|pt| self.degree(pt) >= min_degree).into_iter()
+      .fold(Vec::new(), |best, group| if group.len() > best.len() { group } else { best });
+    room.sort_by_key(|pt| (pt.y, pt.x));
+    room
+  }
+
+  /// For each cell, the fraction of the interior edges within `radius`
+  /// (taxicab distance) of it that are walls: 1.0 for a tight knot of walls,
+  /// 0.0 around an open area. A cell whose neighborhood holds no edges at all
+  /// (radius 0, or a 1x1 maze) gets 0.0.
+  pub fn density_map(&self, radius: usize) -> LabelMap<f64> {
+    let mut map = LabelMap::new(self.width, self.height, 0.0);
+    let near = |p: Point, q: Point| p.distance(q) <= radius;
+
+    for pt in self.iter() {
+      let (mut edges, mut walls) = (0, 0);
+      // Only the box around the diamond, rather than the whole maze
+      let xs = pt.x.saturating_sub(radius)..=(pt.x.saturating_add(radius)).min(self.width - 1);
+      let ys = pt.y.saturating_sub(radius)..=(pt.y.saturating_add(radius)).min(self.height - 1);
+      let around = ys.flat_map(|y| xs.clone().map(move |x| Point { x, y }));
+      for q in around.filter(|&q| near(pt, q)) {
+        for &dir in [Dir::East, Dir::South].iter() {
+          if let Some(n) = self.neighbor(q, dir) {
+            if near(pt, n) {
+              edges += 1;
+              if !self.passage(q, dir) { walls += 1 }
+            }
+          }
+        }
+      }
+      if edges > 0 {
+        map[pt] = walls as f64 / edges as f64;
+      }
+    }
+    map
+  }
+
+  /// How many separate pieces the maze falls into, leaving out any cells a
+  /// mask has off
+  pub fn component_count(&self) -> usize {
+    self.groups(|pt| self.in_maze(pt)).len()
+  }
+
+  /// How many passages could be removed without disconnecting anything: zero
+  /// for a perfect maze, one per independent loop otherwise
+  pub fn count_cycles(&self) -> usize {
+    self.passage_count() + self.component_count() - self.cell_count()
+  }
+
+  /// A fundamental cycle basis: one loop per passage left over once a
+  /// breadth-first spanning tree is taken out, so count_cycles() of them.
+  /// Each loop starts and ends on the same cell.
+  pub fn all_cycles(&self) -> Vec<Vec<Point>> {
+    let mut parent: Vec<Option<Point>> = vec![None; self.width * self.height];
+    let mut depth = vec![0; self.width * self.height];
+    let mut seen = vec![false; self.width * self.height];
+
+    for root in self.iter() {
+      if seen[self.index(root)] { continue }
+      seen[self.index(root)] = true;
+      let mut queue = VecDeque::new();
+      queue.push_back(root);
+      while let Some(pt) = queue.pop_front() {
+        for n in self.open_neighbors(pt) {
+          if !seen[self.index(n)] {
+            seen[self.index(n)] = true;
+            parent[self.index(n)] = Some(pt);
+            depth[self.index(n)] = depth[self.index(pt)] + 1;
+            queue.push_back(n);
+          }
+        }
+      }
+    }
+
+    let mut cycles = Vec::new();
+    for pt in self.iter() {
+      for &dir in [Dir::East, Dir::South].iter() {
+        if !self.passage(pt, dir) { continue }
+        let n = self.neighbor(pt, dir).expect("");
+        if parent[self.index(n)] == Some(pt) || parent[self.index(pt)] == Some(n) { continue }
+
+        // Climb from both ends to where the tree paths meet
+        let (mut a, mut b) = (vec![pt], vec![n]);
+        let (mut x, mut y) = (pt, n);
+        while x != y {
+          if depth[self.index(x)] >= depth[self.index(y)] {
+            x = parent[self.index(x)].expect("");
+            a.push(x);
+          } else {
+            y = parent[self.index(y)].expect("");
+            b.push(y);
+          }
+        }
+        b.pop();
+        a.extend(b.into_iter().rev());
+        a.push(pt);
+        cycles.push(a);
+      }
+    }
+    cycles
+  }
+
+  /// Whether there's exactly one route between any two cells
+  pub fn is_perfect(&self) -> bool {
+    self.validate().is_ok()
+  }
+
+  /// Checks the maze is perfect: every cell reachable from every other, and
+  /// no loops, which for a connected maze means exactly one passage fewer
+  /// than there are cells. Says which it isn't if not, disconnection first.
+  pub fn validate(&self) -> Result<(), ValidationError> {
+    let unreachable = self.unreachable_count();
+    if unreachable > 0 { return Err(ValidationError::Disconnected { unreachable }) }
+    match self.passage_count() + 1 - self.cell_count() {
+      0 => Ok(()),
+      extra_passages => Err(ValidationError::Loops { extra_passages })
+    }
+  }
+
+  /// Finds cells with no passages at all. A generated maze never has any
+  /// (except a lone single cell, which has nowhere to go), so this makes a
+  /// cheap sanity check for imported or hand-edited mazes. Cells a mask has
+  /// off, from new_masked or apply_mask_from_csv, aren't in the maze and
+  /// aren't counted.
+  pub fn verify_no_isolated_cells(&self) -> Result<(), Vec<Point>> {
+    if self.cell_count() == 1 { return Ok(()) }
+    let isolated: Vec<Point> = self.iter().filter(|&pt| self.in_maze(pt) && self.degree(pt) == 0).collect();
+    if isolated.is_empty() { Ok(()) } else { Err(isolated) }
+  }
+
+  /// How far every cell is from `from`, following passages
+  pub fn distances(&self, from: Point) -> DistanceMap {
+    let labels = self.bfs_distances(from);
+    DistanceMap { root: from, distances: LabelMap { width: self.width, height: self.height, labels } }
+  }
+
+  /// The mean number of passages out of a cell: about 2.0 for a big perfect
+  /// maze, heading for 4.0 as it opens up
+  pub fn average_branching_factor(&self) -> f64 {
+    2.0 * self.passage_count() as f64 / self.cell_count() as f64
+  }
+
+  /// How many cells have each number of passages, from 0 to 4
+  pub fn branching_factor_distribution(&self) -> [usize; 5] {
+    let mut counts = [0; 5];
+    for pt in self.iter().filter(|&pt| self.in_maze(pt)) {
+      counts[self.degree(pt)] += 1;
+    }
+    counts
+  }
+
+  /// Every cell with exactly one way out, in row-major order
+  pub fn dead_ends(&self) -> Vec<Point> {
+    self.iter().filter(|&pt| self.degree(pt) == 1).collect()
+  }
+
+  /// How many dead ends and passages the maze has and how many passages
+  /// a cell has on average. Backtracker mazes have few dead ends and long
+  /// corridors; Prim's and Kruskal's have lots of short spurs.
+  pub fn stats(&self) -> MazeStats {
+    MazeStats {
+      dead_ends: self.dead_ends().len(),
+      passages: self.passage_count(),
+      average_degree: self.average_branching_factor(),
+    }
+  }
+
+  /// One maze per connected part of this one, biggest first. Each is the
+  /// full size of the original, with the same mask, but only has the
+  /// passages of its own part.
+  pub fn split_into_components(&self) -> Vec<Maze> {
+    let mut groups = self.groups(|pt| self.in_maze(pt));
+    groups.sort_by_key(|group| std::cmp::Reverse(group.len()));
+    groups.iter().map(|group| {
+      let mut part = Maze::with_topology(self.width, self.height, self.topology).expect("");
+      part.mask = self.mask.clone();
+      for &pt in group {
+        for &dir in [Dir::East, Dir::South].iter() {
+          if self.passage(pt, dir) { part.carve(pt, dir).expect("") }
+        }
+      }
+      part
+    }).collect()
+  }
+
+  /// A spatial index over every cell, for nearest-cell questions
+  pub fn build_kd_tree(&self) -> KdTree {
+    KdTree::new(self.iter().filter(|&pt| self.in_maze(pt)).collect())
+  }
+}
+
+impl DistanceMap {
+  /// The cell the distances are measured from
+  pub fn root(&self) -> Point {
+    self.root
+  }
+
+  /// Every cell the root can reach and how far it is, in row-major order
+  pub fn iter(&self) -> impl Iterator<Item = (Point, usize)> + '_ {
+    let width = self.distances.width;
+    self.distances.labels.iter().enumerate()
+      .filter_map(move |(i, d)| d.map(|d| (Point { x: i % width, y: i / width }, d)))
+  }
+
+  /// The farthest cell from the root and its distance, the first in
+  /// row-major order if there's a tie. None if the root isn't in the maze.
+  pub fn max(&self) -> Option<(Point, usize)> {
+    self.iter().fold(None, |best, (pt, d)| match best {
+      Some((_, b)) if b >= d => best,
+      _ => Some((pt, d))
+    })
+  }
+
+  /// Distances scaled to 0.0 at the root and 1.0 at the farthest cell.
+  /// Unreachable cells come out as NaN.
+  pub fn normalize(&self) -> LabelMap<f64> {
+    let max = self.distances.labels.iter().flatten().copied().max().unwrap_or(0);
+    LabelMap {
+      width: self.distances.width,
+      height: self.distances.height,
+      labels: self.distances.labels.iter().map(|d| match d {
+        Some(_) if max == 0 => 0.0,
+        Some(d) => *d as f64 / max as f64,
+        None => f64::NAN
+      }).collect()
+    }
+  }
+}
+
+impl Index<Point> for DistanceMap {
+  type Output = Option<usize>;
+  fn index(&self, point: Point) -> &Option<usize> {
+    &self.distances[point]
+  }
+}
+
+// A heatmap: one character per cell, rows on their own lines, cells
+// separated by spaces
+impl fmt::Display for DistanceMap {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for row in self.distances.labels.chunks(self.distances.width) {
+      let cells: Vec<String> = row.iter().map(|&d| distance_char(d).to_string()).collect();
+      writeln!(f, "{}", cells.join(" "))?;
+    }
+    Ok(())
+  }
+}
+
+impl fmt::Display for ValidationError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ValidationError::Disconnected { unreachable } => write!(f, "{} cells can't be reached", unreachable),
+      ValidationError::Loops { extra_passages } => write!(f, "{} passages too many, making loops", extra_passages)
+    }
+  }
+}
+
+// A distance as a single base-36 digit, '?' if it's too big for one, or
+// '.' for no distance at all
+pub(crate) fn distance_char(distance: Option<usize>) -> char {
+  match distance {
+    Some(d) => std::char::from_digit(d as u32, 36).filter(|_| d < 36).unwrap_or('?'),
+    None => '.'
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Algorithm, Mask};
+  use rand::rngs::StdRng;
+  use rand::SeedableRng;
+  use std::collections::HashSet;
+
+  #[test]
+  fn maze_reachability_test() {
+    let mut m = Maze::new(3, 2).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::South).expect("");
+    assert!(!m.are_all_cells_mutually_reachable());
+
+    let matrix = m.reachability_matrix();
+    assert_eq!(matrix.len(), 6);
+    assert!(matrix[0][1] && matrix[1][3] && matrix[3][0]);
+    assert!(!matrix[0][2] && !matrix[5][0]);
+    assert!(matrix[5][5]);
+    assert_eq!(m.reachable_from(Point { x: 3, y: 0 }), vec![false; 6]);
+
+    m.binary_tree(&mut StdRng::seed_from_u64(0));
+    assert!(m.are_all_cells_mutually_reachable());
+    assert!(m.reachability_matrix().iter().all(|row| row.iter().all(|&r| r)));
+  }
+
+  #[test]
+  fn maze_largest_room_test() {
+    // A corridor along the top, then a fully open 2x2 block in the bottom right
+    let mut m = Maze::new(4, 3).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 2, y: 0 }, Dir::South).expect("");
+    m.carve(Point { x: 2, y: 1 }, Dir::East).expect("");
+    m.carve(Point { x: 2, y: 1 }, Dir::South).expect("");
+    m.carve(Point { x: 3, y: 1 }, Dir::South).expect("");
+    m.carve(Point { x: 2, y: 2 }, Dir::East).expect("");
+    m.carve(Point { x: 3, y: 0 }, Dir::South).expect("");
+    assert_eq!(m.largest_room(), vec![Point { x: 2, y: 1 }, Point { x: 3, y: 1 }]);
+
+    // No cell has three passages, so this falls back to the corridor cells
+    let mut m = Maze::new(4, 1).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 2, y: 0 }, Dir::East).expect("");
+    assert_eq!(m.largest_room(), vec![Point { x: 1, y: 0 }, Point { x: 2, y: 0 }]);
+  }
+
+  #[test]
+  fn maze_density_map_test() {
+    let mut m = Maze::new(3, 3).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::South).expect("");
+
+    let d = m.density_map(1);
+    assert_eq!(d[Point { x: 0, y: 0 }], 0.0);
+    assert_eq!(d[Point { x: 1, y: 0 }], 2.0 / 3.0);
+    assert_eq!(d[Point { x: 2, y: 2 }], 1.0);
+    assert_eq!(d.get(Point { x: 3, y: 0 }), None);
+
+    let d = m.density_map(4);
+    assert_eq!(d[Point { x: 1, y: 1 }], 10.0 / 12.0);
+    assert_eq!(m.density_map(0)[Point { x: 0, y: 0 }], 0.0);
+
+    // Same as counting over the whole maze, near the edges too
+    let m = Maze::new_seeded_square(9, 4).expect("");
+    let d = m.density_map(3);
+    for pt in m.iter() {
+      let (mut edges, mut walls) = (0, 0);
+      for q in m.iter().filter(|&q| pt.distance(q) <= 3) {
+        for &dir in [Dir::East, Dir::South].iter() {
+          if m.neighbor(q, dir).is_some_and(|n| pt.distance(n) <= 3) {
+            edges += 1;
+            if !m.passage(q, dir) { walls += 1 }
+          }
+        }
+      }
+      assert_eq!(d[pt], walls as f64 / edges as f64);
+    }
+  }
+
+  #[test]
+  fn maze_all_cycles_test() {
+    let mut m = Maze::new(3, 3).expect("");
+    m.binary_tree(&mut StdRng::seed_from_u64(0));
+    assert_eq!(m.count_cycles(), 0);
+    assert!(m.all_cycles().is_empty());
+
+    let all: Vec<Point> = m.iter().collect();
+    m.carve_all_passages_in(&all);
+    assert_eq!(m.count_cycles(), 4);
+
+    let cycles = m.all_cycles();
+    assert_eq!(cycles.len(), 4);
+    for cycle in cycles.iter() {
+      assert_eq!(cycle.first(), cycle.last());
+      assert!(cycle.len() >= 5);
+      assert_eq!(cycle[1..].iter().collect::<HashSet<_>>().len(), cycle.len() - 1);
+      for step in cycle.windows(2) {
+        assert!(m.open_neighbors(step[0]).contains(&step[1]));
+      }
+    }
+
+    let mut m = Maze::new(4, 1).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    assert_eq!(m.component_count(), 3);
+    assert_eq!(m.count_cycles(), 0);
+  }
+
+  #[test]
+  fn maze_isolated_cells_test() {
+    let mut m = Maze::new_seeded_square(5, 1).expect("");
+    assert_eq!(m.verify_no_isolated_cells(), Ok(()));
+
+    m.isolate_region(&[Point { x: 1, y: 1 }, Point { x: 3, y: 4 }]);
+    assert_eq!(m.verify_no_isolated_cells(), Err(vec![Point { x: 1, y: 1 }, Point { x: 3, y: 4 }]));
+    assert_eq!(Maze::new(1, 1).expect("").verify_no_isolated_cells(), Ok(()));
+    assert_eq!(Maze::new(2, 1).expect("").verify_no_isolated_cells().err().map(|v| v.len()), Some(2));
+
+    // Masked-out cells don't count, however they were masked
+    let mut masked = Maze::new_masked(Mask::from_text("X....\n.....\n").expect("")).expect("");
+    masked.recursive_backtracker(&mut StdRng::seed_from_u64(2));
+    assert_eq!(masked.verify_no_isolated_cells(), Ok(()));
+    let mut blocked = Maze::new_seeded_square(3, 1).expect("");
+    blocked.apply_mask_from_csv("1,1,1\n1,1,1\n1,1,0\n").expect("");
+    blocked.kruskal(&mut StdRng::seed_from_u64(2));
+    assert_eq!(blocked.verify_no_isolated_cells(), Ok(()));
+    assert!(!blocked.in_maze(Point { x: 2, y: 2 }));
+    assert!(Maze::new_masked(Mask::from_text("X.\n").expect("")).expect("").verify_no_isolated_cells().is_ok());
+  }
+
+  #[test]
+  fn distance_map_display_test() {
+    let mut m = Maze::new(3, 2).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 2, y: 0 }, Dir::South).expect("");
+    m.carve(Point { x: 2, y: 1 }, Dir::West).expect("");
+
+    let d = m.distances(Point { x: 0, y: 0 });
+    assert_eq!(d[Point { x: 1, y: 1 }], Some(4));
+    assert_eq!(d[Point { x: 0, y: 1 }], None);
+    assert_eq!(d.to_string(), "0 1 2\n. 4 3\n");
+
+    let n = d.normalize();
+    assert_eq!(n[Point { x: 0, y: 0 }], 0.0);
+    assert_eq!(n[Point { x: 2, y: 1 }], 0.75);
+    assert_eq!(n[Point { x: 1, y: 1 }], 1.0);
+    assert!(n[Point { x: 0, y: 1 }].is_nan());
+
+    assert_eq!(distance_char(Some(10)), 'a');
+    assert_eq!(distance_char(Some(35)), 'z');
+    assert_eq!(distance_char(Some(36)), '?');
+
+    let mut long = Maze::new(40, 1).expect("");
+    let all: Vec<Point> = long.iter().collect();
+    long.carve_all_passages_in(&all);
+    assert!(long.distances(Point { x: 0, y: 0 }).to_string().ends_with("x y z ? ? ? ?\n"));
+  }
+
+  #[test]
+  fn maze_branching_factor_test() {
+    let mut m = Maze::new(3, 3).expect("");
+    assert_eq!(m.average_branching_factor(), 0.0);
+    assert_eq!(m.branching_factor_distribution(), [9, 0, 0, 0, 0]);
+
+    let all: Vec<Point> = m.iter().collect();
+    m.carve_all_passages_in(&all);
+    assert_eq!(m.average_branching_factor(), 24.0 / 9.0);
+    assert_eq!(m.branching_factor_distribution(), [0, 0, 4, 4, 1]);
+
+    let m = Maze::new_seeded_square(10, 8).expect("");
+    assert_eq!(m.average_branching_factor(), 1.98);
+    assert_eq!(m.branching_factor_distribution().iter().sum::<usize>(), 100);
+  }
+
+  #[test]
+  fn maze_split_into_components_test() {
+    let mut m = Maze::new(3, 2).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 2, y: 0 }, Dir::South).expect("");
+    m.carve(Point { x: 1, y: 1 }, Dir::East).expect("");
+
+    let parts = m.split_into_components();
+    assert_eq!(parts.len(), m.component_count());
+    assert_eq!(parts.iter().map(|p| p.passage_count()).collect::<Vec<_>>(), vec![2, 1, 0]);
+    assert!(parts[0].passage(Point { x: 1, y: 1 }, Dir::East));
+    assert!(parts[1].passage(Point { x: 0, y: 0 }, Dir::East));
+    assert!(parts.iter().all(|p| (p.width, p.height) == (3, 2)));
+
+    let m = Maze::new_seeded_square(5, 1).expect("");
+    assert_eq!(m.split_into_components().len(), 1);
+  }
+
+  #[test]
+  fn maze_masked_structure_test() {
+    let mut m = Maze::new_masked(Mask::from_text("..X\n...\nX..\n").expect("")).expect("");
+    m.recursive_backtracker(&mut StdRng::seed_from_u64(1));
+    assert_eq!(m.component_count(), 1);
+    assert_eq!(m.count_cycles(), 0);
+    assert_eq!(m.average_branching_factor(), 12.0 / 7.0);
+    assert_eq!(m.branching_factor_distribution().iter().sum::<usize>(), 7);
+
+    let parts = m.split_into_components();
+    assert_eq!(parts.len(), 1);
+    assert_eq!(parts[0].mask(), m.mask());
+    assert!(parts[0].is_perfect());
+
+    let tree = m.build_kd_tree();
+    assert_eq!(tree.within_radius(Point { x: 1, y: 1 }, 2).len(), 7);
+    assert_ne!(tree.nearest(Point { x: 2, y: 0 }), Point { x: 2, y: 0 });
+  }
+
+  #[test]
+  fn distance_map_iter_test() {
+    let mut m = Maze::new(3, 2).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::South).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::East).expect("");
+
+    let d = m.distances(Point { x: 0, y: 0 });
+    let all: Vec<(Point, usize)> = d.iter().collect();
+    assert_eq!(all, vec![
+      (Point { x: 0, y: 0 }, 0), (Point { x: 1, y: 0 }, 1),
+      (Point { x: 2, y: 0 }, 2), (Point { x: 1, y: 1 }, 2),
+    ]);
+    // (2, 0) and (1, 1) tie, and (2, 0) comes first
+    assert_eq!(d.max(), Some((Point { x: 2, y: 0 }, 2)));
+    assert_eq!(m.distances(Point { x: 0, y: 1 }).max(), Some((Point { x: 0, y: 1 }, 0)));
+    assert_eq!(m.distances(Point { x: 5, y: 5 }).max(), None);
+  }
+
+  #[test]
+  fn dead_ends_and_stats_test() {
+    let p = |x, y| Point { x, y };
+    // A T: three dead ends round a junction at (1, 0)
+    let mut m = Maze::new(3, 2).expect("");
+    m.carve(p(1, 0), Dir::West).expect("");
+    m.carve(p(1, 0), Dir::East).expect("");
+    m.carve(p(1, 0), Dir::South).expect("");
+    assert_eq!(m.dead_ends(), vec![p(0, 0), p(2, 0), p(1, 1)]);
+    assert_eq!(m.stats(), MazeStats { dead_ends: 3, passages: 3, average_degree: 1.0 });
+
+    let mut m = Maze::new(10, 10).expect("");
+    m.recursive_backtracker(&mut StdRng::seed_from_u64(1));
+    let stats = m.stats();
+    assert_eq!(stats.passages, 99);
+    assert_eq!(stats.average_degree, 1.98);
+    assert_eq!(stats.dead_ends, m.branching_factor_distribution()[1]);
+  }
+
+  #[test]
+  fn validate_test() {
+    for &algorithm in Algorithm::ALL.iter() {
+      let mut m = Maze::new(7, 5).expect("");
+      m.generate(algorithm, &mut StdRng::seed_from_u64(2));
+      assert!(m.is_perfect(), "{:?}", algorithm);
+    }
+
+    let mut m = Maze::new(3, 3).expect("");
+    assert_eq!(m.validate(), Err(ValidationError::Disconnected { unreachable: 8 }));
+    m.carve_all_passages_in(&m.iter().collect::<Vec<Point>>());
+    assert_eq!(m.validate(), Err(ValidationError::Loops { extra_passages: 4 }));
+    assert_eq!(ValidationError::Loops { extra_passages: 4 }.to_string(), "4 passages too many, making loops");
+    assert!(Maze::new(1, 1).expect("").is_perfect());
+
+    // The masked-out corner doesn't count as a cell nobody can reach
+    let mut masked = Maze::new_masked(Mask::from_text("X....\n.....\n").expect("")).expect("");
+    masked.recursive_backtracker(&mut StdRng::seed_from_u64(2));
+    assert!(masked.is_perfect());
+    assert!(masked.are_all_cells_mutually_reachable());
+    let mut l = Maze::new_masked(Mask::from_text("X.\n..\n").expect("")).expect("");
+    l.carve(Point { x: 1, y: 0 }, Dir::South).expect("");
+    assert_eq!(l.validate(), Err(ValidationError::Disconnected { unreachable: 1 }));
+    l.carve(Point { x: 1, y: 1 }, Dir::West).expect("");
+    assert!(l.is_perfect());
+  }
+}
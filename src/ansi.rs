@@ -0,0 +1,146 @@
+//! Text rendering in color, for terminals.
+
+use std::env;
+use std::io::{self, IsTerminal};
+use crate::{Dir, Heatmap, Maze, Point, RenderStyle};
+
+const RESET: &str = "\x1b[0m";
+
+/// What render_ansi draws in: the text style, and colors as numbers in the
+/// terminal's 256-color palette
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AnsiOptions {
+  pub style: RenderStyle,
+  pub wall: u8,
+  pub path: u8,
+  /// The background of the first cell of the path
+  pub start: u8,
+  /// The background of the last cell of the path
+  pub goal: u8,
+  /// Shades the background of every cell by its distance from the
+  /// heatmap's root, in whichever palette colors are nearest
+  pub gradient: Option<Heatmap>,
+}
+
+impl Default for AnsiOptions {
+  fn default() -> AnsiOptions {
+    AnsiOptions { style: RenderStyle::Ascii, wall: 245, path: 39, start: 34, goal: 127, gradient: None }
+  }
+}
+
+impl Maze {
+  /// Draws the maze as text with a path through it, like
+  /// render_with_solution, colored with ANSI escapes
+  pub fn render_ansi(&self, options: &AnsiOptions, path: &[Point]) -> String {
+    let mark = match options.style { RenderStyle::Ascii => '*', RenderStyle::Unicode => '•' };
+    let shades = options.gradient.map(|heatmap| heatmap.colors(self));
+    let shade = |pt: Point| shades.as_ref().and_then(|colors| colors[pt]).map(palette_color);
+    let cell_background = |pt: Point| {
+      if path.first() == Some(&pt) { Some(options.start) }
+      else if path.last() == Some(&pt) { Some(options.goal) }
+      else { shade(pt) }
+    };
+
+    let mut out = String::new();
+    for (row, line) in self.render_with_solution(options.style, path).lines().enumerate() {
+      let mut current = (None, None);
+      for (col, c) in line.chars().enumerate() {
+        let (x, y) = (col / 2, row / 2);
+        let background = match (col % 2, row % 2) {
+          (1, 1) => cell_background(Point { x, y }),
+          // A passage between two cells gets the shade of the one west or
+          // north of it
+          (0, 1) if x > 0 && x < self.width && self.passage(Point { x: x - 1, y }, Dir::East) => {
+            shade(Point { x: x - 1, y })
+          },
+          (1, 0) if y > 0 && y < self.height && self.passage(Point { x, y: y - 1 }, Dir::South) => {
+            shade(Point { x, y: y - 1 })
+          },
+          _ => None
+        };
+        let foreground = match c {
+          ' ' => None,
+          _ if c == mark => Some(options.path),
+          _ => Some(options.wall)
+        };
+
+        if (foreground, background) != current {
+          if current != (None, None) { out.push_str(RESET) }
+          if let Some(n) = foreground { out.push_str(&format!("\x1b[38;5;{}m", n)) }
+          if let Some(n) = background { out.push_str(&format!("\x1b[48;5;{}m", n)) }
+          current = (foreground, background);
+        }
+        out.push(c);
+      }
+      if current != (None, None) { out.push_str(RESET) }
+      out.push('\n');
+    }
+    out
+  }
+
+  /// Prints render_ansi to stdout if it's a terminal. Piped anywhere else,
+  /// or with NO_COLOR set, it's render_with_solution's plain text instead.
+  pub fn print_ansi(&self, options: &AnsiOptions, path: &[Point]) {
+    if io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none() {
+      print!("{}", self.render_ansi(options, path))
+    } else {
+      print!("{}", self.render_with_solution(options.style, path))
+    }
+  }
+}
+
+// The nearest color to an RGB one in the palette's 6x6x6 cube
+fn palette_color([r, g, b]: [u8; 3]) -> u8 {
+  let level = |c: u8| ((c as u16 * 5 + 127) / 255) as u8;
+  16 + 36 * level(r) + 6 * level(g) + level(b)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // The text with the escapes taken out
+  fn strip(text: &str) -> String {
+    let mut plain = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+      if c == '\x1b' {
+        for c in chars.by_ref() { if c == 'm' { break } }
+      } else {
+        plain.push(c);
+      }
+    }
+    plain
+  }
+
+  #[test]
+  fn maze_render_ansi_test() {
+    let mut m = Maze::new(2, 1).expect("");
+    let p = |x, y| Point { x, y };
+    m.carve(p(0, 0), Dir::East).expect("");
+    let options = AnsiOptions { wall: 1, path: 2, start: 3, goal: 4, ..AnsiOptions::default() };
+    let path = [p(0, 0), p(1, 0)];
+    assert_eq!(m.render_ansi(&options, &path),
+               "\x1b[38;5;1m+-+-+\x1b[0m\n\
+                \x1b[38;5;1m|\x1b[0m\x1b[38;5;2m\x1b[48;5;3m*\x1b[0m\x1b[38;5;2m*\x1b[0m\x1b[38;5;2m\x1b[48;5;4m*\x1b[0m\
+                \x1b[38;5;1m|\x1b[0m\n\
+                \x1b[38;5;1m+-+-+\x1b[0m\n");
+    assert_eq!(strip(&m.render_ansi(&options, &[])), m.render_styled(RenderStyle::Ascii));
+
+    let m = Maze::new_seeded_square(6, 3).expect("");
+    let path = m.solve(p(0, 0), p(5, 5)).expect("");
+    let shaded = AnsiOptions { style: RenderStyle::Unicode, gradient: Some(Heatmap::new(p(0, 0))), ..options };
+    let text = m.render_ansi(&shaded, &path);
+    assert_eq!(strip(&text), m.render_with_solution(RenderStyle::Unicode, &path));
+    // Pale yellow by the root and deep red at the far end
+    assert!(text.contains("\x1b[48;5;230m") && text.contains("\x1b[48;5;161m"));
+  }
+
+  #[test]
+  fn palette_color_test() {
+    assert_eq!(palette_color([0, 0, 0]), 16);
+    assert_eq!(palette_color([255, 255, 255]), 231);
+    assert_eq!(palette_color([255, 0, 0]), 196);
+    assert_eq!(palette_color([0, 140, 0]), 16 + 18);
+  }
+}
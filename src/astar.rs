@@ -0,0 +1,154 @@
+//! A* search: routes found without visiting the whole maze, and a count of
+//! how much of it was looked at to compare against breadth-first search.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use crate::{Maze, Path, Point, Topology};
+
+/// What an A* search found: the route (None if there isn't one) and how
+/// many cells it expanded, looking at their neighbors, along the way
+#[derive(Debug, Clone, PartialEq)]
+pub struct AStarResult {
+  pub path: Option<Path>,
+  pub expanded: usize,
+}
+
+impl Maze {
+  /// A* search from `start` to `goal`. `heuristic(cell, goal)` guesses how
+  /// many steps are left from a cell; the closer the guess the fewer cells
+  /// get expanded, and as long as it never guesses high the route is a
+  /// shortest one. A cell already expanded goes back on the queue if a
+  /// cheaper way to it turns up, so the heuristic doesn't have to be
+  /// consistent too. A heuristic of zero everywhere is breadth-first search.
+  pub fn astar(&self, start: Point, goal: Point, heuristic: impl Fn(Point, Point) -> usize) -> AStarResult {
+    if !self.valid(start) || !self.valid(goal) { return AStarResult { path: None, expanded: 0 } }
+    let mut parents = vec![None; self.width * self.height];
+    let mut costs: Vec<Option<usize>> = vec![None; self.width * self.height];
+    let mut closed = vec![false; self.width * self.height];
+    let mut open = BinaryHeap::new();
+    let mut expanded = 0;
+    costs[self.index(start)] = Some(0);
+    // Cheapest estimate first, then the one nearer the goal, then row-major
+    open.push(Reverse((heuristic(start, goal), heuristic(start, goal), self.index(start))));
+
+    while let Some(Reverse((_, _, i))) = open.pop() {
+      if closed[i] { continue }
+      let pt = self.nth_point(i).expect("");
+      if pt == goal { return AStarResult { path: Some(self.path_to(&parents, goal)), expanded } }
+      closed[i] = true;
+      expanded += 1;
+
+      let cost = costs[i].expect("") + 1;
+      for n in self.open_neighbors(pt) {
+        let j = self.index(n);
+        if costs[j].is_some_and(|c| c <= cost) { continue }
+        closed[j] = false;
+        costs[j] = Some(cost);
+        parents[j] = Some(pt);
+        let h = heuristic(n, goal);
+        open.push(Reverse((cost + h, h, j)));
+      }
+    }
+    AStarResult { path: None, expanded }
+  }
+
+  /// A* guided by Manhattan distance, counting the short way round the
+  /// edges of a maze that wraps
+  pub fn astar_manhattan(&self, start: Point, goal: Point) -> AStarResult {
+    self.astar(start, goal, |a, b| self.manhattan(a, b))
+  }
+
+  /// Steps between two cells if there were no walls at all
+  pub fn manhattan(&self, a: Point, b: Point) -> usize {
+    let dx = a.x.abs_diff(b.x);
+    let dy = a.y.abs_diff(b.y);
+    match self.topology {
+      Topology::Plane => dx + dy,
+      Topology::Cylinder => dx.min(self.width - dx) + dy,
+      Topology::Torus => dx.min(self.width - dx) + dy.min(self.height - dy),
+      // Going round the back flips the row over
+      Topology::Mobius => (dx + dy).min(self.width - dx + (self.height - 1 - a.y).abs_diff(b.y))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Dir;
+  use rand::rngs::StdRng;
+  use rand::{Rng, SeedableRng};
+
+  #[test]
+  fn astar_test() {
+    let p = |x, y| Point { x, y };
+    let mut m = Maze::new(15, 10).expect("");
+    m.recursive_backtracker(&mut StdRng::seed_from_u64(8));
+    // Knock out some walls so there's more than one way round
+    let mut rng = StdRng::seed_from_u64(9);
+    for _ in 0..30 {
+      m.carve(p(rng.gen_range(0, 14), rng.gen_range(0, 10)), Dir::East).expect("");
+    }
+
+    let found = m.astar_manhattan(p(0, 0), p(14, 9));
+    let path = found.path.expect("");
+    assert_eq!(path.first(), Some(&p(0, 0)));
+    assert_eq!(path.last(), Some(&p(14, 9)));
+    assert_eq!(path.len(), m.solve(p(0, 0), p(14, 9)).expect("").len());
+
+    let blind = m.astar(p(0, 0), p(14, 9), |_, _| 0);
+    assert_eq!(blind.path.map(|path| path.len()), Some(path.len()));
+    assert!(found.expanded <= blind.expanded);
+
+    let start = m.astar_manhattan(p(3, 3), p(3, 3));
+    assert_eq!(start, AStarResult { path: Some(vec![p(3, 3)]), expanded: 0 });
+    assert_eq!(m.astar_manhattan(p(0, 0), p(15, 0)), AStarResult { path: None, expanded: 0 });
+
+    let walled = Maze::new(3, 3).expect("");
+    assert_eq!(walled.astar_manhattan(p(0, 0), p(2, 2)), AStarResult { path: None, expanded: 1 });
+  }
+
+  #[test]
+  fn astar_inconsistent_heuristic_test() {
+    let p = |x, y| Point { x, y };
+    let mut m = Maze::new(8, 8).expect("");
+    m.recursive_backtracker(&mut StdRng::seed_from_u64(4));
+    let mut rng = StdRng::seed_from_u64(5);
+    for _ in 0..25 {
+      m.carve(p(rng.gen_range(0, 7), rng.gen_range(0, 8)), Dir::East).expect("");
+    }
+    let goal = p(7, 7);
+    let distances = m.bfs_distances(goal);
+
+    // Never guesses high, but jumps around from one cell to the next
+    for seed in 0..50 {
+      let mut rng = StdRng::seed_from_u64(seed);
+      let guesses: Vec<usize> = distances.iter().map(|d| rng.gen_range(0, d.expect("") + 1)).collect();
+      let found = m.astar(p(0, 0), goal, |n, _| guesses[m.index(n)]).path.expect("");
+      assert_eq!(found.len(), distances[0].expect("") + 1, "seed {}", seed);
+    }
+  }
+
+  #[test]
+  fn manhattan_test() {
+    let p = |x, y| Point { x, y };
+    assert_eq!(Maze::new(6, 4).expect("").manhattan(p(0, 0), p(5, 3)), 8);
+    assert_eq!(Maze::with_topology(6, 4, Topology::Cylinder).expect("").manhattan(p(0, 0), p(5, 3)), 4);
+    assert_eq!(Maze::with_topology(6, 4, Topology::Torus).expect("").manhattan(p(0, 0), p(5, 3)), 2);
+    assert_eq!(Maze::with_topology(6, 4, Topology::Mobius).expect("").manhattan(p(0, 0), p(5, 3)), 1);
+
+    // Exactly the real distance once every wall is down
+    for &topology in Topology::ALL.iter() {
+      let mut m = Maze::with_topology(5, 4, topology).expect("");
+      for pt in m.iter().collect::<Vec<Point>>() {
+        for &dir in Dir::ALL.iter() { m.carve(pt, dir).ok(); }
+      }
+      for a in m.iter() {
+        let distances = m.bfs_distances(a);
+        for b in m.iter() {
+          assert_eq!(Some(m.manhattan(a, b)), distances[m.index(b)], "{:?} {:?} {:?}", topology, a, b);
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,120 @@
+//! A packed list of bools, for keeping a maze's walls in.
+
+use std::fmt;
+use std::ops::Index;
+
+/// Bools packed 64 to a word, first bool in the lowest bit, so a maze's
+/// walls take an eighth of the room a Vec<bool> would. Bits past the end
+/// are always zero, so two with the same bools compare equal.
+#[derive(Clone, PartialEq, Eq, Default)]
+pub(crate) struct BitVec {
+  words: Vec<u64>,
+  len: usize,
+}
+
+impl BitVec {
+  /// `len` bools, all `value`
+  pub fn new(len: usize, value: bool) -> BitVec {
+    let mut bits = BitVec { words: vec![if value { !0 } else { 0 }; len.div_ceil(64)], len };
+    bits.clear_tail();
+    bits
+  }
+
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn get(&self, i: usize) -> bool {
+    assert!(i < self.len, "bit {} is past the end of {}", i, self.len);
+    self.words[i / 64] & (1 << (i % 64)) != 0
+  }
+
+  pub fn set(&mut self, i: usize, value: bool) {
+    assert!(i < self.len, "bit {} is past the end of {}", i, self.len);
+    if value {
+      self.words[i / 64] |= 1 << (i % 64)
+    } else {
+      self.words[i / 64] &= !(1 << (i % 64))
+    }
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+    (0..self.len).map(move |i| self.get(i))
+  }
+
+  /// How many of the bools are true
+  pub fn count_ones(&self) -> usize {
+    self.words.iter().map(|word| word.count_ones() as usize).sum()
+  }
+
+  // Zeroes whatever of the last word is past the end
+  fn clear_tail(&mut self) {
+    if !self.len.is_multiple_of(64) {
+      if let Some(last) = self.words.last_mut() { *last &= (1 << (self.len % 64)) - 1 }
+    }
+  }
+}
+
+impl From<&[bool]> for BitVec {
+  fn from(bools: &[bool]) -> BitVec {
+    let mut bits = BitVec::new(bools.len(), false);
+    for (i, &b) in bools.iter().enumerate() {
+      if b { bits.set(i, true) }
+    }
+    bits
+  }
+}
+
+impl From<Vec<bool>> for BitVec {
+  fn from(bools: Vec<bool>) -> BitVec {
+    BitVec::from(&bools[..])
+  }
+}
+
+// So walls[n] reads the same as it did when they were a Vec<bool>
+impl Index<usize> for BitVec {
+  type Output = bool;
+  fn index(&self, i: usize) -> &bool {
+    if self.get(i) { &true } else { &false }
+  }
+}
+
+impl PartialEq<Vec<bool>> for BitVec {
+  fn eq(&self, other: &Vec<bool>) -> bool {
+    self.len == other.len() && self.iter().zip(other.iter()).all(|(a, &b)| a == b)
+  }
+}
+
+// Printed as a list of bools, like the Vec<bool> it stands in for
+impl fmt::Debug for BitVec {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_list().entries(self.iter()).finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn bitvec_test() {
+    let mut bits = BitVec::new(130, true);
+    assert_eq!(bits.len(), 130);
+    assert_eq!(bits.count_ones(), 130);
+    bits.set(0, false);
+    bits.set(64, false);
+    bits.set(129, false);
+    assert!(!bits.get(64) && bits.get(65) && !bits[129]);
+    assert_eq!(bits.count_ones(), 127);
+    assert_eq!(bits.iter().filter(|&b| !b).count(), 3);
+
+    let bools = vec![true, false, true];
+    assert_eq!(BitVec::from(&bools[..]), bools);
+    assert_eq!(format!("{:?}", BitVec::from(bools.clone())), format!("{:?}", bools));
+    // The unused bits don't get in the way of comparing
+    let mut cleared = BitVec::new(3, true);
+    cleared.set(1, false);
+    assert_eq!(cleared, BitVec::from(bools));
+    assert_eq!(BitVec::new(0, true), BitVec::default());
+  }
+}
@@ -0,0 +1,516 @@
+//! Vector output: SVG, TikZ, Three.js and polygon paths.
+
+use std::collections::{BTreeMap, HashSet};
+use crate::{Dir, GenerationState, LabelMap, Maze, Path, Point};
+
+/// How Maze::to_svg draws a maze: each cell `cell_size` units across,
+/// walls in `stroke` `stroke_width` units wide, over a rectangle of
+/// `background`, or nothing with None. Colors are anything SVG understands,
+/// like "black" or "#3366ff".
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgOptions {
+  pub cell_size: usize,
+  pub stroke_width: f64,
+  pub stroke: String,
+  pub background: Option<String>,
+}
+
+impl Default for SvgOptions {
+  fn default() -> Self {
+    SvgOptions { cell_size: 20, stroke_width: 2.0, stroke: "black".to_string(), background: Some("white".to_string()) }
+  }
+}
+
+/// Coloring for a maze drawn as a heatmap of how far each cell is from
+/// `root`: from `near` at the root to `far` at the farthest cell, as RGB.
+/// Cells that can't be reached from the root are left the background.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Heatmap {
+  pub root: Point,
+  pub near: [u8; 3],
+  pub far: [u8; 3],
+}
+
+impl Heatmap {
+  /// Pale yellow at `root` to deep red at the far end
+  pub fn new(root: Point) -> Heatmap {
+    Heatmap { root, near: [255, 255, 204], far: [189, 0, 38] }
+  }
+
+  /// Each cell's color, or None for the ones that don't get one
+  pub fn colors(&self, maze: &Maze) -> LabelMap<Option<[u8; 3]>> {
+    let distances = maze.distances(self.root).normalize();
+    let mut colors = maze.cell_data(None);
+    for (pt, &t) in distances.iter().filter(|&(_, t)| !t.is_nan()) {
+      let mix = |i: usize| (self.near[i] as f64 + (self.far[i] as f64 - self.near[i] as f64) * t).round() as u8;
+      colors[pt] = Some([mix(0), mix(1), mix(2)]);
+    }
+    colors
+  }
+}
+
+impl Maze {
+  /// The walls as a single SVG path, joined into as few strokes as
+  /// to_polybool_paths can manage. The picture has room for half a stroke
+  /// round the outside so the boundary isn't clipped.
+  pub fn to_svg(&self, options: &SvgOptions) -> String {
+    self.svg_with(options, "", "")
+  }
+
+  /// Like to_svg, with each cell filled in by its distance from the
+  /// heatmap's root
+  pub fn to_svg_heatmap(&self, options: &SvgOptions, heatmap: &Heatmap) -> String {
+    let size = options.cell_size;
+    let cells: String = heatmap.colors(self).iter().filter_map(|(pt, color)| {
+      let [r, g, b] = (*color)?;
+      Some(format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#{:02x}{:02x}{:02x}\"/>\n",
+                   pt.x * size, pt.y * size, size, size, r, g, b))
+    }).collect();
+    self.svg_with(options, &cells, "")
+  }
+
+  /// Like to_svg, with a path drawn over the maze as a red line through the
+  /// middle of each cell along it. A step that wraps round the edge breaks
+  /// the line rather than crossing the whole maze.
+  pub fn to_svg_with_solution(&self, options: &SvgOptions, path: &[Point]) -> String {
+    if path.is_empty() { return self.to_svg(options) }
+    let size = options.cell_size as f64;
+    let steps: Vec<String> = path.iter().enumerate().map(|(i, &pt)| {
+      let joined = i > 0 && path[i - 1].distance(pt) == 1;
+      format!("{} {} {}", if joined { "L" } else { "M" }, (pt.x as f64 + 0.5) * size, (pt.y as f64 + 0.5) * size)
+    }).collect();
+    let line = format!("<path d=\"{}\" fill=\"none\" stroke=\"red\" stroke-width=\"{}\" stroke-linecap=\"round\" \
+                        stroke-linejoin=\"round\"/>\n", steps.join(" "), options.stroke_width * 1.5);
+    self.svg_with(options, "", &line)
+  }
+
+  // to_svg with extra elements drawn under the walls and over them
+  fn svg_with(&self, options: &SvgOptions, under: &str, over: &str) -> String {
+    let (w, h) = (self.width * options.cell_size, self.height * options.cell_size);
+    let pad = options.stroke_width / 2.0;
+    let (vw, vh) = (w as f64 + options.stroke_width, h as f64 + options.stroke_width);
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\">\n",
+                          vw, vh, -pad, -pad, vw, vh);
+    if let Some(background) = &options.background {
+      svg.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                            -pad, -pad, vw, vh, background));
+    }
+    svg.push_str(under);
+    let d: Vec<String> = self.to_polybool_paths(options.cell_size as f64).iter().map(|points| {
+      let steps: Vec<String> = points.iter().map(|&(x, y)| format!("{} {}", x, y)).collect();
+      format!("M {}", steps.join(" L "))
+    }).collect();
+    svg.push_str(&format!("<path d=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"square\"/>\n",
+                          d.join(" "), options.stroke, options.stroke_width));
+    svg.push_str(over);
+    svg.push_str("</svg>\n");
+    svg
+  }
+
+  /// Like to_svg, with whatever `glyph` makes of each cell's data written in
+  /// the middle of the cell. Cells it gives None for are left empty.
+  pub fn to_svg_with_data<T>(&self, options: &SvgOptions, data: &LabelMap<T>, glyph: impl Fn(&T) -> Option<char>) -> String {
+    let mut labels = String::new();
+    let size = options.cell_size as f64;
+    for (pt, value) in data.iter().filter(|&(pt, _)| self.valid(pt)) {
+      let c = match glyph(value) {
+        Some(c) => c,
+        None => continue
+      };
+      let text = match c {
+        '<' => "&lt;".to_string(),
+        '>' => "&gt;".to_string(),
+        '&' => "&amp;".to_string(),
+        _ => c.to_string()
+      };
+      labels.push_str(&format!("<text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>\n",
+                               (pt.x as f64 + 0.5) * size, (pt.y as f64 + 0.5) * size, size * 0.7, text));
+    }
+    self.svg_with(options, "", &labels)
+  }
+
+  /// An animated SVG of a maze being generated, one frame per state, each
+  /// carved passage flashing red in the frame it's carved in
+  pub fn to_svg_animated_generation(steps: &[GenerationState], cell_size: usize, frame_duration_ms: u32) -> String {
+    let (width, height) = steps.first().map_or((0, 0), |s| (s.maze.width, s.maze.height));
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+                          width * cell_size, height * cell_size, width * cell_size, height * cell_size);
+    svg.push_str(&format!("<rect x=\"0\" y=\"0\" width=\"{}\" height=\"{}\" fill=\"white\" stroke=\"black\" stroke-width=\"2\"/>\n",
+                          width * cell_size, height * cell_size));
+
+    let duration = frame_duration_ms as usize * steps.len();
+    let animate = |frames: Vec<bool>| {
+      let values: Vec<&str> = frames.iter().map(|&v| if v { "visible" } else { "hidden" }).collect();
+      format!("<animate attributeName=\"visibility\" values=\"{}\" dur=\"{}ms\" calcMode=\"discrete\" fill=\"freeze\"/>",
+              values.join(";"), duration)
+    };
+    let line = |pt: Point, dir: Dir, style: &str, frames: Vec<bool>| {
+      let ((x1, y1), (x2, y2)) = wall_line(pt, dir);
+      format!("<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {}>{}</line>\n",
+              x1 * cell_size, y1 * cell_size, x2 * cell_size, y2 * cell_size, style, animate(frames))
+    };
+
+    if let Some(first) = steps.first() {
+      for (pt, dir) in first.maze.interior_edges() {
+        let frames: Vec<bool> = steps.iter().map(|s| !s.maze.passage(pt, dir)).collect();
+        if frames.iter().any(|&wall| wall) {
+          svg.push_str(&line(pt, dir, "stroke=\"black\" stroke-width=\"2\"", frames));
+        }
+      }
+    }
+    for (n, step) in steps.iter().enumerate() {
+      if let Some((pt, dir)) = step.carved {
+        let frames = (0..steps.len()).map(|i| i == n).collect();
+        svg.push_str(&line(pt, dir, "stroke=\"red\" stroke-width=\"2\"", frames));
+      }
+    }
+    svg.push_str("</svg>\n");
+    svg
+  }
+
+  /// Every wall as a line in cell-corner coordinates, the outer boundary
+  /// included, each drawn once
+  pub fn wall_lines(&self) -> Vec<((usize, usize), (usize, usize))> {
+    let mut lines = Vec::new();
+    for pt in self.iter() {
+      if pt.y == 0 && !self.open_side(pt, Dir::North) { lines.push(wall_line(pt, Dir::North)) }
+      if pt.x == 0 && !self.open_side(pt, Dir::West) { lines.push(wall_line(pt, Dir::West)) }
+      for &dir in [Dir::East, Dir::South].iter() {
+        if !self.open_side(pt, dir) { lines.push(wall_line(pt, dir)) }
+      }
+    }
+    lines
+  }
+
+  fn svg_walls(&self, cell_size: usize) -> String {
+    self.wall_lines().iter().map(|&((x1, y1), (x2, y2))| {
+      format!("<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"black\" stroke-width=\"2\"/>\n",
+              x1 * cell_size, y1 * cell_size, x2 * cell_size, y2 * cell_size)
+    }).collect()
+  }
+
+  /// The passage graph drawn as a graph: a dot in the middle of each cell,
+  /// with a line between two dots wherever there's a passage
+  pub fn to_svg_dual_graph(&self, cell_size: usize) -> String {
+    self.svg_dual_graph(cell_size, false)
+  }
+
+  /// The same, drawn over the maze's walls
+  pub fn to_svg_dual_graph_with_walls(&self, cell_size: usize) -> String {
+    self.svg_dual_graph(cell_size, true)
+  }
+
+  fn svg_dual_graph(&self, cell_size: usize, walls: bool) -> String {
+    let (w, h) = (self.width * cell_size, self.height * cell_size);
+    let center = |pt: Point| (pt.x * cell_size + cell_size / 2, pt.y * cell_size + cell_size / 2);
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+                          w, h, w, h);
+    svg.push_str(&format!("<rect x=\"0\" y=\"0\" width=\"{}\" height=\"{}\" fill=\"white\"/>\n", w, h));
+    if walls {
+      svg.push_str(&self.svg_walls(cell_size));
+    }
+    for (pt, dir) in self.interior_edges() {
+      if self.passage(pt, dir) {
+        let ((x1, y1), (x2, y2)) = (center(pt), center(self.neighbor(pt, dir).expect("")));
+        svg.push_str(&format!("<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"blue\" stroke-width=\"2\"/>\n",
+                              x1, y1, x2, y2));
+      }
+    }
+    for pt in self.iter() {
+      let (cx, cy) = center(pt);
+      svg.push_str(&format!("<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"blue\"/>\n", cx, cy, (cell_size / 8).max(1)));
+    }
+    svg.push_str("</svg>\n");
+    svg
+  }
+
+  /// The walls joined up into as few polylines as possible, for renderers
+  /// where each line is expensive: wall segments meeting at a corner where
+  /// nothing else meets are chained together, and straight runs collapse
+  /// to their two ends. Points are in the same units as cell_size.
+  pub fn to_polybool_paths(&self, cell_size: f64) -> Vec<Vec<(f64, f64)>> {
+    let mut adjacent: BTreeMap<(usize, usize), Vec<(usize, usize)>> = BTreeMap::new();
+    for (a, b) in self.wall_lines() {
+      adjacent.entry(a).or_default().push(b);
+      adjacent.entry(b).or_default().push(a);
+    }
+
+    let mut used: HashSet<((usize, usize), (usize, usize))> = HashSet::new();
+    let mut chains = Vec::new();
+    let walk = |start: (usize, usize), used: &mut HashSet<_>| {
+      let mut chain = vec![start];
+      let mut at = start;
+      while let Some(&next) = adjacent[&at].iter().find(|&&n| !used.contains(&(at, n))) {
+        used.insert((at, next));
+        used.insert((next, at));
+        chain.push(next);
+        at = next;
+        if adjacent[&at].len() != 2 { break }
+      }
+      chain
+    };
+
+    // Chains run between junctions and line ends; whatever's left is loops
+    let ends: Vec<(usize, usize)> = adjacent.iter().filter(|(_, ns)| ns.len() != 2).map(|(&v, _)| v).collect();
+    for &v in ends.iter().chain(adjacent.keys()) {
+      while adjacent[&v].iter().any(|&n| !used.contains(&(v, n))) {
+        chains.push(walk(v, &mut used));
+      }
+    }
+
+    chains.into_iter().map(|chain| {
+      let mut points: Vec<(usize, usize)> = Vec::new();
+      for &p in chain.iter() {
+        let n = points.len();
+        if n >= 2 && (points[n - 2].0 == p.0) == (points[n - 1].0 == p.0) &&
+          (points[n - 2].1 == p.1) == (points[n - 1].1 == p.1) {
+          points[n - 1] = p;
+        } else {
+          points.push(p);
+        }
+      }
+      points.iter().map(|&(x, y)| (x as f64 * cell_size, y as f64 * cell_size)).collect()
+    }).collect()
+  }
+
+  /// The maze as a TikZ picture for dropping into a LaTeX document, every
+  /// wall its own \draw and the doors left open. TikZ's y axis points up,
+  /// so row 0 ends up at the top as usual.
+  pub fn to_latex_tikz(&self, cell_size_cm: f64) -> String {
+    let mut tikz = String::from("\\begin{tikzpicture}\n");
+    tikz.push_str(&self.tikz_walls(cell_size_cm));
+    tikz.push_str("\\end{tikzpicture}\n");
+    tikz
+  }
+
+  /// The same, with `path` drawn through the cell centres in red
+  pub fn to_latex_tikz_with_solution(&self, path: &Path, cell_size_cm: f64) -> String {
+    let mut tikz = String::from("\\begin{tikzpicture}\n");
+    tikz.push_str(&self.tikz_walls(cell_size_cm));
+    if !path.is_empty() {
+      let coords: Vec<String> = path.iter().map(|pt| {
+        self.tikz_coord(pt.x as f64 + 0.5, pt.y as f64 + 0.5, cell_size_cm)
+      }).collect();
+      tikz.push_str(&format!("  \\draw[red, thick] {};\n", coords.join(" -- ")));
+    }
+    tikz.push_str("\\end{tikzpicture}\n");
+    tikz
+  }
+
+  fn tikz_walls(&self, cell_size_cm: f64) -> String {
+    self.wall_lines().iter().map(|&((x1, y1), (x2, y2))| {
+      format!("  \\draw {} -- {};\n",
+              self.tikz_coord(x1 as f64, y1 as f64, cell_size_cm),
+              self.tikz_coord(x2 as f64, y2 as f64, cell_size_cm))
+    }).collect()
+  }
+
+  fn tikz_coord(&self, x: f64, y: f64, cell_size_cm: f64) -> String {
+    format!("({},{})", x * cell_size_cm, (self.height as f64 - y) * cell_size_cm)
+  }
+
+  /// The maze as Three.js geometry parameters, for JSON.parse on a web page:
+  ///
+  ///   {"floor": {"type": "PlaneGeometry", "width", "height", "x", "y", "z"},
+  ///    "walls": [{"type": "BoxGeometry", "x", "y", "z", "w", "h", "d"}, ...]}
+  ///
+  /// Three.js puts y up, so the maze lies in the x-z plane with row 0 at z = 0.
+  /// Every (x, y, z) is the centre of its mesh. A PlaneGeometry is made in the
+  /// x-y plane, so the floor wants rotating by -PI/2 about x to lie flat. The
+  /// walls are 1 unit thick and `wall_height` tall, each one a touch longer
+  /// than its run of cells so they meet at the corners.
+  pub fn to_three_js_json(&self, cell_size: f32, wall_height: f32) -> String {
+    let (w, d) = (self.width as f32 * cell_size, self.height as f32 * cell_size);
+    let floor = format!("{{\"type\": \"PlaneGeometry\", \"width\": {}, \"height\": {}, \"x\": {}, \"y\": 0, \"z\": {}}}",
+                        w, d, w / 2.0, d / 2.0);
+    let walls: Vec<String> = self.wall_lines().into_iter().map(|((x1, y1), (x2, y2))| {
+      let (x1, z1, x2, z2) = (x1 as f32 * cell_size, y1 as f32 * cell_size, x2 as f32 * cell_size, y2 as f32 * cell_size);
+      format!("{{\"type\": \"BoxGeometry\", \"x\": {}, \"y\": {}, \"z\": {}, \"w\": {}, \"h\": {}, \"d\": {}}}",
+              (x1 + x2) / 2.0, wall_height / 2.0, (z1 + z2) / 2.0,
+              x2 - x1 + 1.0, wall_height, z2 - z1 + 1.0)
+    }).collect();
+    format!("{{\"floor\": {}, \"walls\": [{}]}}", floor, walls.join(", "))
+  }
+}
+
+// The line, in cell-corner coordinates, that the wall on one side of a cell
+// is drawn along
+fn wall_line(point: Point, dir: Dir) -> ((usize, usize), (usize, usize)) {
+  let (x, y) = (point.x, point.y);
+  match dir {
+    Dir::North => ((x, y), (x + 1, y)),
+    Dir::South => ((x, y + 1), (x + 1, y + 1)),
+    Dir::East => ((x + 1, y), (x + 1, y + 1)),
+    Dir::West => ((x, y), (x, y + 1))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn maze_animated_generation_svg_test() {
+    let carves = [(Point { x: 0, y: 0 }, Dir::East), (Point { x: 1, y: 0 }, Dir::South),
+                  (Point { x: 0, y: 1 }, Dir::East)];
+    let states = GenerationState::replay(2, 2, &carves).expect("");
+    assert_eq!(states.len(), 4);
+    assert!(GenerationState::replay(2, 2, &[(Point { x: 1, y: 0 }, Dir::East)]).is_err());
+
+    let svg = Maze::to_svg_animated_generation(&states, 10, 250);
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"20\" height=\"20\""));
+    assert!(svg.contains("<line x1=\"10\" y1=\"0\" x2=\"10\" y2=\"10\" stroke=\"black\" stroke-width=\"2\">\
+                          <animate attributeName=\"visibility\" values=\"visible;hidden;hidden;hidden\" \
+                          dur=\"1000ms\" calcMode=\"discrete\" fill=\"freeze\"/></line>"));
+    // The one wall never carved stays up throughout
+    assert!(svg.contains("values=\"visible;visible;visible;visible\""));
+    assert!(svg.contains("stroke=\"red\" stroke-width=\"2\"><animate attributeName=\"visibility\" \
+                          values=\"hidden;visible;hidden;hidden\""));
+    assert_eq!(svg.matches("<line").count(), 7);
+  }
+
+  #[test]
+  fn maze_svg_dual_graph_test() {
+    let mut m = Maze::new(2, 1).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    assert_eq!(m.wall_lines(), vec![((0, 0), (1, 0)), ((0, 0), (0, 1)), ((0, 1), (1, 1)),
+                                    ((1, 0), (2, 0)), ((2, 0), (2, 1)), ((1, 1), (2, 1))]);
+
+    let svg = m.to_svg_dual_graph(20);
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"40\" height=\"20\""));
+    assert!(svg.contains("<line x1=\"10\" y1=\"10\" x2=\"30\" y2=\"10\" stroke=\"blue\" stroke-width=\"2\"/>"));
+    assert!(svg.contains("<circle cx=\"30\" cy=\"10\" r=\"2\" fill=\"blue\"/>"));
+    assert_eq!(svg.matches("<circle").count(), 2);
+    assert_eq!(svg.matches("stroke=\"black\"").count(), 0);
+    assert_eq!(m.to_svg_dual_graph_with_walls(20).matches("stroke=\"black\"").count(), 6);
+  }
+
+  #[test]
+  fn maze_polybool_paths_test() {
+    // With every wall up, the boundary is one loop and the inside a grid
+    let m = Maze::new(1, 1).expect("");
+    assert_eq!(m.to_polybool_paths(10.0),
+               vec![vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0), (0.0, 0.0)]]);
+
+    let mut m = Maze::new(2, 2).expect("");
+    let all: Vec<Point> = m.iter().collect();
+    m.carve_all_passages_in(&all);
+    m.uncarve(Point { x: 0, y: 0 }, Dir::South).expect("");
+    let paths = m.to_polybool_paths(1.0);
+    assert_eq!(paths, vec![vec![(0.0, 1.0), (0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0), (0.0, 1.0)],
+                           vec![(0.0, 1.0), (1.0, 1.0)]]);
+
+    let m = Maze::new_seeded_square(12, 3).expect("");
+    let paths = m.to_polybool_paths(1.0);
+    let segments: usize = paths.iter().map(|p| p.len() - 1).sum();
+    assert!(segments < m.wall_lines().len());
+    assert!(paths.len() * 2 < m.wall_lines().len());
+  }
+
+  #[test]
+  fn latex_tikz_test() {
+    let mut m = Maze::new(2, 2).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::South).expect("");
+    m.carve(Point { x: 1, y: 1 }, Dir::West).expect("");
+
+    let tikz = m.to_latex_tikz(1.0);
+    assert_eq!(tikz, "\\begin{tikzpicture}\n  \\draw (0,2) -- (1,2);\n  \\draw (0,2) -- (0,1);\n  \\draw (0,1) -- (1,1);\n  \
+                      \\draw (1,2) -- (2,2);\n  \\draw (2,2) -- (2,1);\n  \\draw (0,1) -- (0,0);\n  \\draw (0,0) -- (1,0);\n  \
+                      \\draw (2,1) -- (2,0);\n  \\draw (1,0) -- (2,0);\n\\end{tikzpicture}\n");
+    // The doors show up as gaps in the boundary
+    m.open_entrance(Point { x: 0, y: 0 }, Dir::West).expect("");
+    m.open_exit(Point { x: 1, y: 1 }, Dir::South).expect("");
+    let opened = m.to_latex_tikz(1.0);
+    assert!(!opened.contains("(0,2) -- (0,1)") && !opened.contains("(1,0) -- (2,0)"));
+    assert_eq!(opened.matches("\\draw").count(), 7);
+
+    let path = m.solve_iter(Point { x: 0, y: 0 }, Point { x: 0, y: 1 }).last().expect("").path.expect("");
+    let solved = m.to_latex_tikz_with_solution(&path, 0.5);
+    assert!(solved.starts_with("\\begin{tikzpicture}\n  \\draw (0,1) -- (0.5,1);\n"));
+    assert!(solved.contains("  \\draw[red, thick] (0.25,0.75) -- (0.75,0.75) -- (0.75,0.25) -- (0.25,0.25);\n"));
+    assert!(solved.ends_with("\\end{tikzpicture}\n"));
+  }
+
+  #[test]
+  fn three_js_json_test() {
+    let mut m = Maze::new(2, 2).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::South).expect("");
+    m.carve(Point { x: 1, y: 1 }, Dir::West).expect("");
+
+    let box_at = |x: f32, z: f32, w: f32, d: f32| {
+      format!("{{\"type\": \"BoxGeometry\", \"x\": {}, \"y\": 1, \"z\": {}, \"w\": {}, \"h\": 2, \"d\": {}}}", x, z, w, d)
+    };
+    let walls = [
+      box_at(5.0, 0.0, 11.0, 1.0), box_at(0.0, 5.0, 1.0, 11.0), box_at(5.0, 10.0, 11.0, 1.0),
+      box_at(15.0, 0.0, 11.0, 1.0), box_at(20.0, 5.0, 1.0, 11.0),
+      box_at(0.0, 15.0, 1.0, 11.0), box_at(5.0, 20.0, 11.0, 1.0),
+      box_at(20.0, 15.0, 1.0, 11.0), box_at(15.0, 20.0, 11.0, 1.0),
+    ];
+    let expected = format!("{{\"floor\": {{\"type\": \"PlaneGeometry\", \"width\": 20, \"height\": 20, \"x\": 10, \"y\": 0, \"z\": 10}}, \"walls\": [{}]}}",
+                           walls.join(", "));
+    assert_eq!(m.to_three_js_json(10.0, 2.0), expected);
+  }
+
+  #[test]
+  fn maze_to_svg_test() {
+    let mut m = Maze::new(2, 1).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    assert_eq!(m.to_svg(&SvgOptions::default()),
+               "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"42\" height=\"22\" viewBox=\"-1 -1 42 22\">\n\
+                <rect x=\"-1\" y=\"-1\" width=\"42\" height=\"22\" fill=\"white\"/>\n\
+                <path d=\"M 0 0 L 40 0 L 40 20 L 0 20 L 0 0\" fill=\"none\" stroke=\"black\" stroke-width=\"2\" \
+                stroke-linecap=\"square\"/>\n</svg>\n");
+
+    let options = SvgOptions { cell_size: 10, stroke_width: 1.0, stroke: "#336".to_string(), background: None };
+    let svg = Maze::new_seeded_square(5, 2).expect("").to_svg(&options);
+    assert!(svg.contains("viewBox=\"-0.5 -0.5 51 51\""));
+    assert!(svg.contains("stroke=\"#336\" stroke-width=\"1\""));
+    assert!(!svg.contains("<rect"));
+  }
+
+  #[test]
+  fn maze_svg_heatmap_test() {
+    // A corridor along the top, and a cell underneath that can't be reached
+    let mut m = Maze::new(3, 2).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::East).expect("");
+    let heatmap = Heatmap::new(Point { x: 0, y: 0 });
+    let colors = heatmap.colors(&m);
+    assert_eq!(colors[Point { x: 0, y: 0 }], Some(heatmap.near));
+    assert_eq!(colors[Point { x: 1, y: 0 }], Some([222, 128, 121]));
+    assert_eq!(colors[Point { x: 2, y: 0 }], Some(heatmap.far));
+    assert_eq!(colors[Point { x: 0, y: 1 }], None);
+
+    let svg = m.to_svg_heatmap(&SvgOptions::default(), &heatmap);
+    assert_eq!(svg.matches("<rect").count(), 4);
+    assert!(svg.contains("<rect x=\"40\" y=\"0\" width=\"20\" height=\"20\" fill=\"#bd0026\"/>"));
+    // Cells go under the walls
+    assert!(svg.find("fill=\"#ffffcc\"") < svg.find("<path"));
+  }
+
+  #[test]
+  fn maze_svg_with_data_test() {
+    let m = Maze::new(2, 1).expect("");
+    let mut marks = m.cell_data(None);
+    marks[Point { x: 1, y: 0 }] = Some('<');
+    let svg = m.to_svg_with_data(&SvgOptions::default(), &marks, |&mark| mark);
+    assert!(svg.starts_with(&m.to_svg(&SvgOptions::default()).replace("</svg>\n", "")));
+    assert!(svg.ends_with("<text x=\"30\" y=\"10\" font-size=\"14\" text-anchor=\"middle\" \
+                           dominant-baseline=\"central\">&lt;</text>\n</svg>\n"));
+    assert_eq!(svg.matches("<text").count(), 1);
+  }
+
+  #[test]
+  fn maze_svg_with_solution_test() {
+    let m = Maze::new_open(3, 2).expect("");
+    let p = |x, y| Point { x, y };
+    let svg = m.to_svg_with_solution(&SvgOptions::default(), &[p(0, 0), p(1, 0), p(1, 1), p(2, 0)]);
+    assert!(svg.ends_with("<path d=\"M 10 10 L 30 10 L 30 30 M 50 10\" fill=\"none\" stroke=\"red\" stroke-width=\"3\" \
+                           stroke-linecap=\"round\" stroke-linejoin=\"round\"/>\n</svg>\n"));
+    // Over the walls
+    assert!(svg.find("stroke=\"black\"") < svg.find("stroke=\"red\""));
+    assert_eq!(m.to_svg_with_solution(&SvgOptions::default(), &[]), m.to_svg(&SvgOptions::default()));
+  }
+}
@@ -0,0 +1,665 @@
+//! Reading and writing mazes as text, and as bytes.
+
+use std::convert::TryInto;
+use crate::bits::BitVec;
+use crate::{Dir, Mask, Maze, ParseError, Point, Topology};
+#[cfg(any(feature = "toml", feature = "serde"))]
+use crate::Algorithm;
+
+const MAGIC: &[u8; 4] = b"MAZE";
+const BYTES_VERSION: u8 = 1;
+// Set in the topology byte of to_bytes when the mask follows the walls
+const BYTES_MASKED: u8 = 0x80;
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+impl Maze {
+  /// A short text form of the maze for URLs, config files and the like:
+  /// "{width}x{height}:{walls}", where walls is east_walls then south_walls,
+  /// bit-packed and encoded as URL-safe base64 without padding. A maze whose
+  /// edges wrap has its topology's name after the size, as in
+  /// "{width}x{height}-Torus:{walls}", and a masked maze has its mask's
+  /// cells, row by row and packed the same way, after another ":".
+  pub fn to_string_compact(&self) -> String {
+    let mut s = match self.topology {
+      Topology::Plane => format!("{}x{}:{}", self.width, self.height, self.packed_walls()),
+      topology => format!("{}x{}-{}:{}", self.width, self.height, topology.name(), self.packed_walls())
+    };
+    if let Some(mask) = self.packed_mask() {
+      s.push(':');
+      s.push_str(&encode_base64(&mask));
+    }
+    s
+  }
+
+  /// Reads back a maze written by to_string_compact
+  pub fn from_compact_str(s: &str) -> Result<Maze, ParseError> {
+    let (size, data) = s.trim().split_once(':').ok_or(ParseError::Format)?;
+    let (data, mask) = match data.split_once(':') {
+      Some((data, mask)) => (data, Some(decode_base64(mask).ok_or(ParseError::Format)?)),
+      None => (data, None)
+    };
+    let (size, topology) = match size.split_once('-') {
+      Some((size, name)) => (size, Topology::from_name(name).ok_or(ParseError::Format)?),
+      None => (size, Topology::Plane)
+    };
+    let (width, height) = size.split_once('x').ok_or(ParseError::Format)?;
+    let width = width.parse().map_err(|_| ParseError::Format)?;
+    let height = height.parse().map_err(|_| ParseError::Format)?;
+    // As in from_bytes, the size is checked against the walls given before
+    // making the maze
+    let edges = wall_count(width, height, topology).ok_or(ParseError::Dimensions)?;
+    if decode_base64(data).ok_or(ParseError::Format)?.len() != edges.div_ceil(8) {
+      return Err(ParseError::Dimensions)
+    }
+    let mut maze = Maze::with_topology(width, height, topology).map_err(|_| ParseError::Dimensions)?;
+    maze.set_packed_walls(data)?;
+    if let Some(mask) = mask { maze.set_packed_mask(&mask)? }
+    Ok(maze)
+  }
+
+  /// Reads a maze back from the text print and render_ascii draw: a "+" at
+  /// every corner, "-" or "|" for a wall and a space for a passage, and
+  /// anything at all inside the cells, so labelled renders read back too.
+  /// The border must be all wall. An unexpected character is reported by
+  /// its line and its position along the line, both counting from 0.
+  pub fn parse(text: &str) -> Result<Maze, ParseError> {
+    let mut lines: Vec<Vec<char>> = text.lines().map(|line| line.trim_end().chars().collect()).collect();
+    while lines.last().is_some_and(|line| line.is_empty()) { lines.pop(); }
+    let across = lines.first().map_or(0, |line| line.len());
+    if lines.len() < 3 || lines.len().is_multiple_of(2) || across < 3 || across.is_multiple_of(2) ||
+      lines.iter().any(|line| line.len() != across) {
+      return Err(ParseError::Dimensions)
+    }
+
+    let (width, height) = ((across - 1) / 2, (lines.len() - 1) / 2);
+    let mut maze = Maze::new(width, height).map_err(|_| ParseError::Dimensions)?;
+    for (row, line) in lines.iter().enumerate() {
+      for (col, &c) in line.iter().enumerate() {
+        let (x, y) = (col / 2, row / 2);
+        // What a wall here looks like, and which passage a space here
+        // would be; the border has to be wall
+        let (wall, passage) = match (row % 2, col % 2) {
+          (0, 0) => ('+', None),
+          (0, _) if row > 0 && y < height => ('-', Some((Point { x, y: y - 1 }, Dir::South))),
+          (0, _) => ('-', None),
+          (_, 0) if col > 0 && x < width => ('|', Some((Point { x: x - 1, y }, Dir::East))),
+          (_, 0) => ('|', None),
+          _ => continue
+        };
+        match passage {
+          Some((pt, dir)) if c == ' ' => maze.carve(pt, dir).expect(""),
+          _ if c == wall => {},
+          _ => return Err(ParseError::InvalidValue { row, col })
+        }
+      }
+    }
+    Ok(maze)
+  }
+
+  /// A small binary form for shipping lots of mazes: the bytes "MAZE", a
+  /// version byte (1), the topology's position in Topology::ALL, width and
+  /// height as big-endian u32s, then east_walls and south_walls bit-packed
+  /// as in to_string_compact. A masked maze has the top bit of the topology
+  /// byte set and its mask's cells packed after the walls.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(BYTES_VERSION);
+    let topology = Topology::ALL.iter().position(|&t| t == self.topology).expect("") as u8;
+    bytes.push(if self.mask.is_some() { topology | BYTES_MASKED } else { topology });
+    bytes.extend_from_slice(&(self.width as u32).to_be_bytes());
+    bytes.extend_from_slice(&(self.height as u32).to_be_bytes());
+    let walls: Vec<bool> = self.east_walls.iter().chain(self.south_walls.iter()).collect();
+    bytes.extend(pack_bits(&walls));
+    bytes.extend(self.packed_mask().unwrap_or_default());
+    bytes
+  }
+
+  /// Reads back a maze written by to_bytes
+  pub fn from_bytes(bytes: &[u8]) -> Result<Maze, ParseError> {
+    if bytes.len() < 14 || &bytes[0..4] != MAGIC { return Err(ParseError::Format) }
+    if bytes[4] != BYTES_VERSION { return Err(ParseError::UnsupportedVersion(bytes[4])) }
+    let masked = bytes[5] & BYTES_MASKED != 0;
+    let topology = *Topology::ALL.get((bytes[5] & !BYTES_MASKED) as usize).ok_or(ParseError::Format)?;
+    let size = |at: usize| u32::from_be_bytes(bytes[at..(at + 4)].try_into().expect("")) as usize;
+    let (width, height) = (size(6), size(10));
+    // Checked before making the maze, so a bad header can't ask for more
+    // memory than there is
+    let edges = wall_count(width, height, topology).ok_or(ParseError::Dimensions)?;
+    let cells = if masked { width.checked_mul(height).ok_or(ParseError::Dimensions)? } else { 0 };
+    let walls_end = 14 + edges.div_ceil(8);
+    if bytes.len() - 14 != edges.div_ceil(8) + cells.div_ceil(8) { return Err(ParseError::Dimensions) }
+    let mut maze = Maze::with_topology(width, height, topology).map_err(|_| ParseError::Dimensions)?;
+    let walls = unpack_bits(&bytes[14..walls_end], edges);
+    let (east, south) = walls.split_at(maze.east_walls.len());
+    maze.east_walls = BitVec::from(east);
+    maze.south_walls = BitVec::from(south);
+    if masked { maze.set_packed_mask(&bytes[walls_end..])? }
+    Ok(maze)
+  }
+
+  // East walls then south walls, bit-packed and in base64
+  fn packed_walls(&self) -> String {
+    let walls: Vec<bool> = self.east_walls.iter().chain(self.south_walls.iter()).collect();
+    encode_base64(&pack_bits(&walls))
+  }
+
+  fn set_packed_walls(&mut self, data: &str) -> Result<(), ParseError> {
+    let edges = self.east_walls.len() + self.south_walls.len();
+    let bytes = decode_base64(data).ok_or(ParseError::Format)?;
+    if bytes.len() != edges.div_ceil(8) {
+      return Err(ParseError::Dimensions)
+    }
+
+    let walls = unpack_bits(&bytes, edges);
+    let (east, south) = walls.split_at(self.east_walls.len());
+    self.east_walls = BitVec::from(east);
+    self.south_walls = BitVec::from(south);
+    Ok(())
+  }
+
+  // The mask's cells in row-major order, bit-packed like the walls, if
+  // there's a mask
+  fn packed_mask(&self) -> Option<Vec<u8>> {
+    self.mask.as_ref().map(|mask| pack_bits(&mask.cells))
+  }
+
+  fn set_packed_mask(&mut self, bytes: &[u8]) -> Result<(), ParseError> {
+    let cells = self.width * self.height;
+    if bytes.len() != cells.div_ceil(8) { return Err(ParseError::Dimensions) }
+    self.set_loaded_mask(Mask { width: self.width, height: self.height, cells: unpack_bits(bytes, cells) })
+  }
+
+  // A mask read back with the maze, held to the same rules as new_masked's
+  fn set_loaded_mask(&mut self, mask: Mask) -> Result<(), ParseError> {
+    if (mask.width, mask.height) != (self.width, self.height) { return Err(ParseError::Dimensions) }
+    if mask.count() == 0 || !mask.is_connected() { return Err(ParseError::Mask) }
+    self.mask = Some(mask);
+    Ok(())
+  }
+
+  /// The passage graph in NetworkX's node-link format, so from Python
+  /// `nx.node_link_graph(json.loads(s))` rebuilds it (NetworkX 3.4 and up
+  /// also want `edges="links"`). Nodes are named "x_y", and cells a mask
+  /// has off are left out.
+  pub fn to_networkx_json(&self) -> String {
+    let nodes: Vec<String> = self.iter().filter(|&pt| self.in_maze(pt)).map(|pt| format!("{{\"id\": \"{}_{}\"}}", pt.x, pt.y)).collect();
+    let links: Vec<String> = self.interior_edges().into_iter()
+      .filter(|&(pt, dir)| self.passage(pt, dir))
+      .map(|(pt, dir)| {
+        let n = self.neighbor(pt, dir).expect("");
+        format!("{{\"source\": \"{}_{}\", \"target\": \"{}_{}\"}}", pt.x, pt.y, n.x, n.y)
+      })
+      .collect();
+    format!("{{\"directed\": false, \"multigraph\": false, \"graph\": {{}}, \"nodes\": [{}], \"links\": [{}]}}",
+            nodes.join(", "), links.join(", "))
+  }
+
+  /// The same graph in NetworkX's adjacency format, for `nx.adjacency_graph`
+  pub fn to_networkx_adjacency_json(&self) -> String {
+    let cells: Vec<Point> = self.iter().filter(|&pt| self.in_maze(pt)).collect();
+    let nodes: Vec<String> = cells.iter().map(|pt| format!("{{\"id\": \"{}_{}\"}}", pt.x, pt.y)).collect();
+    let adjacency: Vec<String> = cells.iter().map(|&pt| {
+      let ns: Vec<String> = self.open_neighbors(pt).iter()
+        .map(|n| format!("{{\"id\": \"{}_{}\"}}", n.x, n.y))
+        .collect();
+      format!("[{}]", ns.join(", "))
+    }).collect();
+    format!("{{\"directed\": false, \"multigraph\": false, \"graph\": [], \"nodes\": [{}], \"adjacency\": [{}]}}",
+            nodes.join(", "), adjacency.join(", "))
+  }
+
+  /// The passage graph in Graphviz's DOT language: a node per cell, named
+  /// "x_y" like the NetworkX formats, and an edge per passage. Each node is
+  /// pinned where its cell is, so `neato -n` draws it as the maze, and
+  /// `dot` lays a perfect maze out as the tree it is.
+  pub fn to_dot(&self) -> String {
+    let mut dot = String::from("graph maze {\n  node [shape=point];\n");
+    for pt in self.iter().filter(|&pt| self.in_maze(pt)) {
+      dot.push_str(&format!("  \"{}_{}\" [pos=\"{},{}\"];\n", pt.x, pt.y, pt.x * 36, (self.height - 1 - pt.y) * 36));
+    }
+    for (pt, dir) in self.interior_edges() {
+      if !self.passage(pt, dir) { continue }
+      let n = self.neighbor(pt, dir).expect("");
+      dot.push_str(&format!("  \"{}_{}\" -- \"{}_{}\";\n", pt.x, pt.y, n.x, n.y));
+    }
+    dot.push_str("}\n");
+    dot
+  }
+
+  /// The maze as TOML, meant for editing by hand:
+  ///
+  ///   [maze]
+  ///   width = 2
+  ///   height = 2
+  ///   algorithm = "RecursiveBacktracker"
+  ///
+  ///   [maze.walls]
+  ///   east = [true, false]
+  ///   south = [false, true]
+  ///
+  /// with `algorithm` left out if the maze wasn't generated. A masked maze
+  /// also gets `mask = ["..X", ".XX"]`, a row per string drawn as for
+  /// Mask::from_text.
+  #[cfg(feature = "toml")]
+  pub fn to_toml_string(&self) -> String {
+    use toml::Value;
+    let bools = |walls: &BitVec| Value::Array(walls.iter().map(Value::Boolean).collect());
+
+    let mut walls = toml::map::Map::new();
+    walls.insert("east".to_string(), bools(&self.east_walls));
+    walls.insert("south".to_string(), bools(&self.south_walls));
+
+    let mut maze = toml::map::Map::new();
+    maze.insert("width".to_string(), Value::Integer(self.width as i64));
+    maze.insert("height".to_string(), Value::Integer(self.height as i64));
+    if let Some(algorithm) = self.algorithm {
+      maze.insert("algorithm".to_string(), Value::String(algorithm.name().to_string()));
+    }
+    if self.topology != Topology::Plane {
+      maze.insert("topology".to_string(), Value::String(self.topology.name().to_string()));
+    }
+    if let Some(mask) = &self.mask {
+      let rows = (0..self.height).map(|y| {
+        Value::String((0..self.width).map(|x| if mask.get(Point { x, y }) { '.' } else { 'X' }).collect())
+      });
+      maze.insert("mask".to_string(), Value::Array(rows.collect()));
+    }
+    maze.insert("walls".to_string(), Value::Table(walls));
+
+    let mut root = toml::map::Map::new();
+    root.insert("maze".to_string(), Value::Table(maze));
+    toml::to_string(&Value::Table(root)).expect("")
+  }
+
+  /// Reads back a maze written by to_toml_string
+  #[cfg(feature = "toml")]
+  pub fn from_toml_str(s: &str) -> Result<Maze, ParseError> {
+    let root: toml::Value = s.parse().map_err(|_| ParseError::Format)?;
+    let maze = root.get("maze").ok_or(ParseError::Format)?;
+    let size = |key: &str| maze.get(key).and_then(|v| v.as_integer()).filter(|&n| n >= 0).ok_or(ParseError::Format);
+    let walls = |key: &str| -> Result<Vec<bool>, ParseError> {
+      maze.get("walls").and_then(|w| w.get(key)).and_then(|v| v.as_array()).ok_or(ParseError::Format)?
+        .iter().map(|v| v.as_bool().ok_or(ParseError::Format)).collect()
+    };
+
+    let topology = match maze.get("topology") {
+      Some(name) => name.as_str().and_then(Topology::from_name).ok_or(ParseError::Format)?,
+      None => Topology::Plane
+    };
+    let (width, height) = (size("width")? as usize, size("height")? as usize);
+    let (east, south) = (walls("east")?, walls("south")?);
+    // The walls given have to add up before the maze is made, so a huge size
+    // is turned down rather than allocated
+    if wall_count(width, height, topology) != Some(east.len() + south.len()) {
+      return Err(ParseError::Dimensions)
+    }
+    let mut m = Maze::with_topology(width, height, topology).map_err(|_| ParseError::Dimensions)?;
+    if east.len() != m.east_walls.len() || south.len() != m.south_walls.len() {
+      return Err(ParseError::Dimensions)
+    }
+    m.east_walls = BitVec::from(east);
+    m.south_walls = BitVec::from(south);
+    m.algorithm = match maze.get("algorithm") {
+      Some(name) => Some(name.as_str().and_then(Algorithm::from_name).ok_or(ParseError::Format)?),
+      None => None
+    };
+    if let Some(rows) = maze.get("mask") {
+      let rows: Vec<&str> = rows.as_array().ok_or(ParseError::Format)?
+        .iter().map(|row| row.as_str().ok_or(ParseError::Format)).collect::<Result<_, _>>()?;
+      m.set_loaded_mask(Mask::from_text(&rows.join("\n"))?)?;
+    }
+    Ok(m)
+  }
+}
+
+// What a Maze looks like to serde
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct MazeRepr {
+  width: usize,
+  height: usize,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  topology: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  algorithm: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  entrance: Option<Point>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  exit: Option<Point>,
+  walls: String,
+  // The mask's cells packed like the walls, if there is one
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  mask: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+impl From<Maze> for MazeRepr {
+  fn from(maze: Maze) -> MazeRepr {
+    MazeRepr {
+      width: maze.width,
+      height: maze.height,
+      topology: Some(maze.topology).filter(|&t| t != Topology::Plane).map(|t| t.name().to_string()),
+      algorithm: maze.algorithm.map(|a| a.name().to_string()),
+      entrance: maze.entrance,
+      exit: maze.exit,
+      walls: maze.packed_walls(),
+      mask: maze.packed_mask().map(|mask| encode_base64(&mask)),
+    }
+  }
+}
+
+#[cfg(feature = "serde")]
+impl std::convert::TryFrom<MazeRepr> for Maze {
+  type Error = ParseError;
+  fn try_from(repr: MazeRepr) -> Result<Maze, ParseError> {
+    let topology = match repr.topology {
+      Some(name) => Topology::from_name(&name).ok_or(ParseError::Format)?,
+      None => Topology::Plane
+    };
+    // Checked against the walls given before making the maze, like from_bytes
+    let edges = wall_count(repr.width, repr.height, topology).ok_or(ParseError::Dimensions)?;
+    if decode_base64(&repr.walls).ok_or(ParseError::Format)?.len() != edges.div_ceil(8) {
+      return Err(ParseError::Dimensions)
+    }
+    let mut maze = Maze::with_topology(repr.width, repr.height, topology).map_err(|_| ParseError::Dimensions)?;
+    maze.set_packed_walls(&repr.walls)?;
+    if let Some(mask) = repr.mask { maze.set_packed_mask(&decode_base64(&mask).ok_or(ParseError::Format)?)? }
+    maze.algorithm = match repr.algorithm {
+      Some(name) => Some(Algorithm::from_name(&name).ok_or(ParseError::Format)?),
+      None => None
+    };
+    if let Some(entrance) = repr.entrance { maze.set_entrance(entrance).map_err(|_| ParseError::Dimensions)? }
+    if let Some(exit) = repr.exit { maze.set_exit(exit).map_err(|_| ParseError::Dimensions)? }
+    Ok(maze)
+  }
+}
+
+// How many walls a maze of this size and topology keeps, east ones and
+// south ones together, without making it; None if it couldn't be made
+fn wall_count(width: usize, height: usize, topology: Topology) -> Option<usize> {
+  let east_stride = if topology == Topology::Plane { width.checked_sub(1)? } else { width };
+  let south_rows = if topology == Topology::Torus { height } else { height.checked_sub(1)? };
+  east_stride.checked_mul(height)?.checked_add(width.checked_mul(south_rows)?)
+}
+
+// Packs bools into bytes, eight to a byte, first bool in the high bit
+fn pack_bits(bits: &[bool]) -> Vec<u8> {
+  bits.chunks(8).map(|chunk| {
+    chunk.iter().enumerate().fold(0, |byte, (i, &bit)| if bit { byte | (0x80 >> i) } else { byte })
+  }).collect()
+}
+
+fn unpack_bits(bytes: &[u8], count: usize) -> Vec<bool> {
+  (0..count).map(|i| bytes[i / 8] & (0x80 >> (i % 8)) != 0).collect()
+}
+
+// URL-safe base64, without padding
+fn encode_base64(bytes: &[u8]) -> String {
+  let mut out = String::new();
+  for chunk in bytes.chunks(3) {
+    let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+    for i in 0..(chunk.len() + 1) {
+      out.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+    }
+  }
+  out
+}
+
+fn decode_base64(s: &str) -> Option<Vec<u8>> {
+  let mut out = Vec::new();
+  for chunk in s.as_bytes().chunks(4) {
+    if chunk.len() == 1 { return None }
+    let mut n = 0u32;
+    for (i, c) in chunk.iter().enumerate() {
+      let value = BASE64_ALPHABET.iter().position(|a| a == c)? as u32;
+      n |= value << (18 - 6 * i);
+    }
+    for i in 0..(chunk.len() - 1) {
+      out.push((n >> (16 - 8 * i)) as u8);
+    }
+  }
+  Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[cfg(feature = "serde")]
+  use crate::Cell;
+  use rand::rngs::StdRng;
+  use rand::SeedableRng;
+
+  #[test]
+  fn base64_test() {
+    assert_eq!(encode_base64(b"Man"), "TWFu");
+    assert_eq!(encode_base64(b"Ma"), "TWE");
+    assert_eq!(encode_base64(&[0xfb, 0xff]), "-_8");
+    assert_eq!(decode_base64("TWFu"), Some(b"Man".to_vec()));
+    assert_eq!(decode_base64("-_8"), Some(vec![0xfb, 0xff]));
+    assert_eq!(decode_base64("TWFuT"), None);
+    assert_eq!(decode_base64("TW=u"), None);
+  }
+
+  #[test]
+  fn maze_compact_string_test() {
+    for &size in [5, 10, 100].iter() {
+      let mut m = Maze::new(size, size).expect("");
+      m.binary_tree(&mut StdRng::seed_from_u64(0));
+      let s = m.to_string_compact();
+      let m2 = Maze::from_compact_str(&s).expect("");
+      assert_eq!(m2.to_string_compact(), s);
+      assert_eq!(m2.east_walls, m.east_walls);
+      assert_eq!(m2.south_walls, m.south_walls);
+      if size == 10 { assert!(s.len() < 50) }
+    }
+
+    assert_eq!(Maze::from_compact_str("2x2").err(), Some(ParseError::Format));
+    assert_eq!(Maze::from_compact_str("2y2:AA").err(), Some(ParseError::Format));
+    assert_eq!(Maze::from_compact_str("0x2:AA").err(), Some(ParseError::Dimensions));
+    assert_eq!(Maze::from_compact_str("2x2:AAAA").err(), Some(ParseError::Dimensions));
+    assert_eq!(Maze::from_compact_str("2x2:A!").err(), Some(ParseError::Format));
+    assert!(Maze::from_compact_str("2x2:8A").is_ok());
+
+    for &topology in Topology::ALL.iter() {
+      let mut m = Maze::with_topology(6, 4, topology).expect("");
+      m.recursive_backtracker(&mut StdRng::seed_from_u64(1));
+      let s = m.to_string_compact();
+      assert_eq!(s.starts_with("6x4:"), topology == Topology::Plane);
+      let back = Maze::from_compact_str(&s).expect("");
+      assert_eq!(back.topology(), topology);
+      assert_eq!((back.east_walls, back.south_walls), (m.east_walls, m.south_walls));
+    }
+    assert_eq!(Maze::from_compact_str("3x2-Torus:8A").err(), Some(ParseError::Dimensions));
+    assert_eq!(Maze::from_compact_str("2x2-Klein:8A").err(), Some(ParseError::Format));
+    assert_eq!(Maze::from_compact_str("1x2-Cylinder:").err(), Some(ParseError::Dimensions));
+    assert_eq!(Maze::from_compact_str("99999999x99999999:AA").err(), Some(ParseError::Dimensions));
+  }
+
+  #[test]
+  fn maze_networkx_json_test() {
+    let mut m = Maze::new(2, 1).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    assert_eq!(m.to_networkx_json(),
+               "{\"directed\": false, \"multigraph\": false, \"graph\": {}, \
+                \"nodes\": [{\"id\": \"0_0\"}, {\"id\": \"1_0\"}], \
+                \"links\": [{\"source\": \"0_0\", \"target\": \"1_0\"}]}");
+    assert_eq!(m.to_networkx_adjacency_json(),
+               "{\"directed\": false, \"multigraph\": false, \"graph\": [], \
+                \"nodes\": [{\"id\": \"0_0\"}, {\"id\": \"1_0\"}], \
+                \"adjacency\": [[{\"id\": \"1_0\"}], [{\"id\": \"0_0\"}]]}");
+  }
+
+  #[test]
+  fn maze_masked_formats_test() {
+    let mask = Mask::from_text("..X\n...\nX..\n").expect("");
+    let mut m = Maze::new_masked(mask.clone()).expect("");
+    m.recursive_backtracker(&mut StdRng::seed_from_u64(1));
+    let same = |back: Maze| {
+      assert_eq!(back.mask(), Some(&mask));
+      assert_eq!((back.east_walls.clone(), back.south_walls.clone()), (m.east_walls.clone(), m.south_walls.clone()));
+      assert!(back.is_perfect());
+    };
+
+    let s = m.to_string_compact();
+    assert_eq!(s.matches(':').count(), 2);
+    same(Maze::from_compact_str(&s).expect(""));
+    let bytes = m.to_bytes();
+    assert_eq!(bytes[5], BYTES_MASKED);
+    same(Maze::from_bytes(&bytes).expect(""));
+    assert_eq!(Maze::from_bytes(&bytes[..bytes.len() - 1]).err(), Some(ParseError::Dimensions));
+    #[cfg(feature = "toml")]
+    same(Maze::from_toml_str(&m.to_toml_string()).expect(""));
+    #[cfg(feature = "serde")]
+    same(serde_json::from_str(&serde_json::to_string(&m).expect("")).expect(""));
+
+    // A mask read back has to do for new_masked too
+    let (walls, _) = s.rsplit_once(':').expect("");
+    assert_eq!(Maze::from_compact_str(&format!("{}:AAA", walls)).err(), Some(ParseError::Mask));
+    assert_eq!(Maze::from_compact_str(&format!("{}:-w", walls)).err(), Some(ParseError::Dimensions));
+
+    let json = m.to_networkx_json();
+    assert_eq!(json.matches("\"id\"").count(), 7);
+    assert!(!json.contains("\"2_0\"") && !json.contains("\"0_2\""));
+    assert_eq!(m.to_networkx_adjacency_json().matches("\"id\"").count(), 7 + 12);
+  }
+
+  #[test]
+  fn maze_dot_test() {
+    let mut m = Maze::new(2, 2).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::South).expect("");
+    assert_eq!(m.to_dot(),
+               "graph maze {\n  node [shape=point];\n\
+                \x20 \"0_0\" [pos=\"0,36\"];\n  \"1_0\" [pos=\"36,36\"];\n\
+                \x20 \"0_1\" [pos=\"0,0\"];\n  \"1_1\" [pos=\"36,0\"];\n\
+                \x20 \"0_0\" -- \"1_0\";\n  \"1_0\" -- \"1_1\";\n}\n");
+
+    // A perfect maze has one edge fewer than it has nodes
+    let tree = Maze::new_seeded_square(6, 3).expect("").to_dot();
+    assert_eq!(tree.matches("[pos=").count(), 36);
+    assert_eq!(tree.matches(" -- ").count(), 35);
+  }
+
+  #[cfg(feature = "toml")]
+  #[test]
+  fn maze_toml_test() {
+    let m = Maze::new_seeded_square(6, 2).expect("");
+    let s = m.to_toml_string();
+    assert!(s.starts_with("[maze]\nwidth = 6\nheight = 6\nalgorithm = \"RecursiveBacktracker\"\n\n[maze.walls]\neast = ["));
+
+    let m2 = Maze::from_toml_str(&s).expect("");
+    assert_eq!(m2.east_walls, m.east_walls);
+    assert_eq!(m2.south_walls, m.south_walls);
+    assert_eq!(m2.algorithm, Some(Algorithm::RecursiveBacktracker));
+
+    let blank = Maze::from_toml_str(&Maze::new(2, 1).expect("").to_toml_string()).expect("");
+    assert_eq!(blank.algorithm, None);
+    assert_eq!(blank.east_walls, vec![true]);
+
+    assert_eq!(Maze::from_toml_str("[maze]\nwidth = 2").err(), Some(ParseError::Format));
+    assert_eq!(Maze::from_toml_str("[maze]\nwidth = 2\nheight = 1\n[maze.walls]\neast = []\nsouth = []").err(),
+               Some(ParseError::Dimensions));
+    assert_eq!(Maze::from_toml_str("[maze]\nwidth = 4294967296\nheight = 4294967296\n\
+                                    [maze.walls]\neast = []\nsouth = []").err(),
+               Some(ParseError::Dimensions));
+    assert_eq!(Maze::from_toml_str("[maze]\nwidth = 2\nheight = 2\n[maze.walls]\neast = [true, true]\nsouth = []").err(),
+               Some(ParseError::Dimensions));
+    assert_eq!(Maze::from_toml_str("[maze]\nwidth = 2\nheight = 1\nalgorithm = \"Magic\"\n\
+                                    [maze.walls]\neast = [true]\nsouth = []").err(),
+               Some(ParseError::Format));
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn maze_serde_test() {
+    let mut m = Maze::new_seeded_square(6, 3).expect("");
+    m.set_entrance(Point { x: 0, y: 0 }).expect("");
+    m.set_exit(Point { x: 5, y: 5 }).expect("");
+    let json = serde_json::to_string(&m).expect("");
+    assert!(json.starts_with("{\"width\":6,\"height\":6,\"algorithm\":\"RecursiveBacktracker\",\"entrance\":{\"x\":0,\"y\":0},"));
+    let m2: Maze = serde_json::from_str(&json).expect("");
+    assert_eq!(m2.to_string_compact(), m.to_string_compact());
+    assert_eq!((m2.entrance, m2.exit, m2.algorithm), (m.entrance, m.exit, m.algorithm));
+
+    let torus = Maze::new_torus(4, 3, &mut StdRng::seed_from_u64(1)).expect("");
+    let back: Maze = serde_json::from_str(&serde_json::to_string(&torus).expect("")).expect("");
+    assert_eq!(back.topology, Topology::Torus);
+    assert_eq!((back.east_walls, back.south_walls), (torus.east_walls, torus.south_walls));
+
+    let plain = serde_json::to_string(&Maze::new(2, 1).expect("")).expect("");
+    assert_eq!(plain, "{\"width\":2,\"height\":1,\"walls\":\"gA\"}");
+    assert!(serde_json::from_str::<Maze>("{\"width\":3,\"height\":1,\"walls\":\"gA\"}").is_ok());
+    assert!(serde_json::from_str::<Maze>("{\"width\":30,\"height\":1,\"walls\":\"gA\"}").is_err());
+    // Too big to make is turned down before anything is allocated
+    assert!(serde_json::from_str::<Maze>("{\"width\":4294967296,\"height\":4294967296,\"walls\":\"gA\"}").is_err());
+    assert!(serde_json::from_str::<Maze>("{\"width\":100000,\"height\":100000,\"walls\":\"gA\"}").is_err());
+    assert!(serde_json::from_str::<Maze>("{\"width\":2,\"height\":1,\"walls\":\"gA\",\"exit\":{\"x\":2,\"y\":0}}").is_err());
+
+    let cell = Cell { north: true, east: false, south: false, west: true };
+    assert_eq!(serde_json::from_str::<Cell>(&serde_json::to_string(&cell).expect("")).expect(""), cell);
+  }
+
+  #[test]
+  fn maze_bytes_test() {
+    let mut m = Maze::new(3, 2).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::South).expect("");
+    // Walls: east 0 1 1 1, south 1 0 1
+    assert_eq!(m.to_bytes(), vec![b'M', b'A', b'Z', b'E', 1, 0, 0, 0, 0, 3, 0, 0, 0, 2, 0b0111_1010]);
+
+    for &topology in Topology::ALL.iter() {
+      let mut m = Maze::with_topology(9, 7, topology).expect("");
+      m.kruskal(&mut StdRng::seed_from_u64(4));
+      let back = Maze::from_bytes(&m.to_bytes()).expect("");
+      assert_eq!(back.topology, topology);
+      assert_eq!((back.east_walls, back.south_walls), (m.east_walls, m.south_walls));
+    }
+
+    let good = m.to_bytes();
+    assert_eq!(Maze::from_bytes(b"MAZ").err(), Some(ParseError::Format));
+    assert_eq!(Maze::from_bytes(&[b"MAZX", &good[4..]].concat()).err(), Some(ParseError::Format));
+    let mut future = good.clone();
+    future[4] = 2;
+    assert_eq!(Maze::from_bytes(&future).err(), Some(ParseError::UnsupportedVersion(2)));
+    assert_eq!(Maze::from_bytes(&good[..14]).err(), Some(ParseError::Dimensions));
+    assert_eq!(Maze::from_bytes(&[&good[..], &[0]].concat()).err(), Some(ParseError::Dimensions));
+    let mut empty = good.clone();
+    empty[9] = 0;
+    assert_eq!(Maze::from_bytes(&empty).err(), Some(ParseError::Dimensions));
+    // A huge maze with none of its walls there is turned down, not made
+    let mut huge = good.clone();
+    huge[6..14].copy_from_slice(&[0, 0xff, 0xff, 0xff, 0, 0xff, 0xff, 0xff]);
+    assert_eq!(Maze::from_bytes(&huge).err(), Some(ParseError::Dimensions));
+    for &topology in Topology::ALL.iter() {
+      let m = Maze::with_topology(5, 3, topology).expect("");
+      assert_eq!(wall_count(5, 3, topology), Some(m.east_walls.len() + m.south_walls.len()));
+    }
+  }
+
+  #[test]
+  fn maze_parse_test() {
+    for seed in 0..3 {
+      let mut m = Maze::new(7, 4).expect("");
+      m.wilsons(&mut StdRng::seed_from_u64(seed));
+      let back = Maze::parse(&m.render_ascii()).expect("");
+      assert_eq!(back.to_string_compact(), m.to_string_compact());
+    }
+
+    // Labels inside cells are fine, and so is a missing final newline
+    let m = Maze::parse("+-+-+\n|a  |\n+-+ +\n| |*|\n+-+-+").expect("");
+    assert_eq!((m.width, m.height), (2, 2));
+    assert!(m.passage(Point { x: 0, y: 0 }, Dir::East));
+    assert!(m.passage(Point { x: 1, y: 0 }, Dir::South));
+    assert_eq!(m.passage_count(), 2);
+
+    assert_eq!(Maze::parse("").err(), Some(ParseError::Dimensions));
+    assert_eq!(Maze::parse("+-+\n| |\n+-+-+\n").err(), Some(ParseError::Dimensions));
+    assert_eq!(Maze::parse("+-+\n| |\n").err(), Some(ParseError::Dimensions));
+    assert_eq!(Maze::parse("+-+-+\n|   |\n+-+ +\n| | \n+-+-+\n").err(), Some(ParseError::Dimensions));
+    assert_eq!(Maze::parse("+-+-+\n|   |\n+-+ +\n| |  \n+-+-+\n").err(), Some(ParseError::Dimensions));
+    assert_eq!(Maze::parse("+-+-+\n|   |\n+-+ +\n| | |\n+-+ +\n").err(), Some(ParseError::InvalidValue { row: 4, col: 3 }));
+    assert_eq!(Maze::parse("+-+-+\n|   |\n+-x +\n| | |\n+-+-+\n").err(), Some(ParseError::InvalidValue { row: 2, col: 2 }));
+    assert_eq!(Maze::parse("+-+-+\n| - |\n+---+\n| | |\n+-+-+\n").err(), Some(ParseError::InvalidValue { row: 1, col: 2 }));
+  }
+}
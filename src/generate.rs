@@ -0,0 +1,1546 @@
+//! Maze generation algorithms.
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashSet};
+use crate::{grid, BoundsError, Cell, Dir, Grid, Mask, Maze, Path, Point, Topology, UnionFind};
+
+// What a generator tells about each thing it does; see generate_observed
+type Observer<'a> = &'a mut dyn FnMut(GenerationEvent);
+
+/// How many cells across each cell of a fractal maze is split into
+pub const FRACTAL_SUBDIVISION: usize = 3;
+
+/// The ways a maze can be generated
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Algorithm {
+  BinaryTree, RecursiveBacktracker, Ellers, Wilsons, Sidewinder, AldousBroder, HuntAndKill, Kruskal,
+  Prim, WeightedPrim, RecursiveDivision, GrowingTree,
+}
+
+/// Eller's algorithm as a stream of rows: only the current row's sets are
+/// kept, so a maze of any height comes out in O(width) memory. Each row's
+/// cells say which ways are open; the rows stack up into a perfect maze.
+/// Without a row limit the stream runs until `finish` closes it off.
+pub struct EllersStream<R: Rng> {
+  width: usize,
+  rng: R,
+  // The set each column of the coming row is in, if it's been joined from above
+  sets: Vec<Option<usize>>,
+  next_set: usize,
+  rows: Option<usize>,
+  emitted: usize,
+  done: bool,
+}
+
+/// Two directions that can't bias a binary tree maze, not being at right
+/// angles to each other
+#[derive(Debug, PartialEq)]
+pub struct BiasError;
+
+/// Which of its active cells the growing tree carves on from next.
+/// Newest makes it the recursive backtracker, Random makes it much like
+/// Prim's, and Oldest makes long straight runs out from the start. Mixed(p)
+/// takes the newest with probability p and a random one otherwise.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GrowingTreeStrategy { Newest, Oldest, Random, Mixed(f64) }
+
+/// Eller's algorithm as an iterator: `height` finished rows, top to bottom,
+/// each a Vec of cells saying which of their sides are open. Only one row is
+/// held at a time, so each can be written out and dropped as it comes.
+pub struct EllerRows<R: Rng> {
+  stream: EllersStream<R>,
+}
+
+/// The maze partway through being generated, and the passage just carved
+#[derive(Debug, Clone)]
+pub struct GenerationState {
+  pub maze: Maze,
+  pub carved: Option<(Point, Dir)>,
+}
+
+/// Something a generator did, for watching it work a step at a time
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GenerationEvent {
+  /// Reached a cell for the first time
+  Visit(Point),
+  /// Knocked down the wall on the `Dir` side of a cell
+  Carve(Point, Dir),
+  /// Put up a wall on the `Dir` side of a cell, as recursive division does
+  Wall(Point, Dir),
+  /// Ran out of ways on from a cell, and went back to the one before it
+  Backtrack(Point),
+}
+
+/// The recursive backtracker as an iterator, carving into the maze as it
+/// goes and saying what it did each step: a Visit for the start, then a
+/// Carve and Visit for each cell it moves into and a Backtrack for each one
+/// it leaves. Run to the end, it makes the same maze from the same rng as
+/// Maze::recursive_backtracker.
+pub struct BacktrackerSteps<'a, R: Rng> {
+  maze: &'a mut Maze,
+  rng: R,
+  visited: Vec<bool>,
+  stack: Vec<Point>,
+  // The cell just carved into, to be visited on the next step
+  arriving: Option<Point>,
+}
+
+impl Maze {
+  /// A cylindrical maze, made with the recursive backtracker
+  pub fn new_cylinder(width: usize, height: usize, rng: &mut impl Rng) -> Result<Maze, BoundsError> {
+    let mut maze = Maze::with_topology(width, height, Topology::Cylinder)?;
+    maze.recursive_backtracker(rng);
+    Ok(maze)
+  }
+
+  /// A maze on a torus, wrapping every way, made with the recursive backtracker
+  pub fn new_torus(width: usize, height: usize, rng: &mut impl Rng) -> Result<Maze, BoundsError> {
+    let mut maze = Maze::with_topology(width, height, Topology::Torus)?;
+    maze.recursive_backtracker(rng);
+    Ok(maze)
+  }
+
+  /// A Mobius strip maze, made with the recursive backtracker
+  pub fn new_mobius(width: usize, height: usize, rng: &mut impl Rng) -> Result<Maze, BoundsError> {
+    let mut maze = Maze::with_topology(width, height, Topology::Mobius)?;
+    maze.recursive_backtracker(rng);
+    Ok(maze)
+  }
+
+  /// The binary tree algorithm: every cell opens either north or east,
+  /// chosen by a coin flip
+  pub fn binary_tree(&mut self, rng: &mut impl Rng) {
+    self.binary_tree_biased(Dir::North, Dir::East, rng).expect("")
+  }
+
+  /// The binary tree algorithm leaning some other way: every cell opens
+  /// toward `a` or `b`, whichever it can, or a coin flip's choice where it
+  /// can do both. Everything drains toward the corner the two point at, with
+  /// straight corridors along its two sides. They have to be at right
+  /// angles, like South and West; North and South is an error.
+  pub fn binary_tree_biased(&mut self, a: Dir, b: Dir, rng: &mut impl Rng) -> Result<(), BiasError> {
+    self.binary_tree_observed(a, b, rng, &mut |_| {})
+  }
+
+  fn binary_tree_observed(&mut self, a: Dir, b: Dir, rng: &mut impl Rng, observer: Observer<'_>) -> Result<(), BiasError> {
+    if a == b || a == b.opposite() { return Err(BiasError) }
+    self.algorithm = Some(Algorithm::BinaryTree);
+    for i in 0..(self.width * self.height) {
+      if let Some(pt) = self.nth_point(i) {
+        let can_a = self.neighbor(pt, a).is_some();
+        let can_b = self.neighbor(pt, b).is_some();
+
+        if can_a && !can_b {
+          self.carve_observed(pt, a, observer);
+        } else if can_b && !can_a {
+          self.carve_observed(pt, b, observer);
+        } else if can_a && can_b {
+          if rng.gen() {
+            self.carve_observed(pt, a, observer);
+          } else {
+            self.carve_observed(pt, b, observer);
+          }
+        }
+      }
+    }
+    // A cell with the mask's edge on both of its sides can't open either way
+    self.join_masked_pieces(rng, observer);
+    Ok(())
+  }
+
+  /// Turns a perfect maze into a unicursal labyrinth: a single corridor that
+  /// winds through every cell, returned here as the path from one end to the
+  /// other.
+  ///
+  /// This uses the standard doubling trick. Each cell becomes a 2x2 block
+  /// and the corridor traces the outline of the original maze's passages,
+  /// as if walking through it with a hand on the wall. Within a block, the
+  /// two cells along a side are joined if that side was a wall; otherwise
+  /// each of them continues through into the neighboring block. Every new
+  /// cell then has exactly two passages, and because the original is a tree
+  /// the outline is one loop through all of them. Cutting that loop next to
+  /// the top-left cell of the first cell's block, between the top two cells
+  /// of the block if they're joined, leaves the two ends.
+  ///
+  /// The maze is replaced by the doubled one, twice as wide and tall, its
+  /// edges joined up the same way. A mask is doubled along with it, and the
+  /// entrance, exit and doors move to the corner of their cell's block on
+  /// the same side. If it wasn't perfect, the outline falls into several
+  /// loops and the path only covers the one through the first cell.
+  pub fn make_unicursal(&mut self) -> Path {
+    let mut doubled = Maze::with_topology(self.width * 2, self.height * 2, self.topology).expect("");
+    doubled.mask = self.mask.as_ref().map(|mask| {
+      let width = mask.width * 2;
+      let cells = (0..width * mask.height * 2).map(|i| mask.get(Point { x: i % width / 2, y: i / width / 2 }));
+      Mask { width, height: mask.height * 2, cells: cells.collect() }
+    });
+    // The cell of a block on its `dir` side, favoring the top left
+    let corner = |pt: Point, dir: Dir| {
+      Point { x: pt.x * 2 + (dir == Dir::East) as usize, y: pt.y * 2 + (dir == Dir::South) as usize }
+    };
+    let moved = |pt: Point| {
+      self.doors.iter().find(|&&(door, _)| door == pt).map_or(corner(pt, Dir::North), |&(_, dir)| corner(pt, dir))
+    };
+    doubled.doors = self.doors.iter().map(|&(pt, dir)| (corner(pt, dir), dir)).collect();
+    doubled.entrance = self.entrance.map(moved);
+    doubled.exit = self.exit.map(moved);
+
+    for pt in self.iter().filter(|&pt| self.in_maze(pt)) {
+      let (x, y) = (pt.x * 2, pt.y * 2);
+      let corners = [Point { x, y }, Point { x: x + 1, y }, Point { x, y: y + 1 }, Point { x: x + 1, y: y + 1 }];
+      let sides = [(Dir::North, 0, 1, Dir::East), (Dir::South, 2, 3, Dir::East),
+                   (Dir::West, 0, 2, Dir::South), (Dir::East, 1, 3, Dir::South)];
+
+      for &(side, a, b, along) in sides.iter() {
+        if self.passage(pt, side) {
+          doubled.carve(corners[a], side).expect("");
+          doubled.carve(corners[b], side).expect("");
+        } else {
+          doubled.carve(corners[a], along).expect("");
+        }
+      }
+    }
+
+    // On a maze that wraps, the top two cells may each lead off the edge
+    // instead, so cut whichever passage the loop really takes out of start
+    let start = corner(self.first_cell(), Dir::North);
+    let cut = [Dir::East, Dir::South, Dir::West, Dir::North].iter().copied()
+      .find(|&dir| doubled.passage(start, dir)).expect("");
+    doubled.uncarve(start, cut).expect("");
+    *self = doubled;
+
+    let mut path = vec![start];
+    let mut prev = None;
+    let mut current = start;
+    while let Some(next) = self.open_neighbors(current).into_iter().find(|&n| Some(n) != prev) {
+      if next == start { break }
+      prev = Some(current);
+      current = next;
+      path.push(next);
+    }
+    path
+  }
+
+  /// Braids the maze: knocks a wall out of about `ratio` of its dead ends
+  /// (0.0 none, 1.0 all of them), joining each to a neighbor so it's part of
+  /// a loop. A neighbor that's a dead end too is picked first, which clears
+  /// two at once; otherwise any walled-off neighbor will do.
+  pub fn braid(&mut self, ratio: f64, rng: &mut impl Rng) {
+    let mut dead_ends = self.dead_ends();
+    dead_ends.shuffle(rng);
+    for pt in dead_ends {
+      // An earlier dead end may have been joined to this one already
+      if self.degree(pt) != 1 || !rng.gen_bool(ratio.clamp(0.0, 1.0)) { continue }
+      let walled: Vec<Dir> = Dir::ALL.iter().copied()
+        .filter(|&dir| !self.passage(pt, dir) && self.neighbor(pt, dir).is_some())
+        .collect();
+      let also_dead: Vec<Dir> = walled.iter().copied()
+        .filter(|&dir| self.degree(self.neighbor(pt, dir).expect("")) == 1)
+        .collect();
+      if let Some(&dir) = also_dead.choose(rng).or_else(|| walled.choose(rng)) {
+        self.carve(pt, dir).expect("");
+      }
+    }
+  }
+
+  /// Picks a uniformly random spanning tree of the existing passages, using
+  /// Wilson's algorithm, and returns it as a new maze. A perfect maze comes
+  /// back unchanged; a braided one loses just enough passages to be perfect
+  /// again. Disconnected parts each get their own tree.
+  pub fn random_spanning_tree(&self, rng: &mut impl Rng) -> Maze {
+    let mut tree = Maze::with_topology(self.width, self.height, self.topology).expect("");
+    for group in self.groups(|_| true) {
+      grid::wilson_walks(&mut tree, rng, &group, |_, pt| self.open_neighbors(pt), |_, _, _| {});
+    }
+    tree
+  }
+
+  /// Depth-first search from a random cell, carving into a random unvisited
+  /// neighbor each step and backing up when there isn't one. The stack is
+  /// kept by hand so huge mazes don't overflow the call stack.
+  pub fn recursive_backtracker(&mut self, rng: &mut impl Rng) {
+    self.algorithm = Some(Algorithm::RecursiveBacktracker);
+    grid::recursive_backtracker(self, rng);
+  }
+
+  // BacktrackerSteps makes the same maze as recursive_backtracker, and says
+  // what it's doing along the way
+  fn recursive_backtracker_observed(&mut self, rng: &mut impl Rng, observer: Observer<'_>) {
+    for event in BacktrackerSteps::new(self, rng) {
+      observer(event)
+    }
+  }
+
+  /// The simplest way to get a maze: a square perfect maze that's the same
+  /// every time for the same seed
+  pub fn new_seeded_square(size: usize, seed: u64) -> Result<Maze, BoundsError> {
+    let mut maze = Maze::new(size, size)?;
+    maze.recursive_backtracker(&mut SmallRng::seed_from_u64(seed));
+    Ok(maze)
+  }
+
+  /// A `size`-square recursive backtracker maze with a fresh random seed
+  pub fn new_random_square(size: usize) -> Result<Maze, BoundsError> {
+    let mut maze = Maze::new(size, size)?;
+    maze.recursive_backtracker(&mut rand::thread_rng());
+    Ok(maze)
+  }
+
+  /// A self-similar maze: a width x height maze whose every cell is itself
+  /// a FRACTAL_SUBDIVISION-square maze, and so on, `depth` levels down. Each
+  /// passage of a coarser level becomes a single passage between the facing
+  /// edges of the two sub-mazes. The result is FRACTAL_SUBDIVISION^(depth-1)
+  /// times as wide and tall as asked for, and still perfect; depth 1 is just
+  /// a recursive backtracker maze.
+  pub fn new_fractal(width: usize, height: usize, depth: usize, rng: &mut impl Rng) -> Result<Maze, BoundsError> {
+    if depth == 0 { return Err(BoundsError) }
+    let mut outer = Maze::new(width, height)?;
+    outer.recursive_backtracker(rng);
+    if depth == 1 { return Ok(outer) }
+
+    let scale = FRACTAL_SUBDIVISION.pow(depth as u32 - 1);
+    let mut maze = Maze::new(width * scale, height * scale)?;
+    for pt in outer.iter() {
+      let tile = Maze::new_fractal(FRACTAL_SUBDIVISION, FRACTAL_SUBDIVISION, depth - 1, rng)?;
+      for (q, dir) in tile.interior_edges() {
+        if tile.passage(q, dir) {
+          maze.carve(Point { x: pt.x * scale + q.x, y: pt.y * scale + q.y }, dir)?;
+        }
+      }
+    }
+
+    for (pt, dir) in outer.interior_edges() {
+      if !outer.passage(pt, dir) { continue }
+      let k = rng.gen_range(0, scale);
+      let door = match dir {
+        Dir::East => Point { x: pt.x * scale + scale - 1, y: pt.y * scale + k },
+        _ => Point { x: pt.x * scale + k, y: pt.y * scale + scale - 1 }
+      };
+      maze.carve(door, dir)?;
+    }
+    Ok(maze)
+  }
+
+  /// A maze made of concentric square rings around a single centre cell,
+  /// `2 * rings - 1` cells across. Each ring is one long corridor broken
+  /// into arcs by a few closed gaps, and every arc has exactly one radial
+  /// door into the next ring in, so the whole thing stays a perfect maze.
+  pub fn new_ring_maze(rings: usize, rng: &mut impl Rng) -> Result<Maze, BoundsError> {
+    if rings == 0 { return Err(BoundsError) }
+    let side = 2 * rings - 1;
+    let mut maze = Maze::new(side, side)?;
+
+    for ring in 0..rings - 1 {
+      let cells = maze.ring_cells(ring);
+      let len = cells.len();
+
+      // Cells not on a corner all have a neighbor in the next ring in
+      let doors: Vec<usize> = {
+        let mut doors: Vec<usize> = (0..len).filter(|&n| maze.inward(ring, cells[n]).is_some() && rng.gen_bool(0.2)).collect();
+        if doors.is_empty() { doors.push(maze.random_door(ring, &cells, rng)) }
+        doors
+      };
+
+      // One gap between each pair of doors; the gap after cell n is the
+      // edge from cells[n] to cells[n + 1]
+      let mut gaps = HashSet::new();
+      for (k, &door) in doors.iter().enumerate() {
+        let next = if k + 1 < doors.len() { doors[k + 1] } else { doors[0] + len };
+        gaps.insert((door + rng.gen_range(0, next - door)) % len);
+      }
+
+      for n in 0..len {
+        if gaps.contains(&n) { continue }
+        let (a, b) = (cells[n], cells[(n + 1) % len]);
+        maze.carve(a, a.direction_to(b).expect(""))?;
+      }
+      for &door in doors.iter() {
+        let dir = maze.inward(ring, cells[door]).expect("");
+        maze.carve(cells[door], dir)?;
+      }
+    }
+    Ok(maze)
+  }
+
+  fn random_door(&self, ring: usize, cells: &[Point], rng: &mut impl Rng) -> usize {
+    let candidates: Vec<usize> = (0..cells.len()).filter(|&n| self.inward(ring, cells[n]).is_some()).collect();
+    *candidates.choose(rng).expect("")
+  }
+
+  // Which ring a cell of a square ring maze sits on, 0 being the boundary
+  fn ring_of(&self, point: Point) -> usize {
+    point.x.min(point.y).min(self.width - 1 - point.x).min(self.height - 1 - point.y)
+  }
+
+  // The cells of a ring in clockwise order, starting from its top-left corner
+  fn ring_cells(&self, ring: usize) -> Vec<Point> {
+    let (lo, hi) = (ring, self.width - 1 - ring);
+    if lo == hi { return vec![Point { x: lo, y: lo }] }
+    let mut cells = Vec::new();
+    for x in lo..hi { cells.push(Point { x, y: lo }) }
+    for y in lo..hi { cells.push(Point { x: hi, y }) }
+    for x in (lo + 1..=hi).rev() { cells.push(Point { x, y: hi }) }
+    for y in (lo + 1..=hi).rev() { cells.push(Point { x: lo, y }) }
+    cells
+  }
+
+  // The direction from a ring cell to its neighbor one ring further in, if
+  // it has one (corners don't)
+  fn inward(&self, ring: usize, point: Point) -> Option<Dir> {
+    Dir::ALL.iter().copied().find(|&dir| {
+      self.neighbor(point, dir).is_some_and(|n| self.ring_of(n) == ring + 1)
+    })
+  }
+
+  /// Eller's algorithm, fed from an EllersStream one row at a time
+  pub fn ellers(&mut self, rng: &mut impl Rng) {
+    self.ellers_observed(rng, &mut |_| {})
+  }
+
+  fn ellers_observed(&mut self, rng: &mut impl Rng, observer: Observer<'_>) {
+    for (y, row) in EllerRows::new(self.width, self.height, &mut *rng).enumerate() {
+      for (x, cell) in row.into_iter().enumerate() {
+        let pt = Point { x, y };
+        if cell.east { self.carve_in_mask(pt, Dir::East, observer) }
+        if cell.south { self.carve_in_mask(pt, Dir::South, observer) }
+      }
+    }
+    self.join_masked_pieces(rng, observer);
+    self.algorithm = Some(Algorithm::Ellers);
+  }
+
+  // The generators' carve: knocks the wall down and tells the observer,
+  // if it wasn't down already
+  fn carve_observed(&mut self, point: Point, dir: Dir, observer: Observer<'_>) {
+    if self.passage(point, dir) { return }
+    self.carve(point, dir).expect("");
+    observer(GenerationEvent::Carve(point, dir));
+  }
+
+  // And uncarve, for recursive division
+  fn uncarve_observed(&mut self, point: Point, dir: Dir, observer: Observer<'_>) {
+    if !self.passage(point, dir) { return }
+    self.uncarve(point, dir).expect("");
+    observer(GenerationEvent::Wall(point, dir));
+  }
+
+  // For the generators that work along rows or across rooms and don't know
+  // about masks: carves a passage unless it leads out of the mask
+  fn carve_in_mask(&mut self, point: Point, dir: Dir, observer: Observer<'_>) {
+    if self.neighbor(point, dir).is_some() { self.carve_observed(point, dir, observer) }
+  }
+
+  // The passages carve_in_mask skipped can leave a masked maze in pieces,
+  // each still a tree, so Kruskal's joins them back into one
+  fn join_masked_pieces(&mut self, rng: &mut impl Rng, observer: Observer<'_>) {
+    if self.mask.is_some() { self.join_with_kruskal(rng, observer) }
+  }
+
+  /// An unbounded Eller's stream `width` cells wide, seeded from the OS
+  pub fn new_ellers_streaming(width: usize) -> EllersStream<SmallRng> {
+    EllersStream::new(width, SmallRng::from_entropy())
+  }
+
+  /// Wilson's algorithm: loop-erased random walks from each cell until they
+  /// hit the tree grown so far. Every spanning tree of the grid is equally
+  /// likely, so unlike the backtracker it has no bias toward long corridors.
+  pub fn wilsons(&mut self, rng: &mut impl Rng) {
+    grid::wilsons(self, rng);
+    self.algorithm = Some(Algorithm::Wilsons);
+  }
+
+  fn wilsons_observed(&mut self, rng: &mut impl Rng, observer: Observer<'_>) {
+    let cells = Grid::cells(self);
+    grid::wilson_walks(self, rng, &cells, Grid::neighbors, |maze, a, b| {
+      observer(GenerationEvent::Carve(a, maze.direction_between(a, b).expect("")))
+    });
+    self.algorithm = Some(Algorithm::Wilsons);
+  }
+
+  /// A minimum spanning tree of the grid where every edge weighs the same,
+  /// picked uniformly from all of them. That's exactly what Wilson's
+  /// algorithm produces, so this is another name for `wilsons`.
+  pub fn minimum_spanning_labyrinth(&mut self, rng: &mut impl Rng) {
+    self.wilsons(rng)
+  }
+
+  /// Sidewinder: the top row is one long corridor, and every row below it
+  /// is cut into runs of cells joined east-west. Each run ends, at random
+  /// or at the east edge, by opening one of its cells north. The chance of
+  /// closing out a run at each cell is `closeout_probability`: near 0.0 the
+  /// maze is all long horizontal corridors, near 1.0 it's mostly vertical.
+  /// It's clamped to between 0.0 and 1.0.
+  pub fn sidewinder(&mut self, rng: &mut impl Rng, closeout_probability: f64) {
+    self.sidewinder_observed(rng, closeout_probability, &mut |_| {})
+  }
+
+  fn sidewinder_observed(&mut self, rng: &mut impl Rng, closeout_probability: f64, observer: Observer<'_>) {
+    self.algorithm = Some(Algorithm::Sidewinder);
+    for y in 0..self.height {
+      let mut run = Vec::new();
+      for x in 0..self.width {
+        let pt = Point { x, y };
+        run.push(pt);
+        let at_east_edge = x + 1 == self.width;
+        if y == 0 {
+          if !at_east_edge { self.carve_in_mask(pt, Dir::East, observer) }
+        } else if at_east_edge || rng.gen_bool(closeout_probability.clamp(0.0, 1.0)) {
+          let &door = run.choose(rng).expect("");
+          self.carve_in_mask(door, Dir::North, observer);
+          run.clear();
+        } else {
+          self.carve_in_mask(pt, Dir::East, observer);
+        }
+      }
+    }
+    self.join_masked_pieces(rng, observer);
+  }
+
+  /// Aldous-Broder: a random walk over the whole grid, carving into each
+  /// cell the first time it's reached, until every cell has been. Like
+  /// Wilson's this gives every spanning tree the same chance, but it's slow
+  /// to finish, since the walk spends longer and longer crossing ground it's
+  /// already covered to find the last few cells.
+  pub fn aldous_broder(&mut self, rng: &mut impl Rng) {
+    self.aldous_broder_observed(rng, &mut |_| {})
+  }
+
+  fn aldous_broder_observed(&mut self, rng: &mut impl Rng, observer: Observer<'_>) {
+    self.algorithm = Some(Algorithm::AldousBroder);
+    let mut visited = vec![false; self.width * self.height];
+    let mut pt = self.random_cell(rng);
+    visited[self.index(pt)] = true;
+    observer(GenerationEvent::Visit(pt));
+    let mut remaining = self.iter().filter(|&pt| self.in_maze(pt)).count() - 1;
+
+    while remaining > 0 {
+      let dirs: Vec<Dir> = Dir::ALL.iter().copied().filter(|&dir| self.neighbor(pt, dir).is_some()).collect();
+      let &dir = dirs.choose(rng).expect("");
+      let n = self.neighbor(pt, dir).expect("");
+      if !visited[self.index(n)] {
+        self.carve_observed(pt, dir, observer);
+        visited[self.index(n)] = true;
+        observer(GenerationEvent::Visit(n));
+        remaining -= 1;
+      }
+      pt = n;
+    }
+  }
+
+  /// Hunt-and-kill: a random walk that only steps into cells it hasn't
+  /// been to, carving as it goes. When it gets stuck it hunts, scanning the
+  /// grid a row at a time for the first unvisited cell next to a visited
+  /// one, joins the two, and sets off walking again from there. The walks
+  /// make long winding passages with fewer dead ends than the backtracker.
+  pub fn hunt_and_kill(&mut self, rng: &mut impl Rng) {
+    self.hunt_and_kill_observed(rng, &mut |_| {})
+  }
+
+  fn hunt_and_kill_observed(&mut self, rng: &mut impl Rng, observer: Observer<'_>) {
+    self.algorithm = Some(Algorithm::HuntAndKill);
+    let mut visited = vec![false; self.width * self.height];
+    let mut current = Some(self.random_cell(rng));
+
+    while let Some(mut pt) = current {
+      visited[self.index(pt)] = true;
+      observer(GenerationEvent::Visit(pt));
+
+      // Kill: walk until there's nowhere new to go
+      loop {
+        let unvisited: Vec<Dir> = Dir::ALL.iter().copied()
+          .filter(|&dir| self.neighbor(pt, dir).is_some_and(|n| !visited[self.index(n)]))
+          .collect();
+        let &dir = match unvisited.choose(rng) { Some(dir) => dir, None => break };
+        self.carve_observed(pt, dir, observer);
+        pt = self.neighbor(pt, dir).expect("");
+        visited[self.index(pt)] = true;
+        observer(GenerationEvent::Visit(pt));
+      }
+
+      // Hunt: find somewhere to start the next walk
+      current = None;
+      for cell in self.iter() {
+        if visited[self.index(cell)] { continue }
+        let joins: Vec<Dir> = Dir::ALL.iter().copied()
+          .filter(|&dir| self.neighbor(cell, dir).is_some_and(|n| visited[self.index(n)]))
+          .collect();
+        if let Some(&dir) = joins.choose(rng) {
+          self.carve_observed(cell, dir, observer);
+          current = Some(cell);
+          break
+        }
+      }
+    }
+  }
+
+  /// Randomized Kruskal's: every wall between two cells in a random order,
+  /// knocking each one down if the cells on either side aren't connected
+  /// yet. Starts from the maze's existing passages, so cells already joined
+  /// stay one piece.
+  pub fn kruskal(&mut self, rng: &mut impl Rng) {
+    self.kruskal_observed(rng, &mut |_| {})
+  }
+
+  fn kruskal_observed(&mut self, rng: &mut impl Rng, observer: Observer<'_>) {
+    self.algorithm = Some(Algorithm::Kruskal);
+    self.join_with_kruskal(rng, observer);
+  }
+
+  fn join_with_kruskal(&mut self, rng: &mut impl Rng, observer: Observer<'_>) {
+    let mut sets = UnionFind::new(self.width * self.height);
+    let mut walls = Vec::new();
+    for (pt, dir) in self.interior_edges() {
+      let n = self.neighbor(pt, dir).expect("");
+      if self.passage(pt, dir) {
+        sets.union(self.index(pt), self.index(n));
+      } else {
+        walls.push((pt, dir, n));
+      }
+    }
+
+    walls.shuffle(rng);
+    for (pt, dir, n) in walls {
+      if sets.union(self.index(pt), self.index(n)) {
+        self.carve_observed(pt, dir, observer);
+      }
+    }
+  }
+
+  /// Simplified Prim's: grows one region out from a random cell, each step
+  /// knocking down a random wall from the set between the region and the
+  /// cells outside it. The region spreads evenly in every direction, so the
+  /// maze has lots of short dead ends radiating out from where it started.
+  pub fn prim(&mut self, rng: &mut impl Rng) {
+    self.prim_observed(rng, &mut |_| {})
+  }
+
+  fn prim_observed(&mut self, rng: &mut impl Rng, observer: Observer<'_>) {
+    self.algorithm = Some(Algorithm::Prim);
+    let mut in_maze = vec![false; self.width * self.height];
+    let start = self.random_cell(rng);
+    let mut frontier: Vec<(Point, Dir)> = Vec::new();
+    in_maze[self.index(start)] = true;
+    observer(GenerationEvent::Visit(start));
+    frontier.extend(Dir::ALL.iter().filter(|&&dir| self.neighbor(start, dir).is_some()).map(|&dir| (start, dir)));
+
+    while !frontier.is_empty() {
+      let (pt, dir) = frontier.swap_remove(rng.gen_range(0, frontier.len()));
+      let n = self.neighbor(pt, dir).expect("");
+      if in_maze[self.index(n)] { continue }
+      self.carve_observed(pt, dir, observer);
+      in_maze[self.index(n)] = true;
+      observer(GenerationEvent::Visit(n));
+      for &d in Dir::ALL.iter() {
+        if self.neighbor(n, d).is_some_and(|m| !in_maze[self.index(m)]) {
+          frontier.push((n, d));
+        }
+      }
+    }
+  }
+
+  /// Recursive division: knocks down every wall inside the grid (putting up
+  /// any wrapping ones) and then works the other way to the other generators,
+  /// splitting the room in two with a wall that has a single gap in it, and
+  /// splitting each half the same way until every room is one cell wide.
+  /// Makes long straight walls and a boxy look. The rooms still to split are
+  /// kept on a stack rather than recursed into.
+  pub fn recursive_division(&mut self, rng: &mut impl Rng) {
+    self.recursive_division_observed(rng, &mut |_| {})
+  }
+
+  fn recursive_division_observed(&mut self, rng: &mut impl Rng, observer: Observer<'_>) {
+    self.algorithm = Some(Algorithm::RecursiveDivision);
+    for (pt, dir) in self.interior_edges() {
+      self.uncarve_observed(pt, dir, observer);
+    }
+    for pt in self.iter().collect::<Vec<Point>>() {
+      if pt.x + 1 < self.width { self.carve_in_mask(pt, Dir::East, observer) }
+      if pt.y + 1 < self.height { self.carve_in_mask(pt, Dir::South, observer) }
+    }
+
+    // Rooms as (corner, width, height)
+    let mut rooms = vec![(Point { x: 0, y: 0 }, self.width, self.height)];
+    while let Some((corner, w, h)) = rooms.pop() {
+      if w < 2 || h < 2 { continue }
+      let horizontal = if w == h { rng.gen_bool(0.5) } else { h > w };
+      if horizontal {
+        // A wall along the south side of row `at`, with a gap at column `gap`;
+        // in a masked maze, the parts of it along the mask's edge are
+        // walls already
+        let at = rng.gen_range(0, h - 1);
+        let gap = rng.gen_range(0, w);
+        for x in (0..w).filter(|&x| x != gap) {
+          self.uncarve_observed(Point { x: corner.x + x, y: corner.y + at }, Dir::South, observer);
+        }
+        rooms.push((corner, w, at + 1));
+        rooms.push((Point { x: corner.x, y: corner.y + at + 1 }, w, h - at - 1));
+      } else {
+        let at = rng.gen_range(0, w - 1);
+        let gap = rng.gen_range(0, h);
+        for y in (0..h).filter(|&y| y != gap) {
+          self.uncarve_observed(Point { x: corner.x + at, y: corner.y + y }, Dir::East, observer);
+        }
+        rooms.push((corner, at + 1, h));
+        rooms.push((Point { x: corner.x + at + 1, y: corner.y }, w - at - 1, h));
+      }
+    }
+    self.join_masked_pieces(rng, observer);
+  }
+
+  /// The growing tree: a list of active cells, starting with a random one.
+  /// Each step picks an active cell by `strategy` and carves from it into a
+  /// random unvisited neighbor, which becomes active too; a cell with no
+  /// unvisited neighbors left is dropped from the list.
+  pub fn growing_tree(&mut self, rng: &mut impl Rng, strategy: GrowingTreeStrategy) {
+    self.growing_tree_observed(rng, strategy, &mut |_| {})
+  }
+
+  fn growing_tree_observed(&mut self, rng: &mut impl Rng, strategy: GrowingTreeStrategy, observer: Observer<'_>) {
+    self.algorithm = Some(Algorithm::GrowingTree);
+    let mut visited = vec![false; self.width * self.height];
+    let start = self.random_cell(rng);
+    let mut active = vec![start];
+    visited[self.index(start)] = true;
+    observer(GenerationEvent::Visit(start));
+
+    while !active.is_empty() {
+      let i = match strategy {
+        GrowingTreeStrategy::Newest => active.len() - 1,
+        GrowingTreeStrategy::Oldest => 0,
+        GrowingTreeStrategy::Random => rng.gen_range(0, active.len()),
+        GrowingTreeStrategy::Mixed(p) => {
+          if rng.gen_bool(p.clamp(0.0, 1.0)) { active.len() - 1 } else { rng.gen_range(0, active.len()) }
+        }
+      };
+      let pt = active[i];
+      let unvisited: Vec<Dir> = Dir::ALL.iter().copied()
+        .filter(|&dir| self.neighbor(pt, dir).is_some_and(|n| !visited[self.index(n)]))
+        .collect();
+
+      if let Some(&dir) = unvisited.choose(rng) {
+        let n = self.neighbor(pt, dir).expect("");
+        self.carve_observed(pt, dir, observer);
+        visited[self.index(n)] = true;
+        observer(GenerationEvent::Visit(n));
+        active.push(n);
+      } else {
+        active.remove(i);
+      }
+    }
+  }
+
+  /// Prim's algorithm proper: every wall has a weight, and the region
+  /// growing out from `start` always knocks down the lightest wall on its
+  /// edge next. `weight` is asked once for each wall, from the side already
+  /// in the region, the first time that wall comes up; ties go to the wall
+  /// that came up first. Making East and West walls cheaper than North and
+  /// South gives long horizontal corridors, and so on.
+  pub fn weighted_prim(&mut self, start: Point, weight: impl FnMut(Point, Dir) -> u32) -> Result<(), BoundsError> {
+    self.weighted_prim_observed(start, weight, &mut |_| {})
+  }
+
+  fn weighted_prim_observed(&mut self, start: Point, mut weight: impl FnMut(Point, Dir) -> u32,
+                            observer: Observer<'_>) -> Result<(), BoundsError> {
+    if !self.valid(start) { return Err(BoundsError) }
+    self.algorithm = Some(Algorithm::WeightedPrim);
+    let mut in_maze = vec![false; self.width * self.height];
+    let mut walls: Vec<(Point, Dir)> = Vec::new();
+    let mut heap = BinaryHeap::new();
+
+    let mut joined = Some(start);
+    loop {
+      if let Some(pt) = joined.take() {
+        in_maze[self.index(pt)] = true;
+        observer(GenerationEvent::Visit(pt));
+        for &dir in Dir::ALL.iter() {
+          if self.neighbor(pt, dir).is_some_and(|n| !in_maze[self.index(n)]) {
+            heap.push((Reverse(weight(pt, dir)), Reverse(walls.len())));
+            walls.push((pt, dir));
+          }
+        }
+      }
+
+      let (pt, dir) = match heap.pop() {
+        Some((_, Reverse(i))) => walls[i],
+        None => break
+      };
+      let n = self.neighbor(pt, dir).expect("");
+      if !in_maze[self.index(n)] {
+        self.carve_observed(pt, dir, observer);
+        joined = Some(n);
+      }
+    }
+    Ok(())
+  }
+
+  /// Weighted Prim's from a random cell with random weights
+  pub fn true_prim(&mut self, rng: &mut impl Rng) {
+    self.true_prim_observed(rng, &mut |_| {})
+  }
+
+  fn true_prim_observed(&mut self, rng: &mut impl Rng, observer: Observer<'_>) {
+    let start = self.random_cell(rng);
+    self.weighted_prim_observed(start, |_, _| rng.gen(), observer).expect("");
+  }
+
+  /// Runs any of the generators, for when which one is only known at run
+  /// time. The ones with settings get middling ones: sidewinder closes out
+  /// runs half the time, the growing tree picks active cells at random, and
+  /// weighted Prim's is true_prim.
+  pub fn generate(&mut self, algorithm: Algorithm, rng: &mut impl Rng) {
+    match algorithm {
+      Algorithm::BinaryTree => self.binary_tree(rng),
+      Algorithm::RecursiveBacktracker => self.recursive_backtracker(rng),
+      Algorithm::Ellers => self.ellers(rng),
+      Algorithm::Wilsons => self.wilsons(rng),
+      Algorithm::Sidewinder => self.sidewinder(rng, 0.5),
+      Algorithm::AldousBroder => self.aldous_broder(rng),
+      Algorithm::HuntAndKill => self.hunt_and_kill(rng),
+      Algorithm::Kruskal => self.kruskal(rng),
+      Algorithm::Prim => self.prim(rng),
+      Algorithm::WeightedPrim => self.true_prim(rng),
+      Algorithm::RecursiveDivision => self.recursive_division(rng),
+      Algorithm::GrowingTree => self.growing_tree(rng, GrowingTreeStrategy::Random)
+    }
+  }
+
+  /// Runs a generator like generate, calling `observer` with each thing it
+  /// does as it does it, for progress bars, animations or logging: every
+  /// wall knocked down or put up, every cell reached by the generators that
+  /// grow out a cell at a time, and the backtracker's backtracks. The maze
+  /// comes out the same as from generate with the same rng.
+  pub fn generate_observed(&mut self, algorithm: Algorithm, rng: &mut impl Rng,
+                           mut observer: impl FnMut(GenerationEvent)) {
+    let observer: Observer<'_> = &mut observer;
+    match algorithm {
+      Algorithm::BinaryTree => self.binary_tree_observed(Dir::North, Dir::East, rng, observer).expect(""),
+      Algorithm::RecursiveBacktracker => self.recursive_backtracker_observed(rng, observer),
+      Algorithm::Ellers => self.ellers_observed(rng, observer),
+      Algorithm::Wilsons => self.wilsons_observed(rng, observer),
+      Algorithm::Sidewinder => self.sidewinder_observed(rng, 0.5, observer),
+      Algorithm::AldousBroder => self.aldous_broder_observed(rng, observer),
+      Algorithm::HuntAndKill => self.hunt_and_kill_observed(rng, observer),
+      Algorithm::Kruskal => self.kruskal_observed(rng, observer),
+      Algorithm::Prim => self.prim_observed(rng, observer),
+      Algorithm::WeightedPrim => self.true_prim_observed(rng, observer),
+      Algorithm::RecursiveDivision => self.recursive_division_observed(rng, observer),
+      Algorithm::GrowingTree => self.growing_tree_observed(rng, GrowingTreeStrategy::Random, observer)
+    }
+  }
+}
+
+impl<R: Rng> EllersStream<R> {
+  pub fn new(width: usize, rng: R) -> Self {
+    EllersStream { width, rng, sets: vec![None; width], next_set: 0, rows: None, emitted: 0, done: false }
+  }
+
+  /// Stops the stream after `rows` rows, the last of them closing the maze off
+  pub fn with_rows(mut self, rows: usize) -> Self {
+    self.rows = Some(rows);
+    self.done = rows == 0;
+    self
+  }
+
+  pub fn is_done(&self) -> bool {
+    self.done
+  }
+
+  /// The next row of cells, or an empty row once the stream is done
+  pub fn next_row(&mut self) -> Vec<Cell> {
+    let last = self.rows.is_some_and(|rows| self.emitted + 1 >= rows);
+    self.row(last)
+  }
+
+  /// Ends the stream with a row that joins everything still apart
+  pub fn finish(&mut self) -> Vec<Cell> {
+    self.row(true)
+  }
+
+  fn row(&mut self, last: bool) -> Vec<Cell> {
+    if self.done { return Vec::new() }
+    let north: Vec<bool> = self.sets.iter().map(|s| s.is_some()).collect();
+    let mut sets: Vec<usize> = Vec::with_capacity(self.width);
+    for x in 0..self.width {
+      sets.push(match self.sets[x] {
+        Some(set) => set,
+        None => { self.next_set += 1; self.next_set }
+      });
+    }
+
+    // Join neighbors that aren't already connected; the last row has to join them all
+    let mut east = vec![false; self.width];
+    for x in 0..self.width.saturating_sub(1) {
+      if sets[x] != sets[x + 1] && (last || self.rng.gen_bool(0.5)) {
+        east[x] = true;
+        let (keep, gone) = (sets[x], sets[x + 1]);
+        for set in sets.iter_mut() {
+          if *set == gone { *set = keep }
+        }
+      }
+    }
+
+    // Every set carries on downward through at least one of its cells
+    let mut south = vec![false; self.width];
+    if !last {
+      let mut members: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+      for (x, &set) in sets.iter().enumerate() {
+        members.entry(set).or_default().push(x);
+      }
+      for columns in members.values() {
+        let first = *columns.choose(&mut self.rng).expect("");
+        for &x in columns.iter() {
+          south[x] = x == first || self.rng.gen_bool(0.3);
+        }
+      }
+    }
+
+    self.sets = (0..self.width).map(|x| if south[x] { Some(sets[x]) } else { None }).collect();
+    self.emitted += 1;
+    self.done = last;
+    (0..self.width).map(|x| Cell {
+      north: north[x],
+      east: east[x],
+      south: south[x],
+      west: x > 0 && east[x - 1],
+    }).collect()
+  }
+}
+
+impl<R: Rng> EllerRows<R> {
+  pub fn new(width: usize, height: usize, rng: R) -> Self {
+    EllerRows { stream: EllersStream::new(width, rng).with_rows(height) }
+  }
+}
+
+impl<R: Rng> Iterator for EllerRows<R> {
+  type Item = Vec<Cell>;
+
+  fn next(&mut self) -> Option<Vec<Cell>> {
+    if self.stream.is_done() { None } else { Some(self.stream.next_row()) }
+  }
+}
+
+impl<'a, R: Rng> BacktrackerSteps<'a, R> {
+  pub fn new(maze: &'a mut Maze, mut rng: R) -> BacktrackerSteps<'a, R> {
+    maze.algorithm = Some(Algorithm::RecursiveBacktracker);
+    let start = maze.random_cell(&mut rng);
+    let visited = vec![false; maze.width * maze.height];
+    BacktrackerSteps { maze, rng, visited, stack: Vec::new(), arriving: Some(start) }
+  }
+
+  /// The maze as carved so far
+  pub fn maze(&self) -> &Maze {
+    self.maze
+  }
+}
+
+impl<'a, R: Rng> Iterator for BacktrackerSteps<'a, R> {
+  type Item = GenerationEvent;
+  fn next(&mut self) -> Option<GenerationEvent> {
+    if let Some(cell) = self.arriving.take() {
+      self.visited[self.maze.index(cell)] = true;
+      self.stack.push(cell);
+      return Some(GenerationEvent::Visit(cell))
+    }
+
+    let &cell = self.stack.last()?;
+    let unvisited: Vec<Point> = Grid::neighbors(&*self.maze, cell).into_iter()
+      .filter(|&n| !self.visited[self.maze.index(n)])
+      .collect();
+    match unvisited.choose(&mut self.rng) {
+      Some(&n) => {
+        let dir = self.maze.direction_between(cell, n).expect("");
+        self.maze.carve(cell, dir).expect("");
+        self.arriving = Some(n);
+        Some(GenerationEvent::Carve(cell, dir))
+      }
+      None => {
+        self.stack.pop();
+        Some(GenerationEvent::Backtrack(cell))
+      }
+    }
+  }
+}
+
+impl GenerationState {
+  /// Rebuilds the states of a generation from the order its passages were
+  /// carved in, starting from the fully walled maze
+  pub fn replay(width: usize, height: usize, carves: &[(Point, Dir)]) -> Result<Vec<GenerationState>, BoundsError> {
+    let mut maze = Maze::new(width, height)?;
+    let mut states = vec![GenerationState { maze: maze.clone(), carved: None }];
+    for &(pt, dir) in carves {
+      maze.carve(pt, dir)?;
+      states.push(GenerationState { maze: maze.clone(), carved: Some((pt, dir)) });
+    }
+    Ok(states)
+  }
+}
+
+/// A walk with its loops cut out, in the order they were made: whenever the
+/// walk comes back to a cell it's already been through, everything since the
+/// first visit is dropped. What's left visits each cell at most once and
+/// still runs from the walk's first cell to its last. This is the step that
+/// turns Wilson's random walks into tree branches.
+pub fn loop_erase(walk: &[Point]) -> Path {
+  grid::erase_loops(walk)
+}
+
+impl Algorithm {
+  pub const ALL: [Algorithm; 12] = [
+    Algorithm::BinaryTree, Algorithm::RecursiveBacktracker, Algorithm::Ellers, Algorithm::Wilsons,
+    Algorithm::Sidewinder, Algorithm::AldousBroder, Algorithm::HuntAndKill, Algorithm::Kruskal,
+    Algorithm::Prim, Algorithm::WeightedPrim, Algorithm::RecursiveDivision, Algorithm::GrowingTree,
+  ];
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Algorithm::BinaryTree => "BinaryTree",
+      Algorithm::RecursiveBacktracker => "RecursiveBacktracker",
+      Algorithm::Ellers => "Ellers",
+      Algorithm::Wilsons => "Wilsons",
+      Algorithm::Sidewinder => "Sidewinder",
+      Algorithm::AldousBroder => "AldousBroder",
+      Algorithm::HuntAndKill => "HuntAndKill",
+      Algorithm::Kruskal => "Kruskal",
+      Algorithm::Prim => "Prim",
+      Algorithm::WeightedPrim => "WeightedPrim",
+      Algorithm::RecursiveDivision => "RecursiveDivision",
+      Algorithm::GrowingTree => "GrowingTree"
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Algorithm> {
+    Algorithm::ALL.iter().copied().find(|a| a.name() == name)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::rngs::StdRng;
+
+  #[test]
+  fn maze_make_unicursal_test() {
+    let mut m = Maze::new(4, 3).expect("");
+    m.binary_tree(&mut StdRng::seed_from_u64(0));
+    let path = m.make_unicursal();
+
+    assert_eq!((m.width, m.height), (8, 6));
+    assert_eq!(path.len(), 48);
+    assert_eq!(path.iter().collect::<HashSet<_>>().len(), 48);
+    for step in path.windows(2) {
+      assert!(m.open_neighbors(step[0]).contains(&step[1]));
+    }
+
+    let dead_ends: Vec<Point> = m.iter().filter(|&pt| m.degree(pt) == 1).collect();
+    assert_eq!(dead_ends, vec![Point { x: 0, y: 0 }, Point { x: 1, y: 0 }]);
+    assert!(m.iter().all(|pt| m.degree(pt) <= 2));
+
+    // Passages across a wrapping edge double up across it too
+    for &topology in Topology::ALL.iter() {
+      for seed in 0..16 {
+        let mut m = Maze::with_topology(4, 3, topology).expect("");
+        m.recursive_backtracker(&mut StdRng::seed_from_u64(seed));
+        let path = m.make_unicursal();
+        assert_eq!(m.topology(), topology);
+        assert_eq!(path.iter().collect::<HashSet<_>>().len(), 48, "{:?} {}", topology, seed);
+        assert!(path.windows(2).all(|step| m.open_neighbors(step[0]).contains(&step[1])));
+        let mut ends = m.dead_ends();
+        ends.sort_by_key(|&pt| m.index(pt));
+        let mut expected = vec![path[0], path[47]];
+        expected.sort_by_key(|&pt| m.index(pt));
+        assert_eq!(ends, expected, "{:?} {}", topology, seed);
+      }
+    }
+
+    // A mask doubles up with the maze, and the path starts inside it
+    let mut m = Maze::new_masked(Mask::from_text("X...\n....\n..X.\n").expect("")).expect("");
+    m.recursive_backtracker(&mut StdRng::seed_from_u64(3));
+    m.open_entrance(Point { x: 1, y: 0 }, Dir::North).expect("");
+    m.open_exit(Point { x: 3, y: 2 }, Dir::East).expect("");
+    let path = m.make_unicursal();
+    assert_eq!(m.mask().map(|mask| mask.count()), Some(40));
+    assert_eq!(path.iter().collect::<HashSet<_>>().len(), 40);
+    assert!(path.iter().all(|&pt| m.in_maze(pt)));
+    assert_eq!(m.dead_ends().len(), 2);
+    assert_eq!((m.entrance(), m.exit()), (Some(Point { x: 2, y: 0 }), Some(Point { x: 7, y: 4 })));
+    assert!(m.is_door(Point { x: 2, y: 0 }, Dir::North) && m.is_door(Point { x: 7, y: 4 }, Dir::East));
+  }
+
+  #[test]
+  fn maze_random_spanning_tree_test() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let mut m = Maze::new(4, 4).expect("");
+    let all: Vec<Point> = m.iter().collect();
+    m.carve_all_passages_in(&all);
+
+    let tree = m.random_spanning_tree(&mut rng);
+    assert_eq!(tree.passage_count(), 15);
+    assert!(tree.are_all_cells_mutually_reachable());
+
+    let mut m = Maze::new(6, 5).expect("");
+    m.binary_tree(&mut StdRng::seed_from_u64(0));
+    let tree = m.random_spanning_tree(&mut rng);
+    assert_eq!(tree.east_walls, m.east_walls);
+    assert_eq!(tree.south_walls, m.south_walls);
+
+    let mut m = Maze::new(3, 1).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    let tree = m.random_spanning_tree(&mut rng);
+    assert_eq!(tree.east_walls, vec![false, true]);
+  }
+
+  #[test]
+  fn maze_recursive_backtracker_test() {
+    let mut m = Maze::new(20, 15).expect("");
+    m.recursive_backtracker(&mut StdRng::seed_from_u64(1));
+    assert_eq!(m.passage_count(), 20 * 15 - 1);
+    assert!(m.are_all_cells_mutually_reachable());
+  }
+
+  #[test]
+  fn maze_new_square_test() {
+    let a = Maze::new_seeded_square(10, 42).expect("");
+    let b = Maze::new_seeded_square(10, 42).expect("");
+    let c = Maze::new_seeded_square(10, 43).expect("");
+    assert_eq!(a.to_string_compact(), b.to_string_compact());
+    assert_ne!(a.to_string_compact(), c.to_string_compact());
+    assert!(Maze::new_seeded_square(0, 42).is_err());
+
+    let r = Maze::new_random_square(6).expect("");
+    assert_eq!((r.width, r.height), (6, 6));
+    assert_eq!(r.passage_count(), 35);
+  }
+
+  #[test]
+  fn maze_fractal_test() {
+    let mut rng = StdRng::seed_from_u64(11);
+    let m = Maze::new_fractal(2, 3, 1, &mut rng).expect("");
+    assert_eq!((m.width, m.height), (2, 3));
+
+    let m = Maze::new_fractal(2, 3, 3, &mut rng).expect("");
+    assert_eq!((m.width, m.height), (18, 27));
+    assert_eq!(m.passage_count(), 18 * 27 - 1);
+    assert!(m.are_all_cells_mutually_reachable());
+
+    assert!(Maze::new_fractal(2, 2, 0, &mut rng).is_err());
+    assert!(Maze::new_fractal(0, 2, 2, &mut rng).is_err());
+  }
+
+  #[test]
+  fn ring_maze_test() {
+    assert!(Maze::new_ring_maze(0, &mut StdRng::seed_from_u64(1)).is_err());
+    let single = Maze::new_ring_maze(1, &mut StdRng::seed_from_u64(1)).expect("");
+    assert_eq!((single.width, single.height), (1, 1));
+
+    for seed in 0..20 {
+      let m = Maze::new_ring_maze(5, &mut StdRng::seed_from_u64(seed)).expect("");
+      assert_eq!((m.width, m.height), (9, 9));
+      let center = Point { x: 4, y: 4 };
+      let reach = m.reachable_from(center);
+      assert!(reach.iter().all(|&r| r));
+      // A spanning tree: one fewer passage than cells
+      assert_eq!(m.passage_count(), 80);
+      for pt in m.ring_cells(0) {
+        assert!(m.path_exists(pt, center));
+      }
+    }
+  }
+
+  #[test]
+  fn ellers_stream_test() {
+    let mut stream = EllersStream::new(8, StdRng::seed_from_u64(7)).with_rows(10);
+    let mut rows = Vec::new();
+    while !stream.is_done() {
+      rows.push(stream.next_row());
+    }
+    assert_eq!(rows.len(), 10);
+    assert!(stream.next_row().is_empty());
+
+    let mut m = Maze::new(8, 10).expect("");
+    m.ellers(&mut StdRng::seed_from_u64(7));
+    assert_eq!(m.algorithm, Some(Algorithm::Ellers));
+    for (y, row) in rows.iter().enumerate() {
+      for (x, &cell) in row.iter().enumerate() {
+        assert_eq!(cell, m.cell(Point { x, y }));
+      }
+    }
+    assert!(m.reachable_from(Point { x: 0, y: 0 }).iter().all(|&r| r));
+    assert_eq!(m.passage_count(), 79);
+
+    // An open-ended stream, closed off with finish
+    let mut open = EllersStream::new(5, StdRng::seed_from_u64(3));
+    for _ in 0..1000 {
+      assert_eq!(open.next_row().len(), 5);
+    }
+    assert!(!open.is_done());
+    let last = open.finish();
+    assert!(last.iter().all(|c| !c.south));
+    assert!(open.is_done());
+  }
+
+  #[test]
+  fn wilsons_test() {
+    let mut m = Maze::new(9, 7).expect("");
+    m.wilsons(&mut StdRng::seed_from_u64(11));
+    assert_eq!(m.algorithm, Some(Algorithm::Wilsons));
+    assert!(m.reachable_from(Point { x: 0, y: 0 }).iter().all(|&r| r));
+    assert_eq!(m.passage_count(), 62);
+
+    let mut mst = Maze::new(9, 7).expect("");
+    mst.minimum_spanning_labyrinth(&mut StdRng::seed_from_u64(11));
+    assert_eq!(mst.east_walls, m.east_walls);
+    assert_eq!(mst.south_walls, m.south_walls);
+
+    let mut torus = Maze::with_topology(5, 5, Topology::Torus).expect("");
+    torus.wilsons(&mut StdRng::seed_from_u64(1));
+    assert!(torus.reachable_from(Point { x: 0, y: 0 }).iter().all(|&r| r));
+    assert_eq!(torus.passage_count(), 24);
+  }
+
+  #[test]
+  fn maze_recursive_backtracker_large_test() {
+    // Deep enough that a recursive version would overflow the stack
+    let mut m = Maze::new(1000, 1000).expect("");
+    m.recursive_backtracker(&mut StdRng::seed_from_u64(1));
+    assert_eq!(m.passage_count(), 1000 * 1000 - 1);
+    assert!(m.reachable_from(Point { x: 0, y: 0 }).iter().all(|&r| r));
+  }
+
+  #[test]
+  fn sidewinder_test() {
+    for &p in [0.0, 0.3, 0.5, 1.0].iter() {
+      let mut m = Maze::new(8, 6).expect("");
+      m.sidewinder(&mut StdRng::seed_from_u64(5), p);
+      assert_eq!(m.algorithm, Some(Algorithm::Sidewinder));
+      assert!((0..7).all(|x| m.passage(Point { x, y: 0 }, Dir::East)));
+      assert_eq!(m.passage_count(), 47);
+      assert!(m.are_all_cells_mutually_reachable());
+    }
+
+    // Never closing out early leaves every row a single corridor
+    let mut m = Maze::new(5, 4).expect("");
+    m.sidewinder(&mut StdRng::seed_from_u64(1), 0.0);
+    assert!(m.iter().all(|pt| pt.x == 4 || m.passage(pt, Dir::East)));
+
+    // Always closing out makes every cell below the top row open north
+    let mut m = Maze::new(5, 4).expect("");
+    m.sidewinder(&mut StdRng::seed_from_u64(1), 1.0);
+    assert!(m.iter().all(|pt| pt.y == 0 || m.passage(pt, Dir::North)));
+    // Past either end is the same as that end
+    let mut over = Maze::new(5, 4).expect("");
+    over.sidewinder(&mut StdRng::seed_from_u64(1), 3.0);
+    assert_eq!(over.east_walls, m.east_walls);
+    let mut under = Maze::new(5, 4).expect("");
+    under.sidewinder(&mut StdRng::seed_from_u64(1), -1.0);
+    assert!(under.is_perfect());
+  }
+
+  #[test]
+  fn aldous_broder_test() {
+    for seed in 0..5 {
+      let mut m = Maze::new(9, 7).expect("");
+      m.aldous_broder(&mut StdRng::seed_from_u64(seed));
+      assert_eq!(m.algorithm, Some(Algorithm::AldousBroder));
+      assert_eq!(m.passage_count(), 62);
+      assert!(m.are_all_cells_mutually_reachable());
+    }
+
+    let mut single = Maze::new(1, 1).expect("");
+    single.aldous_broder(&mut StdRng::seed_from_u64(0));
+    assert_eq!(single.passage_count(), 0);
+  }
+
+  #[test]
+  fn loop_erase_test() {
+    let p = |x, y| Point { x, y };
+    assert_eq!(loop_erase(&[]), vec![]);
+    assert_eq!(loop_erase(&[p(0, 0), p(1, 0), p(2, 0)]), vec![p(0, 0), p(1, 0), p(2, 0)]);
+    // Around a square and back to the start, then off east
+    let walk = [p(0, 0), p(1, 0), p(1, 1), p(0, 1), p(0, 0), p(1, 0), p(2, 0)];
+    assert_eq!(loop_erase(&walk), vec![p(0, 0), p(1, 0), p(2, 0)]);
+    // A loop inside a loop
+    let walk = [p(0, 0), p(1, 0), p(2, 0), p(2, 1), p(2, 0), p(1, 0), p(1, 1)];
+    assert_eq!(loop_erase(&walk), vec![p(0, 0), p(1, 0), p(1, 1)]);
+  }
+
+  #[test]
+  fn hunt_and_kill_test() {
+    for seed in 0..5 {
+      let mut m = Maze::new(12, 9).expect("");
+      m.hunt_and_kill(&mut StdRng::seed_from_u64(seed));
+      assert_eq!(m.algorithm, Some(Algorithm::HuntAndKill));
+      assert_eq!(m.passage_count(), 12 * 9 - 1);
+      assert!(m.are_all_cells_mutually_reachable());
+    }
+
+    let mut single = Maze::new(1, 1).expect("");
+    single.hunt_and_kill(&mut StdRng::seed_from_u64(0));
+    assert_eq!(single.passage_count(), 0);
+  }
+
+  #[test]
+  fn kruskal_test() {
+    for seed in 0..5 {
+      let mut m = Maze::new(10, 8).expect("");
+      m.kruskal(&mut StdRng::seed_from_u64(seed));
+      assert_eq!(m.algorithm, Some(Algorithm::Kruskal));
+      assert_eq!(m.passage_count(), 79);
+      assert!(m.are_all_cells_mutually_reachable());
+    }
+
+    // Passages already there are kept, and no loops are added around them
+    let mut m = Maze::new(4, 4).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.kruskal(&mut StdRng::seed_from_u64(3));
+    assert!(m.passage(Point { x: 0, y: 0 }, Dir::East));
+    assert_eq!(m.passage_count(), 15);
+  }
+
+  #[test]
+  fn prim_test() {
+    for seed in 0..5 {
+      let mut m = Maze::new(10, 8).expect("");
+      m.prim(&mut StdRng::seed_from_u64(seed));
+      assert_eq!(m.algorithm, Some(Algorithm::Prim));
+      assert_eq!(m.passage_count(), 79);
+      assert!(m.are_all_cells_mutually_reachable());
+    }
+  }
+
+  #[test]
+  fn weighted_prim_test() {
+    for seed in 0..5 {
+      let mut m = Maze::new(10, 8).expect("");
+      m.true_prim(&mut StdRng::seed_from_u64(seed));
+      assert_eq!(m.algorithm, Some(Algorithm::WeightedPrim));
+      assert_eq!(m.passage_count(), 79);
+      assert!(m.are_all_cells_mutually_reachable());
+    }
+
+    let mut m = Maze::new(3, 3).expect("");
+    assert!(m.weighted_prim(Point { x: 3, y: 0 }, |_, _| 0).is_err());
+
+    // Horizontal walls free and vertical ones dear: every row becomes one
+    // corridor, joined to the next only down the start column
+    let mut m = Maze::new(5, 4).expect("");
+    m.weighted_prim(Point { x: 2, y: 0 }, |_, dir| match dir {
+      Dir::East | Dir::West => 0,
+      _ => 10
+    }).expect("");
+    assert!(m.iter().all(|pt| pt.x == 4 || m.passage(pt, Dir::East)));
+    assert!(m.iter().all(|pt| pt.y == 3 || m.passage(pt, Dir::South) == (pt.x == 2)));
+  }
+
+  #[test]
+  fn eller_rows_test() {
+    let rows: Vec<Vec<Cell>> = EllerRows::new(6, 5, StdRng::seed_from_u64(2)).collect();
+    assert_eq!(rows.len(), 5);
+    assert!(rows.iter().all(|row| row.len() == 6));
+    assert!(rows[0].iter().all(|c| !c.north));
+    assert!(rows[4].iter().all(|c| !c.south));
+
+    let mut m = Maze::new(6, 5).expect("");
+    m.ellers(&mut StdRng::seed_from_u64(2));
+    for (y, row) in rows.iter().enumerate() {
+      for (x, &cell) in row.iter().enumerate() {
+        assert_eq!(cell, m.cell(Point { x, y }));
+      }
+    }
+
+    // Far taller than anyone would want to keep in memory, a row at a time
+    let tall = EllerRows::new(4, 100_000, StdRng::seed_from_u64(1));
+    assert_eq!(tall.filter(|row| !row[3].east).count(), 100_000);
+    assert_eq!(EllerRows::new(4, 0, StdRng::seed_from_u64(1)).count(), 0);
+  }
+
+  #[test]
+  fn recursive_division_test() {
+    for seed in 0..5 {
+      let mut m = Maze::new(10, 7).expect("");
+      m.recursive_division(&mut StdRng::seed_from_u64(seed));
+      assert_eq!(m.algorithm, Some(Algorithm::RecursiveDivision));
+      assert_eq!(m.passage_count(), 69);
+      assert!(m.are_all_cells_mutually_reachable());
+    }
+
+    // Starting from an open grid comes to the same thing
+    let mut open = Maze::new_open(6, 6).expect("");
+    let mut closed = Maze::new(6, 6).expect("");
+    open.recursive_division(&mut StdRng::seed_from_u64(1));
+    closed.recursive_division(&mut StdRng::seed_from_u64(1));
+    assert_eq!(open.to_string_compact(), closed.to_string_compact());
+
+    // Wrapping walls aren't opened, so it's still a tree
+    let mut torus = Maze::with_topology(5, 4, Topology::Torus).expect("");
+    torus.recursive_division(&mut StdRng::seed_from_u64(2));
+    assert_eq!(torus.passage_count(), 19);
+    assert!(torus.are_all_cells_mutually_reachable());
+
+    let mut line = Maze::new(5, 1).expect("");
+    line.recursive_division(&mut StdRng::seed_from_u64(0));
+    assert_eq!(line.passage_count(), 4);
+  }
+
+  #[test]
+  fn growing_tree_test() {
+    let strategies = [
+      GrowingTreeStrategy::Newest, GrowingTreeStrategy::Oldest,
+      GrowingTreeStrategy::Random, GrowingTreeStrategy::Mixed(0.75),
+    ];
+    for &strategy in strategies.iter() {
+      let mut m = Maze::new(9, 7).expect("");
+      m.growing_tree(&mut StdRng::seed_from_u64(4), strategy);
+      assert_eq!(m.algorithm, Some(Algorithm::GrowingTree));
+      assert_eq!(m.passage_count(), 62);
+      assert!(m.are_all_cells_mutually_reachable());
+    }
+
+    // Always taking the newest is the recursive backtracker
+    let mut grown = Maze::new(8, 8).expect("");
+    let mut backtracked = Maze::new(8, 8).expect("");
+    grown.growing_tree(&mut StdRng::seed_from_u64(6), GrowingTreeStrategy::Newest);
+    backtracked.recursive_backtracker(&mut StdRng::seed_from_u64(6));
+    assert_eq!(grown.to_string_compact(), backtracked.to_string_compact());
+  }
+
+  #[test]
+  fn binary_tree_biased_test() {
+    let pairs = [(Dir::South, Dir::West), (Dir::West, Dir::North), (Dir::East, Dir::South)];
+    for &(a, b) in pairs.iter() {
+      let mut m = Maze::new(7, 6).expect("");
+      m.binary_tree_biased(a, b, &mut StdRng::seed_from_u64(0)).expect("");
+      assert_eq!(m.algorithm, Some(Algorithm::BinaryTree));
+      assert_eq!(m.passage_count(), 41);
+      assert!(m.are_all_cells_mutually_reachable());
+    }
+
+    // The two sides the bias points at are straight corridors
+    let mut m = Maze::new(5, 5).expect("");
+    m.binary_tree_biased(Dir::South, Dir::West, &mut StdRng::seed_from_u64(0)).expect("");
+    for x in 1..5 { assert!(m.passage(Point { x, y: 4 }, Dir::West)) }
+    for y in 0..4 { assert!(m.passage(Point { x: 0, y }, Dir::South)) }
+
+    assert_eq!(m.binary_tree_biased(Dir::North, Dir::South, &mut StdRng::seed_from_u64(0)), Err(BiasError));
+    assert_eq!(m.binary_tree_biased(Dir::East, Dir::East, &mut StdRng::seed_from_u64(0)), Err(BiasError));
+  }
+
+  #[test]
+  fn braid_test() {
+    let mut m = Maze::new(12, 12).expect("");
+    m.recursive_backtracker(&mut StdRng::seed_from_u64(3));
+    let mut braided = m.clone();
+    braided.braid(1.0, &mut StdRng::seed_from_u64(1));
+    assert_eq!(braided.dead_ends(), vec![]);
+    assert!(braided.passage_count() > m.passage_count());
+    assert_eq!(braided.algorithm, Some(Algorithm::RecursiveBacktracker));
+
+    let mut half = m.clone();
+    half.braid(0.5, &mut StdRng::seed_from_u64(1));
+    assert!(half.dead_ends().len() < m.dead_ends().len());
+    assert!(!half.dead_ends().is_empty());
+
+    let mut untouched = m.clone();
+    untouched.braid(0.0, &mut StdRng::seed_from_u64(1));
+    assert_eq!(untouched.to_string_compact(), m.to_string_compact());
+  }
+
+  #[test]
+  fn seeded_generation_is_reproducible() {
+    let make = |seed: u64| {
+      let mut m = Maze::new(9, 9).expect("");
+      m.binary_tree(&mut StdRng::seed_from_u64(seed));
+      m.to_string_compact()
+    };
+    assert_eq!(make(7), make(7));
+    assert_ne!(make(7), make(8));
+  }
+
+  #[test]
+  fn generate_test() {
+    for &algorithm in Algorithm::ALL.iter() {
+      let mut m = Maze::new(6, 5).expect("");
+      m.generate(algorithm, &mut StdRng::seed_from_u64(1));
+      assert_eq!(m.algorithm, Some(algorithm));
+      assert_eq!(m.passage_count(), 29);
+    }
+  }
+
+  #[test]
+  fn backtracker_steps_test() {
+    let mut m = Maze::new(6, 5).expect("");
+    let events: Vec<GenerationEvent> = BacktrackerSteps::new(&mut m, StdRng::seed_from_u64(8)).collect();
+    let mut whole = Maze::new(6, 5).expect("");
+    whole.recursive_backtracker(&mut StdRng::seed_from_u64(8));
+    assert_eq!((m.east_walls.clone(), m.south_walls.clone()), (whole.east_walls, whole.south_walls));
+    assert_eq!(m.algorithm(), Some(Algorithm::RecursiveBacktracker));
+
+    // Every cell visited once and left once, with a carve into each but the first
+    let count = |f: fn(&GenerationEvent) -> bool| events.iter().filter(|e| f(e)).count();
+    assert_eq!(count(|e| matches!(e, GenerationEvent::Visit(_))), 30);
+    assert_eq!(count(|e| matches!(e, GenerationEvent::Backtrack(_))), 30);
+    assert_eq!(count(|e| matches!(e, GenerationEvent::Carve(_, _))), 29);
+    for pair in events.windows(2) {
+      if let GenerationEvent::Carve(pt, dir) = pair[0] {
+        assert_eq!(pair[1], GenerationEvent::Visit(pt.translate(dir).expect("")));
+      }
+    }
+    assert!(matches!(events.last(), Some(GenerationEvent::Backtrack(_))));
+
+    // Partway through, the maze has just what's been carved
+    let mut m = Maze::new(6, 5).expect("");
+    let mut steps = BacktrackerSteps::new(&mut m, StdRng::seed_from_u64(8));
+    let carves = steps.by_ref().take(10).filter(|e| matches!(e, GenerationEvent::Carve(_, _))).count();
+    assert_eq!(steps.maze().passage_count(), carves);
+  }
+
+  #[test]
+  fn generate_observed_test() {
+    let mut events = Vec::new();
+    let mut m = Maze::new(7, 6).expect("");
+    m.generate_observed(Algorithm::Kruskal, &mut StdRng::seed_from_u64(2), |event| events.push(event));
+    let carves: Vec<(Point, Dir)> = events.iter().filter_map(|&event| match event {
+      GenerationEvent::Carve(pt, dir) => Some((pt, dir)),
+      _ => None
+    }).collect();
+    assert_eq!((events.len(), carves.len()), (41, 41));
+    // The carves in order rebuild the same maze
+    let replayed = GenerationState::replay(7, 6, &carves).expect("").pop().expect("").maze;
+    assert_eq!((replayed.east_walls, replayed.south_walls), (m.east_walls.clone(), m.south_walls.clone()));
+
+    // Recursive division opens everything up, then builds walls back
+    let mut walls = 0;
+    let mut carves = 0;
+    let mut m = Maze::new(7, 6).expect("");
+    m.generate_observed(Algorithm::RecursiveDivision, &mut StdRng::seed_from_u64(2), |event| match event {
+      GenerationEvent::Wall(_, _) => walls += 1,
+      _ => carves += 1
+    });
+    assert_eq!(carves - walls, m.passage_count());
+    assert!(walls > 0);
+
+    // Every generator makes the same maze watched as not, the ones that
+    // grow a cell at a time visit every cell once, and the backtracker says
+    // what BacktrackerSteps does
+    for &algorithm in Algorithm::ALL.iter() {
+      let mut watched = Maze::new(7, 6).expect("");
+      let mut events = Vec::new();
+      watched.generate_observed(algorithm, &mut StdRng::seed_from_u64(5), |event| events.push(event));
+      let mut unwatched = Maze::new(7, 6).expect("");
+      unwatched.generate(algorithm, &mut StdRng::seed_from_u64(5));
+      assert_eq!((&watched.east_walls, &watched.south_walls), (&unwatched.east_walls, &unwatched.south_walls),
+                 "{:?}", algorithm);
+      assert_eq!(watched.algorithm(), Some(algorithm));
+      let visits = events.iter().filter(|event| matches!(event, GenerationEvent::Visit(_))).count();
+      assert!(visits == 0 || visits == 42, "{:?}", algorithm);
+    }
+    let mut events = Vec::new();
+    Maze::new(5, 4).expect("").generate_observed(Algorithm::RecursiveBacktracker, &mut StdRng::seed_from_u64(1),
+                                                 |event| events.push(event));
+    let mut m = Maze::new(5, 4).expect("");
+    assert_eq!(events, BacktrackerSteps::new(&mut m, StdRng::seed_from_u64(1)).collect::<Vec<_>>());
+  }
+}
@@ -0,0 +1,284 @@
+//! Grids in general: what every kind of maze has in common, and the
+//! generators and solvers that don't need to know any more than that.
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Debug;
+use std::hash::Hash;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use crate::{BoundsError, Dir, Maze, Point};
+
+/// Cells, which of them are next to each other, and which of those have
+/// passages between them. Rectangular, masked, hex, polar, triangle and
+/// multi-level mazes are all grids, so the functions in this module work on
+/// any of them.
+pub trait Grid {
+  type Cell: Copy + Eq + Hash + Debug;
+
+  /// Room for how many cells; every cell's index is below this
+  fn size(&self) -> usize;
+
+  /// A number for each cell, different for every one, for keeping things
+  /// about cells in a Vec
+  fn index(&self, cell: Self::Cell) -> usize;
+
+  /// Every cell in the grid
+  fn cells(&self) -> Vec<Self::Cell>;
+
+  /// The cells next to this one, whether or not there's a wall between
+  fn neighbors(&self, cell: Self::Cell) -> Vec<Self::Cell>;
+
+  /// Whether there's a passage between two cells
+  fn linked(&self, a: Self::Cell, b: Self::Cell) -> bool;
+
+  /// Opens a passage between two neighboring cells
+  fn link(&mut self, a: Self::Cell, b: Self::Cell) -> Result<(), BoundsError>;
+
+  /// Puts back the wall between two neighboring cells
+  fn unlink(&mut self, a: Self::Cell, b: Self::Cell) -> Result<(), BoundsError>;
+
+  /// The cells joined to this one by passages
+  fn links(&self, cell: Self::Cell) -> Vec<Self::Cell> {
+    self.neighbors(cell).into_iter().filter(|&n| self.linked(cell, n)).collect()
+  }
+
+  /// A cell picked at random, or None if there aren't any
+  fn random_cell<R: Rng>(&self, rng: &mut R) -> Option<Self::Cell> {
+    self.cells().choose(rng).copied()
+  }
+}
+
+impl Grid for Maze {
+  type Cell = Point;
+
+  fn size(&self) -> usize {
+    self.width * self.height
+  }
+
+  fn index(&self, cell: Point) -> usize {
+    Maze::index(self, cell)
+  }
+
+  // A masked maze's cells are only the ones in its mask
+  fn cells(&self) -> Vec<Point> {
+    self.iter().filter(|&pt| self.in_maze(pt)).collect()
+  }
+
+  fn neighbors(&self, cell: Point) -> Vec<Point> {
+    Dir::ALL.iter().filter_map(|&dir| self.neighbor(cell, dir)).collect()
+  }
+
+  fn linked(&self, a: Point, b: Point) -> bool {
+    self.direction_between(a, b).is_some_and(|dir| self.passage(a, dir))
+  }
+
+  fn link(&mut self, a: Point, b: Point) -> Result<(), BoundsError> {
+    self.carve(a, self.direction_between(a, b).ok_or(BoundsError)?)
+  }
+
+  fn unlink(&mut self, a: Point, b: Point) -> Result<(), BoundsError> {
+    self.uncarve(a, self.direction_between(a, b).ok_or(BoundsError)?)
+  }
+
+  fn links(&self, cell: Point) -> Vec<Point> {
+    self.open_neighbors(cell)
+  }
+
+  fn random_cell<R: Rng>(&self, rng: &mut R) -> Option<Point> {
+    Some(Maze::random_cell(self, rng))
+  }
+}
+
+/// Depth-first search from a random cell, linking it to a random unvisited
+/// neighbor each step and backing up when there isn't one
+pub fn recursive_backtracker<G: Grid>(grid: &mut G, rng: &mut impl Rng) {
+  let start = match grid.random_cell(rng) {
+    Some(start) => start,
+    None => return
+  };
+  let mut visited = vec![false; grid.size()];
+  let mut stack = vec![start];
+  visited[grid.index(start)] = true;
+
+  while let Some(&cell) = stack.last() {
+    let unvisited: Vec<G::Cell> = grid.neighbors(cell).into_iter()
+      .filter(|&n| !visited[grid.index(n)])
+      .collect();
+
+    if let Some(&n) = unvisited.choose(rng) {
+      grid.link(cell, n).expect("");
+      visited[grid.index(n)] = true;
+      stack.push(n);
+    } else {
+      stack.pop();
+    }
+  }
+}
+
+/// Wilson's algorithm: random walks, with their loops erased, from every
+/// cell not yet in the tree until they hit it. Every spanning tree of the
+/// grid is equally likely.
+pub fn wilsons<G: Grid>(grid: &mut G, rng: &mut impl Rng) {
+  let cells = grid.cells();
+  wilson_walks(grid, rng, &cells, |grid, cell| grid.neighbors(cell), |_, _, _| {});
+}
+
+// Wilson's algorithm over the given cells, stepping between them with
+// `neighbors`: start the tree at a random cell, then from every cell not
+// yet in it take a random walk until hitting the tree, and link the walk
+// with its loops erased, telling `linked` about each link as it's made
+pub(crate) fn wilson_walks<G: Grid>(grid: &mut G, rng: &mut impl Rng, cells: &[G::Cell],
+                                    neighbors: impl Fn(&G, G::Cell) -> Vec<G::Cell>,
+                                    mut linked: impl FnMut(&G, G::Cell, G::Cell)) {
+  let mut in_tree = vec![false; grid.size()];
+  match cells.choose(rng) {
+    Some(&root) => in_tree[grid.index(root)] = true,
+    None => return
+  }
+
+  for &start in cells {
+    if in_tree[grid.index(start)] { continue }
+    let mut walk = vec![start];
+    let mut cell = start;
+    while !in_tree[grid.index(cell)] {
+      cell = *neighbors(grid, cell).choose(rng).expect("");
+      walk.push(cell);
+    }
+
+    let path = erase_loops(&walk);
+    for step in path.windows(2) {
+      in_tree[grid.index(step[0])] = true;
+      grid.link(step[0], step[1]).expect("");
+      linked(grid, step[0], step[1]);
+    }
+  }
+}
+
+// loop_erase for any kind of cell
+pub(crate) fn erase_loops<T: Copy + Eq + Hash>(walk: &[T]) -> Vec<T> {
+  let mut path = Vec::new();
+  let mut positions: HashMap<T, usize> = HashMap::new();
+  for &cell in walk {
+    match positions.get(&cell) {
+      Some(&i) => {
+        for dropped in path.drain(i + 1..) {
+          positions.remove(&dropped);
+        }
+      }
+      None => {
+        positions.insert(cell, path.len());
+        path.push(cell);
+      }
+    }
+  }
+  path
+}
+
+/// Dijkstra's distances from `start`, in steps, to every cell that can be
+/// reached from it. Every passage is one step, so it's a breadth-first
+/// search.
+pub fn distances<G: Grid>(grid: &G, start: G::Cell) -> HashMap<G::Cell, usize> {
+  let mut distances = HashMap::new();
+  let mut queue = VecDeque::new();
+  distances.insert(start, 0);
+  queue.push_back(start);
+
+  while let Some(cell) = queue.pop_front() {
+    let d = distances[&cell];
+    for n in grid.links(cell) {
+      if let Entry::Vacant(entry) = distances.entry(n) {
+        entry.insert(d + 1);
+        queue.push_back(n);
+      }
+    }
+  }
+  distances
+}
+
+/// A shortest route from `start` to `goal` along passages, both ends
+/// included, or None if there isn't one
+pub fn solve<G: Grid>(grid: &G, start: G::Cell, goal: G::Cell) -> Option<Vec<G::Cell>> {
+  let mut parents: HashMap<G::Cell, Option<G::Cell>> = HashMap::new();
+  let mut queue = VecDeque::new();
+  parents.insert(start, None);
+  queue.push_back(start);
+
+  while let Some(cell) = queue.pop_front() {
+    if cell == goal {
+      let mut path = vec![goal];
+      while let Some(&Some(parent)) = parents.get(path.last().expect("")) {
+        path.push(parent);
+      }
+      path.reverse();
+      return Some(path)
+    }
+    for n in grid.links(cell) {
+      if let Entry::Vacant(entry) = parents.entry(n) {
+        entry.insert(Some(cell));
+        queue.push_back(n);
+      }
+    }
+  }
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{HexMaze, Mask, Maze3D, PolarMaze, TriMaze};
+  use rand::rngs::StdRng;
+  use rand::SeedableRng;
+
+  // Every cell joined up, with exactly one route between any two
+  fn is_tree<G: Grid>(grid: &G) -> bool {
+    let cells = grid.cells();
+    let links: usize = cells.iter().map(|&cell| grid.links(cell).len()).sum();
+    links / 2 == cells.len() - 1 && distances(grid, cells[0]).len() == cells.len()
+  }
+
+  fn generate_and_solve<G: Grid>(mut grid: G, seed: u64) {
+    let cells = grid.cells();
+    let (start, goal) = (cells[0], cells[cells.len() - 1]);
+    let mut rng = StdRng::seed_from_u64(seed);
+    recursive_backtracker(&mut grid, &mut rng);
+    assert!(is_tree(&grid));
+    let path = solve(&grid, start, goal).expect("");
+    assert_eq!(path.len(), distances(&grid, start)[&goal] + 1);
+    for step in path.windows(2) { assert!(grid.linked(step[0], step[1])) }
+
+    for &cell in cells.iter() {
+      for n in grid.links(cell) { grid.unlink(cell, n).expect("") }
+    }
+    assert!(cells.iter().all(|&cell| grid.links(cell).is_empty()));
+    assert_eq!(solve(&grid, start, goal), None);
+    wilsons(&mut grid, &mut rng);
+    assert!(is_tree(&grid));
+  }
+
+  #[test]
+  fn grid_generic_test() {
+    generate_and_solve(Maze::new(7, 5).expect(""), 1);
+    generate_and_solve(Maze::new_masked(Mask::from_text("..X..\n.....\nX...X\n").expect("")).expect(""), 2);
+    generate_and_solve(HexMaze::new(6, 5).expect(""), 3);
+    generate_and_solve(PolarMaze::new(5).expect(""), 4);
+    generate_and_solve(TriMaze::new(7, 4).expect(""), 5);
+    generate_and_solve(Maze3D::new(4, 3, 3).expect(""), 6);
+  }
+
+  #[test]
+  fn grid_link_test() {
+    let mut m = Maze::new(3, 3).expect("");
+    let p = |x, y| Point { x, y };
+    m.link(p(0, 0), p(1, 0)).expect("");
+    assert!(m.passage(p(0, 0), Dir::East));
+    assert!(Grid::linked(&m, p(1, 0), p(0, 0)));
+    assert!(m.link(p(0, 0), p(2, 2)).is_err());
+    m.unlink(p(1, 0), p(0, 0)).expect("");
+    assert_eq!(m.passage_count(), 0);
+
+    let mut hex = HexMaze::new(3, 3).expect("");
+    hex.link(p(1, 1), p(2, 1)).expect("");
+    assert_eq!(solve(&hex, p(2, 1), p(1, 1)), Some(vec![p(2, 1), p(1, 1)]));
+  }
+}
@@ -0,0 +1,309 @@
+//! Hex mazes: six-sided cells in a rectangular block.
+
+use rand::Rng;
+use crate::{grid, BoundsError, Grid, Point};
+
+/// The six ways out of a flat-topped hexagon
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum HexDir { North, NorthEast, SouthEast, South, SouthWest, NorthWest }
+
+/// A maze of flat-topped hexagons, `width` columns of `height` cells. Cells
+/// are numbered by column and row like a rectangular maze's, with every odd
+/// column sitting half a cell lower than the even ones beside it; to_axial
+/// and from_axial convert to and from axial coordinates for hex arithmetic.
+#[derive(Debug, Clone)]
+pub struct HexMaze {
+  width: usize,
+  height: usize,
+  // Three walls per cell, its South, SouthEast and NorthEast ones; every
+  // other wall is one of those belonging to a neighbor
+  walls: Vec<bool>,
+}
+
+impl HexDir {
+  pub const ALL: [HexDir; 6] = [
+    HexDir::North, HexDir::NorthEast, HexDir::SouthEast, HexDir::South, HexDir::SouthWest, HexDir::NorthWest,
+  ];
+
+  pub fn opposite(self) -> HexDir {
+    match self {
+      HexDir::North => HexDir::South,
+      HexDir::NorthEast => HexDir::SouthWest,
+      HexDir::SouthEast => HexDir::NorthWest,
+      HexDir::South => HexDir::North,
+      HexDir::SouthWest => HexDir::NorthEast,
+      HexDir::NorthWest => HexDir::SouthEast
+    }
+  }
+}
+
+impl HexMaze {
+  /// A hex maze with every wall up
+  pub fn new(width: usize, height: usize) -> Result<HexMaze, BoundsError> {
+    if width == 0 || height == 0 { return Err(BoundsError) }
+    Ok(HexMaze { width, height, walls: vec![true; width * height * 3] })
+  }
+
+  pub fn width(&self) -> usize {
+    self.width
+  }
+
+  pub fn height(&self) -> usize {
+    self.height
+  }
+
+  pub fn valid(&self, point: Point) -> bool {
+    point.x < self.width && point.y < self.height
+  }
+
+  /// Every cell, a column at a time within each row, top row first
+  pub fn iter(&self) -> impl Iterator<Item = Point> + '_ {
+    (0..self.height).flat_map(move |y| (0..self.width).map(move |x| Point { x, y }))
+  }
+
+  /// The cell on the `dir` side of this one, if it's in the maze
+  pub fn neighbor(&self, point: Point, dir: HexDir) -> Option<Point> {
+    if !self.valid(point) { return None }
+    let Point { x, y } = point;
+    // An odd column's diagonal neighbors are a row further down
+    let odd = x % 2;
+    let n = match dir {
+      HexDir::North => Point { x, y: y.checked_sub(1)? },
+      HexDir::South => Point { x, y: y + 1 },
+      HexDir::NorthEast => Point { x: x + 1, y: (y + odd).checked_sub(1)? },
+      HexDir::SouthEast => Point { x: x + 1, y: y + odd },
+      HexDir::NorthWest => Point { x: x.checked_sub(1)?, y: (y + odd).checked_sub(1)? },
+      HexDir::SouthWest => Point { x: x.checked_sub(1)?, y: y + odd }
+    };
+    Some(n).filter(|&n| self.valid(n))
+  }
+
+  // Where the wall on the `dir` side of a cell is kept
+  fn wall_index(&self, point: Point, dir: HexDir) -> Option<usize> {
+    let n = self.neighbor(point, dir)?;
+    let (owner, side) = match dir {
+      HexDir::South | HexDir::SouthEast | HexDir::NorthEast => (point, dir),
+      _ => (n, dir.opposite())
+    };
+    let k = match side { HexDir::South => 0, HexDir::SouthEast => 1, _ => 2 };
+    Some((owner.x + owner.y * self.width) * 3 + k)
+  }
+
+  /// Whether there's an opening on the `dir` side of a cell. The outside
+  /// edge of the maze is all wall.
+  pub fn passage(&self, point: Point, dir: HexDir) -> bool {
+    self.wall_index(point, dir).is_some_and(|i| !self.walls[i])
+  }
+
+  pub fn carve(&mut self, point: Point, dir: HexDir) -> Result<(), BoundsError> {
+    self.set_wall(point, dir, false)
+  }
+
+  /// Puts a wall back up on the `dir` side of a cell
+  pub fn uncarve(&mut self, point: Point, dir: HexDir) -> Result<(), BoundsError> {
+    self.set_wall(point, dir, true)
+  }
+
+  fn set_wall(&mut self, point: Point, dir: HexDir, wall: bool) -> Result<(), BoundsError> {
+    let i = self.wall_index(point, dir).ok_or(BoundsError)?;
+    self.walls[i] = wall;
+    Ok(())
+  }
+
+  /// Which way to step from `from` to reach `to`, if they're neighbors
+  pub fn direction_between(&self, from: Point, to: Point) -> Option<HexDir> {
+    HexDir::ALL.iter().copied().find(|&dir| self.neighbor(from, dir) == Some(to))
+  }
+
+  /// The cells joined to this one by passages
+  pub fn open_neighbors(&self, point: Point) -> Vec<Point> {
+    HexDir::ALL.iter().filter(|&&dir| self.passage(point, dir)).filter_map(|&dir| self.neighbor(point, dir)).collect()
+  }
+
+  /// A cell's axial coordinates (q, r): q is the column, and r counts rows
+  /// along the line running down to the south-west
+  pub fn to_axial(point: Point) -> (i64, i64) {
+    let (x, y) = (point.x as i64, point.y as i64);
+    (x, y - (x - (x & 1)) / 2)
+  }
+
+  /// The cell at axial coordinates (q, r), if there is one with
+  /// non-negative numbers; it may still be outside any particular maze
+  pub fn from_axial(q: i64, r: i64) -> Option<Point> {
+    let y = r + (q - (q & 1)) / 2;
+    if q < 0 || y < 0 { return None }
+    Some(Point { x: q as usize, y: y as usize })
+  }
+
+  /// Steps between two cells if there were no walls
+  pub fn distance(a: Point, b: Point) -> usize {
+    let ((aq, ar), (bq, br)) = (HexMaze::to_axial(a), HexMaze::to_axial(b));
+    let (dq, dr) = (aq - bq, ar - br);
+    ((dq.abs() + dr.abs() + (dq + dr).abs()) / 2) as usize
+  }
+
+  /// The recursive backtracker, as on a rectangular maze, with six ways to
+  /// go from each cell instead of four
+  pub fn recursive_backtracker(&mut self, rng: &mut impl Rng) {
+    grid::recursive_backtracker(self, rng)
+  }
+
+  /// Draws the maze as text, each hexagon as
+  ///
+  /// ```text
+  ///  __
+  /// /  \
+  /// \__/
+  /// ```
+  ///
+  /// with neighboring hexagons sharing sides, and spaces for passages
+  pub fn render(&self) -> String {
+    let rows = 2 * self.height + if self.width > 1 { 2 } else { 1 };
+    let mut text = vec![vec![' '; 3 * self.width + 1]; rows];
+    for pt in self.iter() {
+      let (col, top) = (3 * pt.x, 2 * pt.y + pt.x % 2);
+      let mut draw = |dir: HexDir, row: usize, cols: &[usize], c: char| {
+        if !self.passage(pt, dir) {
+          for &at in cols { text[row][at] = c }
+        }
+      };
+      draw(HexDir::North, top, &[col + 1, col + 2], '_');
+      draw(HexDir::NorthWest, top + 1, &[col], '/');
+      draw(HexDir::NorthEast, top + 1, &[col + 3], '\\');
+      draw(HexDir::SouthWest, top + 2, &[col], '\\');
+      draw(HexDir::SouthEast, top + 2, &[col + 3], '/');
+      draw(HexDir::South, top + 2, &[col + 1, col + 2], '_');
+    }
+    text.iter().map(|line| line.iter().collect::<String>().trim_end().to_string() + "\n").collect()
+  }
+
+  /// Draws the walls as SVG lines, each hexagon `size` from its middle to
+  /// each corner
+  pub fn to_svg(&self, size: f64) -> String {
+    let h = size * 3f64.sqrt() / 2.0;
+    let width = size * (1.5 * self.width as f64 + 0.5);
+    let height = h * (2 * self.height + if self.width > 1 { 1 } else { 0 }) as f64;
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.1}\" height=\"{:.1}\" viewBox=\"0 0 {:.1} {:.1}\">\n",
+                          width, height, width, height);
+    for pt in self.iter() {
+      let (cx, cy) = (size + 1.5 * size * pt.x as f64, h * (1 + 2 * pt.y + pt.x % 2) as f64);
+      // Corners clockwise from the east one
+      let corner = |i: usize| {
+        let angle = std::f64::consts::PI / 3.0 * i as f64;
+        (cx + size * angle.cos(), cy + size * angle.sin())
+      };
+      let sides = [(HexDir::SouthEast, 0), (HexDir::South, 1), (HexDir::SouthWest, 2),
+                   (HexDir::NorthWest, 3), (HexDir::North, 4), (HexDir::NorthEast, 5)];
+      for &(dir, i) in sides.iter() {
+        // Each wall between two cells is drawn by the cell that keeps it
+        let owned = matches!(dir, HexDir::South | HexDir::SouthEast | HexDir::NorthEast);
+        if !self.passage(pt, dir) && (owned || self.neighbor(pt, dir).is_none()) {
+          let ((x1, y1), (x2, y2)) = (corner(i), corner((i + 1) % 6));
+          svg.push_str(&format!("<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"black\" stroke-width=\"2\"/>\n",
+                                x1, y1, x2, y2));
+        }
+      }
+    }
+    svg.push_str("</svg>\n");
+    svg
+  }
+}
+
+impl Grid for HexMaze {
+  type Cell = Point;
+
+  fn size(&self) -> usize {
+    self.width * self.height
+  }
+
+  fn index(&self, cell: Point) -> usize {
+    cell.x + cell.y * self.width
+  }
+
+  fn cells(&self) -> Vec<Point> {
+    self.iter().collect()
+  }
+
+  fn neighbors(&self, cell: Point) -> Vec<Point> {
+    HexDir::ALL.iter().filter_map(|&dir| self.neighbor(cell, dir)).collect()
+  }
+
+  fn linked(&self, a: Point, b: Point) -> bool {
+    self.direction_between(a, b).is_some_and(|dir| self.passage(a, dir))
+  }
+
+  fn link(&mut self, a: Point, b: Point) -> Result<(), BoundsError> {
+    self.carve(a, self.direction_between(a, b).ok_or(BoundsError)?)
+  }
+
+  fn unlink(&mut self, a: Point, b: Point) -> Result<(), BoundsError> {
+    self.uncarve(a, self.direction_between(a, b).ok_or(BoundsError)?)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::rngs::StdRng;
+  use rand::SeedableRng;
+
+  #[test]
+  fn hex_neighbor_test() {
+    let m = HexMaze::new(4, 4).expect("");
+    let p = |x, y| Point { x, y };
+    let around = |pt| HexDir::ALL.iter().map(|&dir| m.neighbor(pt, dir)).collect::<Vec<_>>();
+    assert_eq!(around(p(2, 1)), vec![Some(p(2, 0)), Some(p(3, 0)), Some(p(3, 1)), Some(p(2, 2)), Some(p(1, 1)), Some(p(1, 0))]);
+    assert_eq!(around(p(1, 1)), vec![Some(p(1, 0)), Some(p(2, 1)), Some(p(2, 2)), Some(p(1, 2)), Some(p(0, 2)), Some(p(0, 1))]);
+    assert_eq!(around(p(0, 0)), vec![None, None, Some(p(1, 0)), Some(p(0, 1)), None, None]);
+
+    // Going there and back again
+    for pt in m.iter() {
+      for &dir in HexDir::ALL.iter() {
+        if let Some(n) = m.neighbor(pt, dir) {
+          assert_eq!(m.neighbor(n, dir.opposite()), Some(pt));
+          assert_eq!(HexMaze::distance(pt, n), 1);
+        }
+      }
+      let (q, r) = HexMaze::to_axial(pt);
+      assert_eq!(HexMaze::from_axial(q, r), Some(pt));
+    }
+    assert_eq!(HexMaze::distance(p(0, 0), p(3, 3)), 5);
+    assert_eq!(HexMaze::from_axial(0, -1), None);
+  }
+
+  #[test]
+  fn hex_walls_test() {
+    let mut m = HexMaze::new(3, 3).expect("");
+    let p = |x, y| Point { x, y };
+    m.carve(p(1, 1), HexDir::NorthWest).expect("");
+    assert!(m.passage(p(0, 1), HexDir::SouthEast));
+    assert!(!m.passage(p(1, 1), HexDir::North));
+    assert!(m.carve(p(0, 0), HexDir::North).is_err());
+    assert_eq!(m.open_neighbors(p(1, 1)), vec![p(0, 1)]);
+
+    let mut m = HexMaze::new(8, 6).expect("");
+    m.recursive_backtracker(&mut StdRng::seed_from_u64(2));
+    assert_eq!(m.walls.iter().filter(|&&w| !w).count(), 47);
+    let mut reached = vec![p(0, 0)];
+    let mut i = 0;
+    while i < reached.len() {
+      for n in m.open_neighbors(reached[i]) {
+        if !reached.contains(&n) { reached.push(n) }
+      }
+      i += 1;
+    }
+    assert_eq!(reached.len(), 48);
+  }
+
+  #[test]
+  fn hex_render_test() {
+    let mut m = HexMaze::new(2, 1).expect("");
+    assert_eq!(m.render(), " __\n/  \\__\n\\__/  \\\n   \\__/\n");
+    m.carve(Point { x: 0, y: 0 }, HexDir::SouthEast).expect("");
+    assert_eq!(m.render(), " __\n/  \\__\n\\__   \\\n   \\__/\n");
+
+    let svg = m.to_svg(10.0);
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"35.0\" height=\"26.0\""));
+    assert_eq!(svg.matches("<line").count(), 10);
+  }
+}
@@ -0,0 +1,120 @@
+//! A spatial index over cells.
+
+use crate::Point;
+
+/// A 2-d tree over a set of points, answering nearest-point and
+/// within-radius queries by Manhattan distance without checking every point.
+/// Built once; points can't be added later.
+#[derive(Debug, Clone)]
+pub struct KdTree {
+  root: Option<Box<KdNode>>,
+}
+
+#[derive(Debug, Clone)]
+struct KdNode {
+  point: Point,
+  // Splits on x at even depths, y at odd ones
+  left: Option<Box<KdNode>>,
+  right: Option<Box<KdNode>>,
+}
+
+impl KdTree {
+  pub fn new(points: Vec<Point>) -> KdTree {
+    KdTree { root: KdTree::build(points, 0) }
+  }
+
+  fn build(mut points: Vec<Point>, depth: usize) -> Option<Box<KdNode>> {
+    if points.is_empty() { return None }
+    points.sort_by_key(|pt| if depth.is_multiple_of(2) { (pt.x, pt.y) } else { (pt.y, pt.x) });
+    let right = points.split_off(points.len() / 2 + 1);
+    let point = points.pop().expect("");
+    Some(Box::new(KdNode {
+      point,
+      left: KdTree::build(points, depth + 1),
+      right: KdTree::build(right, depth + 1),
+    }))
+  }
+
+  /// The closest point in the tree to `query`. Panics on an empty tree,
+  /// which a maze can't produce.
+  pub fn nearest(&self, query: Point) -> Point {
+    let mut best = None;
+    KdTree::search_nearest(&self.root, query, 0, &mut best);
+    best.expect("nearest on an empty KdTree").0
+  }
+
+  fn search_nearest(node: &Option<Box<KdNode>>, query: Point, depth: usize, best: &mut Option<(Point, usize)>) {
+    let node = match node { Some(node) => node, None => return };
+    let d = node.point.distance(query);
+    if best.is_none_or(|(_, b)| d < b) {
+      *best = Some((node.point, d));
+    }
+
+    let (q, p) = if depth.is_multiple_of(2) { (query.x, node.point.x) } else { (query.y, node.point.y) };
+    let (near, far) = if q < p { (&node.left, &node.right) } else { (&node.right, &node.left) };
+    KdTree::search_nearest(near, query, depth + 1, best);
+    // The far side can only hold something closer if the splitting line is
+    if best.is_none_or(|(_, b)| q.abs_diff(p) <= b) {
+      KdTree::search_nearest(far, query, depth + 1, best);
+    }
+  }
+
+  /// Every point within `radius` of `query`, in no particular order
+  pub fn within_radius(&self, query: Point, radius: usize) -> Vec<Point> {
+    let mut found = Vec::new();
+    KdTree::search_radius(&self.root, query, radius, 0, &mut found);
+    found
+  }
+
+  fn search_radius(node: &Option<Box<KdNode>>, query: Point, radius: usize, depth: usize, found: &mut Vec<Point>) {
+    let node = match node { Some(node) => node, None => return };
+    if node.point.distance(query) <= radius {
+      found.push(node.point);
+    }
+
+    let (q, p) = if depth.is_multiple_of(2) { (query.x, node.point.x) } else { (query.y, node.point.y) };
+    if q <= p + radius {
+      KdTree::search_radius(&node.left, query, radius, depth + 1, found);
+    }
+    if q + radius >= p {
+      KdTree::search_radius(&node.right, query, radius, depth + 1, found);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Maze;
+
+  #[test]
+  fn kd_tree_test() {
+    let m = Maze::new(5, 5).expect("");
+    let all = m.build_kd_tree();
+    assert_eq!(all.nearest(Point { x: 3, y: 2 }), Point { x: 3, y: 2 });
+    assert_eq!(all.within_radius(Point { x: 0, y: 0 }, 1).len(), 3);
+    assert_eq!(all.within_radius(Point { x: 2, y: 2 }, 2).len(), 13);
+
+    let points = vec![Point { x: 0, y: 0 }, Point { x: 4, y: 4 }, Point { x: 1, y: 3 }, Point { x: 4, y: 0 }];
+    let tree = KdTree::new(points.clone());
+    assert_eq!(tree.nearest(Point { x: 3, y: 1 }), Point { x: 4, y: 0 });
+    assert_eq!(tree.nearest(Point { x: 0, y: 1 }), Point { x: 0, y: 0 });
+    assert_eq!(tree.nearest(Point { x: 2, y: 4 }), Point { x: 1, y: 3 });
+    assert_eq!(tree.nearest(Point { x: 4, y: 3 }), Point { x: 4, y: 4 });
+
+    let mut near = tree.within_radius(Point { x: 2, y: 2 }, 2);
+    near.sort_by_key(|pt| (pt.y, pt.x));
+    assert_eq!(near, vec![Point { x: 1, y: 3 }]);
+
+    // Check against a brute-force scan
+    for q in m.iter() {
+      let d = tree.nearest(q).distance(q);
+      assert_eq!(d, points.iter().map(|p| p.distance(q)).min().expect(""));
+      let mut found = tree.within_radius(q, 3);
+      let mut expected: Vec<Point> = points.iter().copied().filter(|p| p.distance(q) <= 3).collect();
+      found.sort_by_key(|pt| (pt.y, pt.x));
+      expected.sort_by_key(|pt| (pt.y, pt.x));
+      assert_eq!(found, expected);
+    }
+  }
+}
@@ -0,0 +1,85 @@
+//! A value for every cell of a maze.
+
+use std::ops::{Index, IndexMut};
+use crate::{Maze, Point};
+
+/// A value attached to every cell of a maze, indexed by Point. Besides the
+/// analyses' numbers, it's where to keep game data like items or monsters
+/// in each cell: see Maze::cell_data, and render_data to draw it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelMap<T> {
+  pub(crate) width: usize,
+  pub(crate) height: usize,
+  pub(crate) labels: Vec<T>,
+}
+
+impl<T: Clone> LabelMap<T> {
+  pub fn new(width: usize, height: usize, label: T) -> LabelMap<T> {
+    LabelMap { width, height, labels: vec![label; width * height] }
+  }
+}
+
+impl<T> LabelMap<T> {
+  pub fn width(&self) -> usize {
+    self.width
+  }
+
+  pub fn height(&self) -> usize {
+    self.height
+  }
+
+  pub fn get(&self, point: Point) -> Option<&T> {
+    if point.x < self.width && point.y < self.height {
+      self.labels.get(point.x + point.y * self.width)
+    } else {
+      None
+    }
+  }
+
+  /// Every cell's point and value, in row-major order
+  pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> + '_ {
+    self.labels.iter().enumerate().map(move |(i, label)| (Point { x: i % self.width, y: i / self.width }, label))
+  }
+}
+
+impl Maze {
+  /// A map the size of the maze with `value` in every cell, for attaching
+  /// whatever the maze is for to its cells
+  pub fn cell_data<T: Clone>(&self, value: T) -> LabelMap<T> {
+    LabelMap::new(self.width, self.height, value)
+  }
+}
+
+impl<T> Index<Point> for LabelMap<T> {
+  type Output = T;
+  fn index(&self, point: Point) -> &T {
+    assert!(point.x < self.width && point.y < self.height, "{:?} is outside the map", point);
+    &self.labels[point.x + point.y * self.width]
+  }
+}
+
+impl<T> IndexMut<Point> for LabelMap<T> {
+  fn index_mut(&mut self, point: Point) -> &mut T {
+    assert!(point.x < self.width && point.y < self.height, "{:?} is outside the map", point);
+    &mut self.labels[point.x + point.y * self.width]
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn cell_data_test() {
+    let m = Maze::new(3, 2).expect("");
+    let mut items: LabelMap<Vec<&str>> = m.cell_data(Vec::new());
+    items[Point { x: 2, y: 1 }].push("sword");
+    items[Point { x: 2, y: 1 }].push("shield");
+    assert_eq!((items.width(), items.height()), (3, 2));
+    assert_eq!(items.get(Point { x: 2, y: 1 }).map(|stack| stack.len()), Some(2));
+    assert_eq!(items.get(Point { x: 3, y: 1 }), None);
+    let full: Vec<Point> = items.iter().filter(|(_, stack)| !stack.is_empty()).map(|(pt, _)| pt).collect();
+    assert_eq!(full, vec![Point { x: 2, y: 1 }]);
+    assert_eq!(items.iter().count(), 6);
+  }
+}
@@ -0,0 +1,67 @@
+//! Rectangular mazes: generating them, solving them, measuring them and
+//! drawing them.
+//!
+//! A [`Maze`] starts out with every wall up; a generator then carves
+//! passages through it.
+//!
+//! ```
+//! use maze::{Maze, Point};
+//!
+//! let mut maze = Maze::new(8, 8).unwrap();
+//! maze.recursive_backtracker(&mut rand::thread_rng());
+//! assert!(maze.path_exists(Point { x: 0, y: 0 }, Point { x: 7, y: 7 }));
+//! print!("{}", maze);
+//! ```
+
+mod analysis;
+mod ansi;
+mod astar;
+mod bits;
+mod drawing;
+mod formats;
+mod generate;
+pub mod grid;
+mod hex;
+mod kd_tree;
+mod label_map;
+mod mask;
+mod maze;
+mod maze3d;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod point;
+mod polar;
+#[cfg(feature = "image")]
+mod raster;
+mod render;
+mod room;
+mod solve;
+mod tri;
+pub mod union_find;
+mod weave;
+
+pub use crate::analysis::{DistanceMap, MazeStats, ValidationError};
+pub use crate::ansi::AnsiOptions;
+pub use crate::astar::AStarResult;
+pub use crate::drawing::{Heatmap, SvgOptions};
+pub use crate::generate::{
+  loop_erase, Algorithm, BacktrackerSteps, BiasError, EllerRows, EllersStream, GenerationEvent, GenerationState,
+  GrowingTreeStrategy, FRACTAL_SUBDIVISION,
+};
+pub use crate::grid::Grid;
+pub use crate::hex::{HexDir, HexMaze};
+pub use crate::kd_tree::KdTree;
+pub use crate::label_map::LabelMap;
+pub use crate::mask::Mask;
+pub use crate::maze::{BoundsError, Maze, MazeError, MazeIterator, ParseError, Topology};
+pub use crate::maze3d::{Dir3D, Maze3D, Point3D};
+pub use crate::polar::{PolarDir, PolarMaze};
+pub use crate::point::{Cell, Dir, Path, Point, SymmetryAxis};
+#[cfg(feature = "image")]
+pub use crate::raster::{GifOptions, ImageMaskOptions, ImageStyle};
+pub use crate::render::RenderStyle;
+pub use crate::room::Room;
+pub use crate::tri::{TriMaze, UnsupportedAlgorithm};
+pub use crate::union_find::UnionFind;
+pub use crate::solve::{InteractiveSolver, MoveError, SolutionScore, SolveFrame, SolveIter, SolveStep};
+pub use crate::weave::WeaveMaze;
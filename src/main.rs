@@ -1,276 +1,326 @@
-use rand::prelude::*;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process;
+use std::thread;
+use std::time::Duration;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use maze::{Algorithm, GenerationEvent, Maze, RenderStyle, SvgOptions};
+
+#[cfg(feature = "crossterm")]
+mod play;
+
+const USAGE: &str = "usage: maze [play] [--width N] [--height N] [--algorithm NAME] [--seed N]
+            [--format FORMAT] [--output PATH] [--animate MS]
+
+  play              walk through the maze in the terminal with the arrow keys,
+                    from the entrance to the exit, instead of printing it;
+                    needs the crossterm feature, and draws in ascii unless
+                    the format is unicode
+
+  --width N         cells across (default 8)
+  --height N        cells down (default 8)
+  --algorithm NAME  which generator to use (default binary-tree)
+  --seed N          the same seed always makes the same maze (default random,
+                    and printed to stderr so it can be used again)
+  --format FORMAT   ascii, unicode, svg, png or json (default ascii); png
+                    needs the image feature and json the serde feature
+  --output PATH     where to write the maze (default stdout)
+  --animate MS      draw the maze being generated, again after every wall
+                    that comes down or goes up, MS milliseconds apart; in
+                    ascii unless the format is unicode. Without --output the
+                    last frame is the maze.";
 
-#[derive(Debug)]
-struct Maze {
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Format { Ascii, Unicode, Svg, Png, Json }
+
+#[derive(Debug, PartialEq)]
+struct Options {
   width: usize,
   height: usize,
-  east_walls: Vec<bool>,
-  south_walls: Vec<bool>,
+  algorithm: Algorithm,
+  seed: Option<u64>,
+  format: Format,
+  output: Option<String>,
+  play: bool,
+  animate: Option<u64>,
 }
 
-struct MazeIterator<'a> { maze: &'a Maze, n: usize }
-
-#[derive(Debug)]
-struct BoundsError;
-
-#[derive(Debug, Copy, Clone, PartialEq)]
-struct Point { pub x: usize, pub y: usize }
-
-#[derive(Debug, Copy, Clone, PartialEq)]
-struct Cell { pub north: bool, pub east: bool, pub south: bool, pub west: bool }
-
-#[derive(Debug, Copy, Clone, PartialEq)]
-enum Dir { North, South, East, West }
-
-impl Maze {
-  fn new(width: usize, height: usize) -> Result<Maze, BoundsError> {
-    if width > 0 && height > 0 {
-      Ok(Maze {
-        width, height,
-        east_walls: vec![true; height * (width - 1)],
-        south_walls: vec![true; width * (height - 1)]
-      })
-    } else {
-      Err(BoundsError)
+fn main() {
+  let options = match parse_args(env::args().skip(1)) {
+    Ok(Some(options)) => options,
+    Ok(None) => {
+      println!("{}\n\n{}", USAGE, algorithm_list());
+      return
+    },
+    Err(message) => {
+      eprintln!("maze: {}\n{}", message, USAGE);
+      process::exit(2)
     }
+  };
+
+  let seed = options.seed.unwrap_or_else(|| {
+    let seed = rand::thread_rng().gen();
+    eprintln!("seed: {}", seed);
+    seed
+  });
+  let mut m = Maze::new(options.width, options.height).expect("");
+  let mut rng = StdRng::seed_from_u64(seed);
+  match options.animate {
+    Some(delay) => animate(&mut m, options.algorithm, &mut rng, delay, text_style(options.format)),
+    None => m.generate(options.algorithm, &mut rng)
   }
 
-  fn valid(&self, point: Point) -> bool {
-    point.x < self.width && point.y < self.height
-  }
-
-  fn edge(&self, point: Point) -> bool {
-    self.valid(point) && (
-      point.x == 0 || point.y == 0 ||
-        point.x == self.width - 1 || point.y == self.height - 1)
-  }
-
-  fn corner(&self, point: Point) -> bool {
-    (point.x == 0 || point.x == self.width - 1) &&
-      (point.y == 0 || point.y == self.height - 1)
-  }
-
-  fn neighbor(&self, point: Point, dir: Dir) -> Option<Point> {
-    let n = point.translate(dir)?;
-    if self.valid(n) {
-      Some(n)
-    } else {
-      None
+  if options.play {
+    m.place_entrance_and_exit().expect("");
+    match play_maze(&m, text_style(options.format)) {
+      Ok(Some(moves)) => println!("out in {} moves", moves),
+      Ok(None) => {},
+      Err(e) => {
+        eprintln!("maze: {}", e);
+        process::exit(1)
+      }
     }
+    return
+  }
+  if options.animate.is_some() && options.output.is_none() { return }
+
+  let written = match &options.output {
+    Some(path) => File::create(path).map_err(|e| format!("can't write {}: {}", path, e))
+      .and_then(|file| write_maze(&m, options.format, &mut BufWriter::new(file))),
+    None => write_maze(&m, options.format, &mut io::stdout().lock())
+  };
+  if let Err(message) = written {
+    eprintln!("maze: {}", message);
+    process::exit(1)
   }
+}
 
-  fn nth_point(&self, n: usize) -> Option<Point> {
-    let pt = Point { x: n % self.width, y: n / self.width };
-    if self.valid(pt) {
-      Some(pt)
-    } else {
-      None
+// Generates the maze, drawing it on stdout after every change to its walls.
+// The generator has the maze while it works, so what's drawn is a copy
+// kept in step from the events.
+fn animate(m: &mut Maze, algorithm: Algorithm, rng: &mut impl Rng, delay: u64, style: RenderStyle) {
+  let mut shown = Maze::new(m.width(), m.height()).expect("");
+  let mut lines = 0;
+  let mut draw = move |shown: &Maze| {
+    let frame = shown.render_styled(style);
+    // Back up over the last frame to draw this one in its place
+    if lines > 0 { print!("\x1b[{}A", lines) }
+    print!("{}", frame);
+    io::stdout().flush().ok();
+    lines = frame.lines().count();
+  };
+  draw(&shown);
+  m.generate_observed(algorithm, rng, move |event| {
+    match event {
+      GenerationEvent::Carve(pt, dir) => shown.carve(pt, dir).expect(""),
+      GenerationEvent::Wall(pt, dir) => shown.uncarve(pt, dir).expect(""),
+      _ => return
     }
-  }
+    thread::sleep(Duration::from_millis(delay));
+    draw(&shown);
+  });
+}
 
-  fn iter(&self) -> MazeIterator {
-    MazeIterator { maze: &self, n: 0 }
-  }
+// What play and --animate draw in: box-drawing lines if asked, or else
+// plain text, since the other formats aren't for terminals
+fn text_style(format: Format) -> RenderStyle {
+  if format == Format::Unicode { RenderStyle::Unicode } else { RenderStyle::Ascii }
+}
 
-  fn passage(&self, point: Point, dir: Dir) -> bool {
-    if let Some(_) = self.neighbor(point, dir) {
-      match dir {
-        Dir::North => !self.south_walls[point.x + self.width * (point.y - 1)],
-        Dir::South => !self.south_walls[point.x + self.width * point.y],
-        Dir::East => !self.east_walls[point.x + (self.width - 1) * point.y],
-        Dir::West => !self.east_walls[point.x - 1 + (self.width - 1) * point.y],
-      }
-    } else {
-      false
-    }
-  }
+fn write_maze(m: &Maze, format: Format, out: &mut impl Write) -> Result<(), String> {
+  match format {
+    Format::Ascii => m.write_styled(out, RenderStyle::Ascii).map_err(|e| e.to_string()),
+    Format::Unicode => m.write_styled(out, RenderStyle::Unicode).map_err(|e| e.to_string()),
+    Format::Svg => out.write_all(m.to_svg(&SvgOptions::default()).as_bytes()).map_err(|e| e.to_string()),
+    Format::Png => write_png(m, out),
+    Format::Json => write_json(m, out)
+  }?;
+  out.flush().map_err(|e| e.to_string())
+}
 
-  fn cell(&self, point: Point) -> Cell {
-    Cell {
-      north: self.passage(point, Dir::North),
-      south: self.passage(point, Dir::South),
-      east: self.passage(point, Dir::East),
-      west: self.passage(point, Dir::West)
-    }
-  }
+// parse_args turns down the formats there's no support built in for, so
+// these never get called without it
+#[cfg(feature = "image")]
+fn write_png(m: &Maze, out: &mut impl Write) -> Result<(), String> {
+  m.write_png(out).map_err(|e| e.to_string())
+}
 
-  fn carve(&mut self, point: Point, dir: Dir) -> Result<(), BoundsError> {
-    if let Some(_) = self.neighbor(point, dir) {
-      Ok(match dir {
-        Dir::North => self.south_walls[point.x + self.width * (point.y - 1)] = false,
-        Dir::South => self.south_walls[point.x + self.width * point.y] = false,
-        Dir::East => self.east_walls[point.x + (self.width - 1) * point.y] = false,
-        Dir::West => self.east_walls[point.x - 1 + (self.width - 1) * point.y] = false
-      })
-    } else {
-      Err(BoundsError)
-    }
-  }
+#[cfg(not(feature = "image"))]
+fn write_png(_: &Maze, _: &mut impl Write) -> Result<(), String> {
+  unreachable!()
+}
 
-  fn char(&self, point: Point, dir: Dir) -> &str {
-    if self.passage(point, dir) {
-      " "
-    } else {
-      match dir {
-        Dir::North | Dir::South => "-",
-        Dir::East | Dir::West => "|"
-      }
-    }
-  }
+#[cfg(feature = "crossterm")]
+fn play_maze(m: &Maze, style: RenderStyle) -> io::Result<Option<usize>> {
+  play::play(m, style)
+}
 
-  fn print(&self) {
-    // First print a line of norths
-    for x in 0..(self.width) {
-      print!("+");
-      print!("{}", self.char(Point{x, y: 0}, Dir::North))
-    }
-    println!("+");
+#[cfg(not(feature = "crossterm"))]
+fn play_maze(_: &Maze, _: RenderStyle) -> io::Result<Option<usize>> {
+  unreachable!()
+}
 
-    // Then a loop for each row...
-    for y in 0..(self.height) {
-      // printing the first west, then all easts
-      print!("{}", self.char(Point{x: 0, y}, Dir::West));
-      for x in 0..(self.width) {
-        print!(" ");
-        print!("{}", self.char(Point{x, y}, Dir::East));
-      }
-      println!("");
-      // Then all souths
-      for x in 0..(self.width) {
-        print!("+");
-        print!("{}", self.char(Point{x, y}, Dir::South));
-      }
-      println!("+");
-    }
-  }
+#[cfg(feature = "serde")]
+fn write_json(m: &Maze, out: &mut impl Write) -> Result<(), String> {
+  serde_json::to_writer(&mut *out, m).map_err(|e| e.to_string())?;
+  writeln!(out).map_err(|e| e.to_string())
+}
 
-  fn binary_tree(&mut self) {
-    for i in (0..(self.width * self.height)).into_iter() {
-      if let Some(pt) = self.nth_point(i) {
-        let n = self.neighbor(pt, Dir::North).is_some();
-        let e = self.neighbor(pt, Dir::East).is_some();
+#[cfg(not(feature = "serde"))]
+fn write_json(_: &Maze, _: &mut impl Write) -> Result<(), String> {
+  unreachable!()
+}
 
-        if n && !e {
-          self.carve(pt, Dir::North).expect("");
-        } else if e && !n {
-          self.carve(pt, Dir::East).expect("");
-        } else if n && e {
-          if rand::random() {
-            self.carve(pt, Dir::North).expect("");
-          } else {
-            self.carve(pt, Dir::East).expect("");
-          }
-        }
-      }
+// The options asked for, or None if the user wants the help text
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+  let mut options = Options {
+    width: 8, height: 8, algorithm: Algorithm::BinaryTree, seed: None, format: Format::Ascii, output: None,
+    play: false, animate: None,
+  };
+  let mut args = args.peekable();
+  if args.peek().is_some_and(|arg| arg == "play") {
+    if !cfg!(feature = "crossterm") { return Err("play needs maze built with the crossterm feature".to_string()) }
+    options.play = true;
+    args.next();
+  }
+  while let Some(arg) = args.next() {
+    // Both "--width 10" and "--width=10"
+    let (flag, inline) = match arg.split_once('=') {
+      Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+      None => (arg.clone(), None)
+    };
+    if flag == "--help" || flag == "-h" { return Ok(None) }
+    let value = inline.or_else(|| args.next()).ok_or(format!("{} needs a value", flag))?;
+    match flag.as_str() {
+      "--width" => options.width = size(&flag, &value)?,
+      "--height" => options.height = size(&flag, &value)?,
+      "--algorithm" => options.algorithm = algorithm_named(&value)
+        .ok_or(format!("no algorithm called {:?}; try --help for a list", value))?,
+      "--seed" => options.seed = Some(value.parse()
+        .map_err(|_| format!("--seed has to be a whole number, not {:?}", value))?),
+      "--format" => options.format = format_named(&value)?,
+      "--output" => options.output = Some(value),
+      "--animate" => options.animate = Some(value.parse()
+        .map_err(|_| format!("--animate has to be a whole number of milliseconds, not {:?}", value))?),
+      _ => return Err(format!("unknown option {}", flag))
     }
   }
+  Ok(Some(options))
 }
 
-impl<'a> Iterator for MazeIterator<'a> {
-  type Item = Point;
-  fn next(&mut self) -> Option<Point> {
-    let pt = self.maze.nth_point(self.n);
-    self.n += 1;
-    pt
+fn format_named(name: &str) -> Result<Format, String> {
+  match name {
+    "ascii" => Ok(Format::Ascii),
+    "unicode" => Ok(Format::Unicode),
+    "svg" => Ok(Format::Svg),
+    "png" if cfg!(feature = "image") => Ok(Format::Png),
+    "png" => Err("png output needs maze built with the image feature".to_string()),
+    "json" if cfg!(feature = "serde") => Ok(Format::Json),
+    "json" => Err("json output needs maze built with the serde feature".to_string()),
+    _ => Err(format!("no format called {:?}", name))
   }
 }
 
-impl Point {
-  fn translate(&self, dir: Dir) -> Option<Point> {
-    match dir {
-      Dir::North => Some(Point { x: self.x, y: self.y.checked_sub(1)? }),
-      Dir::South => Some(Point { x: self.x, y: self.y + 1 }),
-      Dir::East => Some(Point { x: self.x + 1, y: self.y }),
-      Dir::West => Some(Point { x: self.x.checked_sub(1)?, y: self.y })
-    }
+fn size(flag: &str, value: &str) -> Result<usize, String> {
+  match value.parse() {
+    Ok(n) if n > 0 => Ok(n),
+    _ => Err(format!("{} has to be a whole number above zero, not {:?}", flag, value))
   }
 }
 
-#[test]
-fn maze_point_tests() {
-  let m = Maze::new(5,5).expect("");
-  assert!(m.valid(Point{x: 2, y: 3}));
-  assert!(! m.valid(Point{x: 2, y: 20}));
-  assert!(m.valid(Point{x: 2, y: 4}));
-  assert!(! m.valid(Point{x: 2, y: 5}));
-
-  assert!(m.edge(Point{x: 0, y: 3}));
-  assert!(m.edge(Point{x: 2, y: 0}));
-  assert!(m.edge(Point{x: 4, y: 2}));
-  assert!(m.edge(Point{x: 3, y: 4}));
-  assert!(! m.edge(Point{x: 3, y: 2}));
-
-  assert!(m.corner(Point{x: 0, y: 0}));
-  assert!(! m.corner(Point{x: 3, y: 2}));
-  assert!(m.corner(Point{x: 4, y: 0}));
-  assert!(m.corner(Point{x: 0, y: 4}));
+// Algorithm names without the capitals, so "RecursiveBacktracker" is
+// "recursive-backtracker" or "recursivebacktracker", and a few short ones
+fn algorithm_named(name: &str) -> Option<Algorithm> {
+  let squashed: String = name.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase();
+  match squashed.as_str() {
+    "backtracker" | "dfs" => Some(Algorithm::RecursiveBacktracker),
+    "division" => Some(Algorithm::RecursiveDivision),
+    "eller" => Some(Algorithm::Ellers),
+    "wilson" => Some(Algorithm::Wilsons),
+    _ => Algorithm::ALL.iter().copied().find(|a| a.name().to_lowercase() == squashed)
+  }
 }
 
-#[test]
-fn point_translate_test() {
-  let p = Point { x: 1, y: 1 };
-
-  assert_eq!(p.translate(Dir::North), Some(Point { x: 1, y: 0 }));
-  assert_eq!(p.translate(Dir::South), Some(Point { x: 1, y: 2 }));
-  assert_eq!(p.translate(Dir::East), Some(Point { x: 2, y: 1 }));
-  assert_eq!(p.translate(Dir::West), Some(Point { x: 0, y: 1 }));
-
-  let p2 = Point { x: 0, y: 0 };
-  assert_eq!(p2.translate(Dir::North), None);
+fn algorithm_list() -> String {
+  let names: Vec<String> = Algorithm::ALL.iter().map(|a| kebab(a.name())).collect();
+  format!("algorithms: {}", names.join(", "))
 }
 
-#[test]
-fn maze_neighbor_test() {
-  let m = Maze::new(5,5).expect("");
-  let p = Point { x: 0, y: 0 };
-
-  assert_eq!(m.neighbor(p, Dir::North), None);
-  assert_eq!(m.neighbor(p, Dir::East), Some(Point{ x: 1, y: 0 }));
+// "HuntAndKill" to "hunt-and-kill"
+fn kebab(name: &str) -> String {
+  let mut out = String::new();
+  for (i, c) in name.chars().enumerate() {
+    if c.is_uppercase() && i > 0 { out.push('-') }
+    out.extend(c.to_lowercase());
+  }
+  out
 }
 
-#[test]
-fn maze_iterator_test() {
-  let m = Maze::new(5, 3).expect("");
-  assert_eq!(m.iter().count(), 15);
-  assert_eq!(m.iter().filter(|p| m.corner(*p)).count(), 4);
-  assert_eq!(m.iter().filter(|p| m.edge(*p)).count(), 12)
-}
+#[cfg(test)]
+mod tests {
+  use super::*;
 
-#[test]
-fn maze_carve_passage_test() {
-  let mut m = Maze::new(2,2).expect("");
-  m.carve(Point { x: 0, y: 0 }, Dir::South);
-  m.carve(Point { x: 0, y: 1 }, Dir::East);
-  assert!(m.passage(Point { x: 0, y: 0 }, Dir::South));
-  assert!(m.passage(Point { x: 0, y: 1 }, Dir::North));
-  assert!(m.passage(Point { x: 0, y: 1 }, Dir::East));
-  assert!(!m.passage(Point { x: 0, y: 0 }, Dir::East));
-  assert!(!m.passage(Point { x: 0, y: 0 }, Dir::West));
+  fn parse(args: &[&str]) -> Result<Option<Options>, String> {
+    parse_args(args.iter().map(|s| s.to_string()))
+  }
 
-  let mut m = Maze::new(2,2).expect("");
-  m.carve(Point { x: 0, y: 1 }, Dir::North);
-  m.carve(Point { x: 1, y: 0 }, Dir::West);
-  assert!(m.passage(Point { x: 0, y: 0 }, Dir::South));
-  assert!(m.passage(Point { x: 0, y: 1 }, Dir::North));
-  assert!(m.passage(Point { x: 0, y: 0 }, Dir::East));
-  assert!(m.passage(Point { x: 1, y: 0 }, Dir::West));
-}
+  #[test]
+  fn parse_args_test() {
+    assert_eq!(parse(&[]), Ok(Some(Options {
+      width: 8, height: 8, algorithm: Algorithm::BinaryTree, seed: None, format: Format::Ascii, output: None,
+      play: false, animate: None,
+    })));
+    assert_eq!(parse(&["--width", "20", "--height=5", "--algorithm", "backtracker", "--seed", "42",
+                       "--format", "svg", "--output", "maze.svg"]),
+               Ok(Some(Options {
+                 width: 20, height: 5, algorithm: Algorithm::RecursiveBacktracker, seed: Some(42),
+                 format: Format::Svg, output: Some("maze.svg".to_string()), play: false, animate: None,
+               })));
+    assert_eq!(parse(&["--animate", "40"]).map(|o| o.map(|o| o.animate)), Ok(Some(Some(40))));
+    assert!(parse(&["--animate", "fast"]).is_err());
+    assert_eq!(parse(&["--algorithm=hunt-and-kill"]).map(|o| o.map(|o| o.algorithm)),
+               Ok(Some(Algorithm::HuntAndKill)));
+    assert_eq!(parse(&["--width", "3", "--help"]), Ok(None));
+
+    assert!(parse(&["--width"]).is_err());
+    assert!(parse(&["--width", "0"]).is_err());
+    assert!(parse(&["--height", "tall"]).is_err());
+    assert!(parse(&["--algorithm", "magic"]).is_err());
+    assert!(parse(&["--colour", "red"]).is_err());
+    assert!(parse(&["--seed", "-1"]).is_err());
+    assert!(parse(&["--format", "gif"]).is_err());
+    assert_eq!(parse(&["--format", "png"]).is_ok(), cfg!(feature = "image"));
+    assert_eq!(parse(&["--format", "json"]).is_ok(), cfg!(feature = "serde"));
+    assert_eq!(parse(&["play", "--width", "4"]).map(|o| o.map(|o| (o.play, o.width))).ok(),
+               if cfg!(feature = "crossterm") { Some(Some((true, 4))) } else { None });
+    assert!(parse(&["--width", "4", "play"]).is_err());
+  }
 
-#[test]
-fn maze_cell_test() {
-  let mut m = Maze::new(2,2).expect("");
-  m.carve(Point { x: 0, y: 1 }, Dir::North);
-  m.carve(Point { x: 1, y: 0 }, Dir::West);
-  assert_eq!(m.cell(Point { x: 0, y: 0 }),
-             Cell { north: false, east: true, south: true, west: false });
-  assert_eq!(m.cell(Point { x: 0, y: 1 }),
-             Cell { north: true, east: false, south: false, west: false });
-  assert_eq!(m.cell(Point { x: 1, y: 1 }),
-             Cell { north: false, east: false, south: false, west: false });
-}
+  #[test]
+  fn write_maze_test() {
+    let m = Maze::new_seeded_square(3, 1).expect("");
+    let write = |format| {
+      let mut out = Vec::new();
+      write_maze(&m, format, &mut out).expect("");
+      String::from_utf8_lossy(&out).into_owned()
+    };
+    assert_eq!(write(Format::Ascii), m.render_ascii());
+    assert_eq!(write(Format::Unicode), m.render_styled(RenderStyle::Unicode));
+    assert!(write(Format::Svg).starts_with("<svg"));
+    if cfg!(feature = "image") { assert!(write(Format::Png).starts_with("\u{fffd}PNG")) }
+    if cfg!(feature = "serde") { assert!(write(Format::Json).starts_with("{\"width\":3,")) }
+  }
 
-fn main() {
-  let mut m = Maze::new(8,8).expect("");
-  m.binary_tree();
-  m.print();
+  #[test]
+  fn algorithm_named_test() {
+    for &algorithm in Algorithm::ALL.iter() {
+      assert_eq!(algorithm_named(algorithm.name()), Some(algorithm));
+      assert_eq!(algorithm_named(&kebab(algorithm.name())), Some(algorithm));
+    }
+    assert_eq!(algorithm_named("Sidewinder"), Some(Algorithm::Sidewinder));
+    assert_eq!(algorithm_named("aldous_broder"), Some(Algorithm::AldousBroder));
+    assert_eq!(kebab("HuntAndKill"), "hunt-and-kill");
+  }
 }
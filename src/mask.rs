@@ -0,0 +1,236 @@
+//! Masks, for mazes that aren't rectangles.
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+use crate::{BoundsError, Dir, Maze, ParseError, Point, Topology};
+
+/// Which cells of a grid are part of a maze. A maze made with
+/// Maze::new_masked only has the cells that are on: the others have no
+/// neighbors, so the generators never carve into them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mask {
+  pub(crate) width: usize,
+  pub(crate) height: usize,
+  pub(crate) cells: Vec<bool>,
+}
+
+impl Mask {
+  /// A mask with every cell on
+  pub fn new(width: usize, height: usize) -> Result<Mask, BoundsError> {
+    if width == 0 || height == 0 { return Err(BoundsError) }
+    Ok(Mask { width, height, cells: vec![true; width * height] })
+  }
+
+  pub fn width(&self) -> usize {
+    self.width
+  }
+
+  pub fn height(&self) -> usize {
+    self.height
+  }
+
+  /// Whether a cell is on; points outside the mask are off
+  pub fn get(&self, point: Point) -> bool {
+    point.x < self.width && point.y < self.height && self.cells[point.x + point.y * self.width]
+  }
+
+  pub fn set(&mut self, point: Point, on: bool) -> Result<(), BoundsError> {
+    if point.x >= self.width || point.y >= self.height { return Err(BoundsError) }
+    self.cells[point.x + point.y * self.width] = on;
+    Ok(())
+  }
+
+  /// How many cells are on
+  pub fn count(&self) -> usize {
+    self.cells.iter().filter(|&&on| on).count()
+  }
+
+  /// The cells that are on, in row-major order
+  pub fn points(&self) -> Vec<Point> {
+    (0..self.cells.len())
+      .filter(|&i| self.cells[i])
+      .map(|i| Point { x: i % self.width, y: i / self.width })
+      .collect()
+  }
+
+  /// A mask drawn as text, a line per row: `.` for a cell in the maze and
+  /// `X` for one that isn't
+  pub fn from_text(text: &str) -> Result<Mask, ParseError> {
+    Mask::from_text_with(text, 'X', '.')
+  }
+
+  /// A mask drawn as text with `off` and `on` for the cells out of and in
+  /// the maze. Every line has to be the same length; blank lines at the
+  /// end are ignored, and any other character is an error.
+  pub fn from_text_with(text: &str, off: char, on: char) -> Result<Mask, ParseError> {
+    let mut lines: Vec<Vec<char>> = text.lines().map(|line| line.chars().collect()).collect();
+    while lines.last().is_some_and(|line| line.is_empty()) { lines.pop(); }
+    let width = lines.first().map_or(0, |line| line.len());
+    if lines.iter().any(|line| line.len() != width) { return Err(ParseError::Dimensions) }
+
+    let mut mask = Mask::new(width, lines.len()).map_err(|_| ParseError::Dimensions)?;
+    for (row, line) in lines.iter().enumerate() {
+      for (col, &c) in line.iter().enumerate() {
+        match c {
+          _ if c == on => {},
+          _ if c == off => mask.set(Point { x: col, y: row }, false).expect(""),
+          _ => return Err(ParseError::InvalidValue { row, col })
+        }
+      }
+    }
+    Ok(mask)
+  }
+
+  /// Whether the cells that are on are all in one piece, each reachable
+  /// from the others by steps north, south, east or west through cells
+  /// that are on
+  pub fn is_connected(&self) -> bool {
+    let points = self.points();
+    let first = match points.first() {
+      Some(&first) => first,
+      None => return true
+    };
+    let mut seen = vec![false; self.cells.len()];
+    seen[first.x + first.y * self.width] = true;
+    let mut stack = vec![first];
+    let mut reached = 1;
+    while let Some(pt) = stack.pop() {
+      for &dir in Dir::ALL.iter() {
+        if let Some(n) = pt.translate(dir).filter(|&n| self.get(n)) {
+          if !seen[n.x + n.y * self.width] {
+            seen[n.x + n.y * self.width] = true;
+            reached += 1;
+            stack.push(n);
+          }
+        }
+      }
+    }
+    reached == points.len()
+  }
+
+  /// One of the cells that are on, picked at random
+  pub fn random_point(&self, rng: &mut impl Rng) -> Option<Point> {
+    self.points().choose(rng).copied()
+  }
+}
+
+impl Maze {
+  /// A maze the size of `mask` with every wall up, whose cells are just the
+  /// ones the mask has on. Leaves of a heart or the hole in a donut are
+  /// cells switched off. At least one cell has to be on, and the ones that
+  /// are have to be in one piece, or no generator could join them all up.
+  pub fn new_masked(mask: Mask) -> Result<Maze, BoundsError> {
+    if mask.count() == 0 || !mask.is_connected() { return Err(BoundsError) }
+    let mut maze = Maze::with_topology(mask.width, mask.height, Topology::Plane)?;
+    maze.mask = Some(mask);
+    Ok(maze)
+  }
+
+  /// The mask the maze was made with, if any
+  pub fn mask(&self) -> Option<&Mask> {
+    self.mask.as_ref()
+  }
+
+  // Where a generator that starts anywhere should start: any cell, or in
+  // a masked maze any cell the mask has on
+  pub(crate) fn random_cell(&self, rng: &mut impl Rng) -> Point {
+    match &self.mask {
+      Some(mask) => mask.random_point(rng).expect(""),
+      None => Point { x: rng.gen_range(0, self.width), y: rng.gen_range(0, self.height) }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Algorithm;
+  use rand::rngs::StdRng;
+  use rand::SeedableRng;
+
+  // A 7x7 ring, with the middle 3x3 switched off
+  fn donut() -> Mask {
+    Mask::from_text(".......\n\
+                     .......\n\
+                     ..XXX..\n\
+                     ..XXX..\n\
+                     ..XXX..\n\
+                     .......\n\
+                     .......\n").expect("")
+  }
+
+  #[test]
+  fn mask_test() {
+    let mask = donut();
+    assert_eq!(mask.count(), 40);
+    assert!(mask.get(Point { x: 1, y: 1 }));
+    assert!(!mask.get(Point { x: 3, y: 3 }));
+    assert!(!mask.get(Point { x: 7, y: 0 }));
+    assert_eq!(mask.points().len(), 40);
+    assert!(Mask::new(0, 3).is_err());
+    assert!(Mask::new(2, 2).expect("").set(Point { x: 2, y: 0 }, false).is_err());
+
+    let m = Maze::new_masked(mask.clone()).expect("");
+    assert_eq!(m.mask(), Some(&mask));
+    assert!(!m.in_maze(Point { x: 2, y: 2 }));
+    assert_eq!(m.neighbor(Point { x: 1, y: 2 }, Dir::East), None);
+    assert_eq!(m.neighbor(Point { x: 3, y: 3 }, Dir::North), None);
+    assert_eq!(m.neighbor(Point { x: 1, y: 2 }, Dir::West), Some(Point { x: 0, y: 2 }));
+
+    let mut off = Mask::new(2, 2).expect("");
+    for pt in off.points() { off.set(pt, false).expect("") }
+    assert!(Maze::new_masked(off).is_err());
+  }
+
+  #[test]
+  fn mask_connected_test() {
+    assert!(donut().is_connected());
+    assert!(Mask::from_text("X....\n.....\n").expect("").is_connected());
+    // Split in two, and a cell on its own in a corner
+    let split = Mask::from_text("..X..\n..X..\n").expect("");
+    let island = Mask::from_text(".X...\nX....\n").expect("");
+    for mask in [split, island].iter() {
+      assert!(!mask.is_connected());
+      assert!(Maze::new_masked(mask.clone()).is_err());
+    }
+    assert!(Maze::new_masked(Mask::from_text("X\n.\n").expect("")).is_ok());
+  }
+
+  #[test]
+  fn mask_from_text_test() {
+    let mut donut_by_hand = Mask::new(7, 7).expect("");
+    for y in 2..5 {
+      for x in 2..5 { donut_by_hand.set(Point { x, y }, false).expect("") }
+    }
+    assert_eq!(donut(), donut_by_hand);
+
+    let heart = Mask::from_text_with(" ## ## \r\n#######\r\n ##### \r\n   #   \r\n\r\n", ' ', '#').expect("");
+    assert_eq!((heart.width(), heart.height(), heart.count()), (7, 4, 17));
+    assert!(heart.get(Point { x: 3, y: 3 }));
+    assert!(!heart.get(Point { x: 3, y: 0 }));
+
+    assert_eq!(Mask::from_text("..\n...\n"), Err(ParseError::Dimensions));
+    assert_eq!(Mask::from_text(""), Err(ParseError::Dimensions));
+    assert_eq!(Mask::from_text("..\n.#\n"), Err(ParseError::InvalidValue { row: 1, col: 1 }));
+  }
+
+  #[test]
+  fn masked_generate_test() {
+    // The notch leaves a cell the binary tree can't open north or east from
+    let notched = Mask::from_text("...X.\n.....\n.X...\n").expect("");
+    for mask in [donut(), notched].iter() {
+      for &algorithm in Algorithm::ALL.iter() {
+        let mut m = Maze::new_masked(mask.clone()).expect("");
+        m.generate(algorithm, &mut StdRng::seed_from_u64(1));
+        assert_eq!(m.algorithm(), Some(algorithm));
+        // Every cell in the mask is joined up as a tree, and nothing carves
+        // into the hole
+        assert_eq!(m.passage_count(), mask.count() - 1, "{:?}", algorithm);
+        let reached = m.reachable_from(Point { x: 0, y: 0 });
+        for pt in m.iter() {
+          assert_eq!(reached[m.index(pt)], mask.get(pt), "{:?} {:?}", algorithm, pt);
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,899 @@
+//! The maze itself: its grid, its walls, and the basic questions about them.
+
+use std::collections::HashSet;
+use std::fmt;
+use crate::bits::BitVec;
+use crate::{Algorithm, Cell, Dir, Mask, Point, SymmetryAxis};
+
+/// A rectangular grid of cells with walls between them. With the "serde"
+/// feature it serializes as its size, the names of its topology and
+/// algorithm, its entrance and exit, and its walls (and mask, if it has one)
+/// packed into strings the same way as to_string_compact.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "crate::formats::MazeRepr", try_from = "crate::formats::MazeRepr"))]
+pub struct Maze {
+  pub(crate) width: usize,
+  pub(crate) height: usize,
+  pub(crate) east_walls: BitVec,
+  pub(crate) south_walls: BitVec,
+  pub(crate) entrance: Option<Point>,
+  pub(crate) exit: Option<Point>,
+  // Whichever generator was last run on the maze, if any
+  pub(crate) algorithm: Option<Algorithm>,
+  pub(crate) topology: Topology,
+  // Which cells are part of the maze, if not all of them
+  pub(crate) mask: Option<Mask>,
+  // Gaps knocked in the outside wall, as the cell and the side they're on
+  pub(crate) doors: Vec<(Point, Dir)>,
+}
+
+/// The cells of a maze in order; see Maze::iter
+pub struct MazeIterator<'a> { maze: &'a Maze, n: usize }
+
+/// A point or size that doesn't fit the maze
+#[derive(Debug)]
+pub struct BoundsError;
+
+/// Why a maze can't be solved
+#[derive(Debug, PartialEq)]
+pub enum MazeError {
+  MissingEntrance,
+  MissingExit,
+  NoPath,
+}
+
+/// Why a maze couldn't be read
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+  Dimensions,
+  Format,
+  InvalidValue { row: usize, col: usize },
+  UnsupportedVersion(u8),
+  /// A mask that leaves no cells, or leaves them in more than one piece
+  Mask,
+}
+
+/// How the edges of the grid join up. On a cylinder the east edge wraps
+/// around to the west one; on a torus the south edge wraps to the north too.
+/// A Mobius strip wraps east-west like a cylinder but upside down, so the
+/// east of row y leads to the west of row height - 1 - y.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Topology { Plane, Cylinder, Torus, Mobius }
+
+impl Maze {
+  /// A `width` x `height` maze with every wall up
+  pub fn new(width: usize, height: usize) -> Result<Maze, BoundsError> {
+    Maze::with_topology(width, height, Topology::Plane)
+  }
+
+  /// A `width` x `height` maze with no walls at all inside it, one big room
+  pub fn new_open(width: usize, height: usize) -> Result<Maze, BoundsError> {
+    let mut maze = Maze::new(width, height)?;
+    for (pt, dir) in maze.interior_edges() {
+      maze.carve(pt, dir).expect("");
+    }
+    Ok(maze)
+  }
+
+  pub fn width(&self) -> usize {
+    self.width
+  }
+
+  pub fn height(&self) -> usize {
+    self.height
+  }
+
+  /// The cell set with set_entrance or open_entrance, if any
+  pub fn entrance(&self) -> Option<Point> {
+    self.entrance
+  }
+
+  /// The cell set with set_exit or open_exit, if any
+  pub fn exit(&self) -> Option<Point> {
+    self.exit
+  }
+
+  /// Whichever generator was last run on the maze, if any
+  pub fn algorithm(&self) -> Option<Algorithm> {
+    self.algorithm
+  }
+
+  pub fn topology(&self) -> Topology {
+    self.topology
+  }
+
+  /// An unconnected maze whose edges join up as `topology` says. Wrapping
+  /// needs at least two cells to wrap between.
+  pub fn with_topology(width: usize, height: usize, topology: Topology) -> Result<Maze, BoundsError> {
+    let wraps_ok = match topology {
+      Topology::Plane => true,
+      Topology::Cylinder | Topology::Mobius => width > 1,
+      Topology::Torus => width > 1 && height > 1
+    };
+    if width > 0 && height > 0 && wraps_ok {
+      let mut maze = Maze {
+        width, height,
+        east_walls: BitVec::default(),
+        south_walls: BitVec::default(),
+        entrance: None,
+        exit: None,
+        algorithm: None,
+        topology,
+        mask: None,
+        doors: Vec::new(),
+      };
+      maze.east_walls = BitVec::new(height * maze.east_stride(), true);
+      maze.south_walls = BitVec::new(width * maze.south_rows(), true);
+      Ok(maze)
+    } else {
+      Err(BoundsError)
+    }
+  }
+
+  /// Copies of the maze laid side by side, `across` by `down`, as one flat
+  /// maze. A torus tiles seamlessly, every passage off an edge leading into
+  /// the next copy; a cylinder only does across, and a Mobius strip's
+  /// flipped edges don't meet their copies at all.
+  pub fn tiled(&self, across: usize, down: usize) -> Result<Maze, BoundsError> {
+    let mut tiles = Maze::new(self.width * across, self.height * down)?;
+    let local = |pt: Point| Point { x: pt.x % self.width, y: pt.y % self.height };
+    for pt in tiles.iter().collect::<Vec<Point>>() {
+      for &dir in [Dir::East, Dir::South].iter() {
+        if let Some(n) = tiles.neighbor(pt, dir) {
+          if self.passage(local(pt), dir) && self.neighbor(local(pt), dir) == Some(local(n)) {
+            tiles.carve(pt, dir)?;
+          }
+        }
+      }
+    }
+    Ok(tiles)
+  }
+
+  fn wraps_east_west(&self) -> bool {
+    self.topology != Topology::Plane
+  }
+
+  fn wraps_north_south(&self) -> bool {
+    self.topology == Topology::Torus
+  }
+
+  // East walls per row: one fewer than the width, unless the last column's
+  // east wall wraps around to the first
+  fn east_stride(&self) -> usize {
+    if self.wraps_east_west() { self.width } else { self.width - 1 }
+  }
+
+  // Rows of south walls, which likewise gets the bottom row's if it wraps
+  fn south_rows(&self) -> usize {
+    if self.wraps_north_south() { self.height } else { self.height - 1 }
+  }
+
+  // Where the wall on the `dir` side of a cell is stored: every wall
+  // belongs to the cell west or north of it, as that cell's east or south
+  fn wall_index(&self, point: Point, dir: Dir) -> Option<(Dir, usize)> {
+    if !self.valid(point) { return None }
+    let n = self.neighbor(point, dir)?;
+    let (owner, side) = match dir {
+      Dir::North => (n, Dir::South),
+      Dir::West => (n, Dir::East),
+      _ => (point, dir)
+    };
+    match side {
+      Dir::East => Some((Dir::East, owner.x + self.east_stride() * owner.y)),
+      _ => Some((Dir::South, owner.x + self.width * owner.y))
+    }
+  }
+
+  /// Whether the point is inside the maze
+  pub fn valid(&self, point: Point) -> bool {
+    point.x < self.width && point.y < self.height
+  }
+
+  /// Whether the point is one of the maze's cells: inside the grid, and in
+  /// its mask if it has one
+  pub fn in_maze(&self, point: Point) -> bool {
+    self.valid(point) && self.mask.as_ref().is_none_or(|mask| mask.get(point))
+  }
+
+  /// Whether the point is on the outside edge of the grid
+  pub fn edge(&self, point: Point) -> bool {
+    self.valid(point) && (
+      point.x == 0 || point.y == 0 ||
+        point.x == self.width - 1 || point.y == self.height - 1)
+  }
+
+  /// Whether the point is one of the four corners
+  pub fn corner(&self, point: Point) -> bool {
+    (point.x == 0 || point.x == self.width - 1) &&
+      (point.y == 0 || point.y == self.height - 1)
+  }
+
+  /// The cell next to `point` going `dir`, wrapping around the edges if
+  /// the topology does. Cells outside a masked maze's mask have no
+  /// neighbors, and aren't anyone's neighbor.
+  pub fn neighbor(&self, point: Point, dir: Dir) -> Option<Point> {
+    if self.valid(point) && !self.in_maze(point) { return None }
+    if self.valid(point) {
+      let (ew, ns) = (self.wraps_east_west(), self.wraps_north_south());
+      let wrapped_y = if self.topology == Topology::Mobius { self.height - 1 - point.y } else { point.y };
+      let wrapped = match dir {
+        Dir::East if ew && point.x == self.width - 1 => Some(Point { x: 0, y: wrapped_y }),
+        Dir::West if ew && point.x == 0 => Some(Point { x: self.width - 1, y: wrapped_y }),
+        Dir::South if ns && point.y == self.height - 1 => Some(Point { x: point.x, y: 0 }),
+        Dir::North if ns && point.y == 0 => Some(Point { x: point.x, y: self.height - 1 }),
+        _ => None
+      };
+      if let Some(n) = wrapped { return Some(n).filter(|&n| self.in_maze(n)) }
+    }
+    let n = point.translate(dir)?;
+    if self.in_maze(n) {
+      Some(n)
+    } else {
+      None
+    }
+  }
+
+  /// Which way to step from `from` to reach `to`, if they're neighbors.
+  /// Unlike Point::direction_to this knows about wrapping.
+  pub fn direction_between(&self, from: Point, to: Point) -> Option<Dir> {
+    Dir::ALL.iter().copied().find(|&dir| self.neighbor(from, dir) == Some(to))
+  }
+
+  /// The nth cell, counting along each row from the top left
+  pub fn nth_point(&self, n: usize) -> Option<Point> {
+    let pt = Point { x: n % self.width, y: n / self.width };
+    if self.valid(pt) {
+      Some(pt)
+    } else {
+      None
+    }
+  }
+
+  /// Every cell, a row at a time from the top left
+  pub fn iter(&self) -> MazeIterator<'_> {
+    MazeIterator { maze: self, n: 0 }
+  }
+
+  /// Whether the way out of `point` toward `dir` is open
+  pub fn passage(&self, point: Point, dir: Dir) -> bool {
+    match self.wall_index(point, dir) {
+      Some((Dir::East, n)) => !self.east_walls[n],
+      Some((_, n)) => !self.south_walls[n],
+      None => false
+    }
+  }
+
+  /// Which sides of a cell are open
+  pub fn cell(&self, point: Point) -> Cell {
+    Cell {
+      north: self.passage(point, Dir::North),
+      south: self.passage(point, Dir::South),
+      east: self.passage(point, Dir::East),
+      west: self.passage(point, Dir::West)
+    }
+  }
+
+  /// Knocks down the wall between a cell and its neighbor toward `dir`
+  pub fn carve(&mut self, point: Point, dir: Dir) -> Result<(), BoundsError> {
+    self.set_wall(point, dir, false)
+  }
+
+  /// The counterpart to carve: puts the wall back between two cells
+  pub fn uncarve(&mut self, point: Point, dir: Dir) -> Result<(), BoundsError> {
+    self.set_wall(point, dir, true)
+  }
+
+  pub(crate) fn set_wall(&mut self, point: Point, dir: Dir, wall: bool) -> Result<(), BoundsError> {
+    match self.wall_index(point, dir) {
+      Some((Dir::East, n)) => self.east_walls.set(n, wall),
+      Some((_, n)) => self.south_walls.set(n, wall),
+      None => return Err(BoundsError)
+    }
+    Ok(())
+  }
+
+  /// Closes every passage to and from the cells marked "0" in a CSV grid of
+  /// "0" (blocked) and "1" (open) values, one row per line, and masks them
+  /// out of the maze so the generators and checks pass them by. The grid has
+  /// to be exactly the size of the maze, and the open cells left have to be
+  /// in one piece; nothing is changed if it can't be read.
+  pub fn apply_mask_from_csv(&mut self, csv: &str) -> Result<(), ParseError> {
+    let rows: Vec<&str> = csv.lines().filter(|line| !line.trim().is_empty()).collect();
+    if rows.len() != self.height {
+      return Err(ParseError::Dimensions)
+    }
+
+    let mut blocked = Vec::new();
+    for (y, row) in rows.iter().enumerate() {
+      let values: Vec<&str> = row.split(',').collect();
+      if values.len() != self.width {
+        return Err(ParseError::Dimensions)
+      }
+
+      for (x, value) in values.iter().enumerate() {
+        match value.trim().trim_matches('"').parse::<u32>() {
+          Ok(0) => blocked.push(Point { x, y }),
+          Ok(1) => {},
+          _ => return Err(ParseError::InvalidValue { row: y, col: x })
+        }
+      }
+    }
+
+    let mut mask = self.mask.clone().unwrap_or(Mask::new(self.width, self.height).expect(""));
+    for &pt in blocked.iter() {
+      mask.set(pt, false).expect("");
+    }
+    if mask.count() == 0 || !mask.is_connected() { return Err(ParseError::Mask) }
+    for pt in blocked {
+      for &dir in Dir::ALL.iter() {
+        self.uncarve(pt, dir).ok();
+      }
+    }
+    self.mask = Some(mask);
+    Ok(())
+  }
+
+  pub(crate) fn index(&self, point: Point) -> usize {
+    point.x + point.y * self.width
+  }
+
+  /// All the cells one open passage away from this one
+  pub fn open_neighbors(&self, point: Point) -> Vec<Point> {
+    Dir::ALL.iter()
+      .filter(|&&dir| self.passage(point, dir))
+      .filter_map(|&dir| self.neighbor(point, dir))
+      .collect()
+  }
+
+  /// How many open passages lead out of a cell
+  pub fn degree(&self, point: Point) -> usize {
+    Dir::ALL.iter().filter(|&&dir| self.passage(point, dir)).count()
+  }
+
+  /// Where a point ends up reflected across `axis`, if that's still in the maze
+  pub fn mirror(&self, point: Point, axis: SymmetryAxis) -> Option<Point> {
+    let (x, y) = match axis {
+      SymmetryAxis::Horizontal => (self.width - 1 - point.x, point.y),
+      SymmetryAxis::Vertical => (point.x, self.height - 1 - point.y),
+      SymmetryAxis::Rotational180 => (self.width - 1 - point.x, self.height - 1 - point.y),
+      SymmetryAxis::Diagonal => (point.y, point.x)
+    };
+    Some(Point { x, y }).filter(|&pt| self.valid(pt))
+  }
+
+  /// Opens the mirror image of every passage, so the maze ends up symmetric
+  /// about the axis. This only ever adds passages, so it will usually add
+  /// loops too. Diagonal symmetry needs a square maze; passages whose mirror
+  /// would fall outside a non-square one are left alone.
+  pub fn symmetrize(&mut self, axis: SymmetryAxis) {
+    let passages: Vec<(Point, Dir)> = self.interior_edges().into_iter()
+      .filter(|&(pt, dir)| self.passage(pt, dir))
+      .collect();
+
+    for (pt, dir) in passages {
+      if let Some(m) = self.mirror(pt, axis) {
+        self.carve(m, dir.mirrored(axis)).ok();
+      }
+    }
+  }
+
+  /// How many walls between cells have been knocked down
+  pub fn passage_count(&self) -> usize {
+    self.total_interior_edges() - self.east_walls.count_ones() - self.south_walls.count_ones()
+  }
+
+  /// How many walls there are between cells, open or not
+  pub fn total_interior_edges(&self) -> usize {
+    self.east_walls.len() + self.south_walls.len()
+  }
+
+  /// How open the maze is: 0.0 with every wall up, 1.0 with none, and about
+  /// 0.5 for a large perfect maze. A 1x1 maze has no edges and counts as 0.0.
+  pub fn passage_ratio(&self) -> f64 {
+    if self.total_interior_edges() == 0 { return 0.0 }
+    self.passage_count() as f64 / self.total_interior_edges() as f64
+  }
+
+  /// Knocks down every wall between two cells of the region, making a room.
+  /// Walls between the region and the rest of the maze are left alone.
+  pub fn carve_all_passages_in(&mut self, region: &[Point]) {
+    self.set_walls_in(region, false)
+  }
+
+  /// Puts up every wall between two cells of the region, so no cell in it
+  /// connects directly to another. Passages out of the region are left alone.
+  pub fn close_all_passages_in(&mut self, region: &[Point]) {
+    self.set_walls_in(region, true)
+  }
+
+  /// Walls off every cell of the region, from each other and from the rest
+  /// of the maze
+  pub fn isolate_region(&mut self, region: &[Point]) {
+    for &pt in region {
+      for &dir in Dir::ALL.iter() {
+        self.uncarve(pt, dir).ok();
+      }
+    }
+  }
+
+  fn set_walls_in(&mut self, region: &[Point], wall: bool) {
+    let cells: HashSet<Point> = region.iter().copied().filter(|&pt| self.valid(pt)).collect();
+    for &pt in cells.iter() {
+      for &dir in [Dir::East, Dir::South].iter() {
+        if self.neighbor(pt, dir).is_some_and(|n| cells.contains(&n)) {
+          self.set_wall(pt, dir, wall).expect("");
+        }
+      }
+    }
+  }
+
+  /// Every pair of adjacent cells, as the cell and East or South
+  pub fn interior_edges(&self) -> Vec<(Point, Dir)> {
+    self.iter()
+      .flat_map(|pt| vec![(pt, Dir::East), (pt, Dir::South)])
+      .filter(|&(pt, dir)| self.neighbor(pt, dir).is_some())
+      .collect()
+  }
+
+  /// Marks the cell a solver starts from
+  pub fn set_entrance(&mut self, point: Point) -> Result<(), BoundsError> {
+    if !self.valid(point) { return Err(BoundsError) }
+    self.entrance = Some(point);
+    Ok(())
+  }
+
+  /// Marks the cell a solver is trying to reach
+  pub fn set_exit(&mut self, point: Point) -> Result<(), BoundsError> {
+    if !self.valid(point) { return Err(BoundsError) }
+    self.exit = Some(point);
+    Ok(())
+  }
+
+  /// Marks the entrance and knocks a gap in the outside wall on its `dir`
+  /// side, for the renderers to show where to come in. The cell has to be
+  /// on the edge of the grid with `dir` facing out of it; a side that wraps
+  /// around to the other edge doesn't count.
+  pub fn open_entrance(&mut self, point: Point, dir: Dir) -> Result<(), BoundsError> {
+    self.open_door(point, dir)?;
+    self.entrance = Some(point);
+    Ok(())
+  }
+
+  /// Marks the exit and knocks a gap in the outside wall, like open_entrance
+  pub fn open_exit(&mut self, point: Point, dir: Dir) -> Result<(), BoundsError> {
+    self.open_door(point, dir)?;
+    self.exit = Some(point);
+    Ok(())
+  }
+
+  fn open_door(&mut self, point: Point, dir: Dir) -> Result<(), BoundsError> {
+    if !self.faces_outside(point, dir) { return Err(BoundsError) }
+    if !self.is_door(point, dir) { self.doors.push((point, dir)) }
+    Ok(())
+  }
+
+  // Whether the `dir` side of a cell is the outside wall: nothing beyond it,
+  // not even the far edge of a wrapping maze. Next to a masked-out cell
+  // counts as outside.
+  pub(crate) fn faces_outside(&self, point: Point, dir: Dir) -> bool {
+    self.in_maze(point) && self.neighbor(point, dir).is_none() &&
+      !point.translate(dir).is_some_and(|n| self.in_maze(n))
+  }
+
+  /// Whether there's a gap in the outside wall on the `dir` side of a cell
+  pub fn is_door(&self, point: Point, dir: Dir) -> bool {
+    self.doors.contains(&(point, dir))
+  }
+
+  // What the renderers draw as open: passages, and doors in the outside wall
+  pub(crate) fn open_side(&self, point: Point, dir: Dir) -> bool {
+    self.passage(point, dir) || self.is_door(point, dir)
+  }
+
+  /// The cell at a relative position, (0.0, 0.0) being the top-left corner
+  /// of the maze and (1.0, 1.0) the bottom-right
+  pub fn cell_at_fraction(&self, fx: f64, fy: f64) -> Option<Point> {
+    let unit = 0.0..=1.0;
+    if !unit.contains(&fx) || !unit.contains(&fy) { return None }
+    let x = ((fx * self.width as f64) as usize).min(self.width - 1);
+    let y = ((fy * self.height as f64) as usize).min(self.height - 1);
+    Some(Point { x, y })
+  }
+}
+
+impl<'a> Iterator for MazeIterator<'a> {
+  type Item = Point;
+  fn next(&mut self) -> Option<Point> {
+    let pt = self.maze.nth_point(self.n);
+    self.n += 1;
+    pt
+  }
+}
+
+impl Topology {
+  pub const ALL: [Topology; 4] = [Topology::Plane, Topology::Cylinder, Topology::Torus, Topology::Mobius];
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Topology::Plane => "Plane",
+      Topology::Cylinder => "Cylinder",
+      Topology::Torus => "Torus",
+      Topology::Mobius => "Mobius"
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Topology> {
+    Topology::ALL.iter().copied().find(|t| t.name() == name)
+  }
+}
+
+impl fmt::Display for MazeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      MazeError::MissingEntrance => write!(f, "the maze has no entrance set"),
+      MazeError::MissingExit => write!(f, "the maze has no exit set"),
+      MazeError::NoPath => write!(f, "there is no path from the entrance to the exit")
+    }
+  }
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ParseError::Dimensions => write!(f, "the maze is the wrong size"),
+      ParseError::Format => write!(f, "the maze isn't in the right format"),
+      ParseError::InvalidValue { row, col } => write!(f, "bad value at row {}, column {}", row, col),
+      ParseError::UnsupportedVersion(v) => write!(f, "version {} of the format isn't supported", v),
+      ParseError::Mask => write!(f, "the mask doesn't leave the maze in one piece")
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SymmetryAxis;
+  use rand::rngs::StdRng;
+  use rand::SeedableRng;
+
+  #[test]
+  fn maze_point_tests() {
+    let m = Maze::new(5,5).expect("");
+    assert!(m.valid(Point{x: 2, y: 3}));
+    assert!(! m.valid(Point{x: 2, y: 20}));
+    assert!(m.valid(Point{x: 2, y: 4}));
+    assert!(! m.valid(Point{x: 2, y: 5}));
+
+    assert!(m.edge(Point{x: 0, y: 3}));
+    assert!(m.edge(Point{x: 2, y: 0}));
+    assert!(m.edge(Point{x: 4, y: 2}));
+    assert!(m.edge(Point{x: 3, y: 4}));
+    assert!(! m.edge(Point{x: 3, y: 2}));
+
+    assert!(m.corner(Point{x: 0, y: 0}));
+    assert!(! m.corner(Point{x: 3, y: 2}));
+    assert!(m.corner(Point{x: 4, y: 0}));
+    assert!(m.corner(Point{x: 0, y: 4}));
+  }
+
+  #[test]
+  fn maze_neighbor_test() {
+    let m = Maze::new(5,5).expect("");
+    let p = Point { x: 0, y: 0 };
+
+    assert_eq!(m.neighbor(p, Dir::North), None);
+    assert_eq!(m.neighbor(p, Dir::East), Some(Point{ x: 1, y: 0 }));
+  }
+
+  #[test]
+  fn maze_iterator_test() {
+    let m = Maze::new(5, 3).expect("");
+    assert_eq!(m.iter().count(), 15);
+    assert_eq!(m.iter().filter(|p| m.corner(*p)).count(), 4);
+    assert_eq!(m.iter().filter(|p| m.edge(*p)).count(), 12)
+  }
+
+  #[test]
+  fn maze_carve_passage_test() {
+    let mut m = Maze::new(2,2).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::South).expect("");
+    m.carve(Point { x: 0, y: 1 }, Dir::East).expect("");
+    assert!(m.passage(Point { x: 0, y: 0 }, Dir::South));
+    assert!(m.passage(Point { x: 0, y: 1 }, Dir::North));
+    assert!(m.passage(Point { x: 0, y: 1 }, Dir::East));
+    assert!(!m.passage(Point { x: 0, y: 0 }, Dir::East));
+    assert!(!m.passage(Point { x: 0, y: 0 }, Dir::West));
+
+    let mut m = Maze::new(2,2).expect("");
+    m.carve(Point { x: 0, y: 1 }, Dir::North).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::West).expect("");
+    assert!(m.passage(Point { x: 0, y: 0 }, Dir::South));
+    assert!(m.passage(Point { x: 0, y: 1 }, Dir::North));
+    assert!(m.passage(Point { x: 0, y: 0 }, Dir::East));
+    assert!(m.passage(Point { x: 1, y: 0 }, Dir::West));
+  }
+
+  #[test]
+  fn maze_cell_test() {
+    let mut m = Maze::new(2,2).expect("");
+    m.carve(Point { x: 0, y: 1 }, Dir::North).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::West).expect("");
+    assert_eq!(m.cell(Point { x: 0, y: 0 }),
+               Cell { north: false, east: true, south: true, west: false });
+    assert_eq!(m.cell(Point { x: 0, y: 1 }),
+               Cell { north: true, east: false, south: false, west: false });
+    assert_eq!(m.cell(Point { x: 1, y: 1 }),
+               Cell { north: false, east: false, south: false, west: false });
+  }
+
+  #[test]
+  fn maze_mask_from_csv_test() {
+    let mut m = Maze::new(5, 5).expect("");
+    for pt in m.iter().collect::<Vec<_>>() {
+      m.carve(pt, Dir::South).ok();
+      m.carve(pt, Dir::East).ok();
+    }
+
+    let csv = "0,0,1,1,1\n0,0,1,1,1\n1,1,1,1,1\n1,1,1,1,1\n1,1,1,1,1\n";
+    m.apply_mask_from_csv(csv).expect("");
+
+    let closed = Cell { north: false, east: false, south: false, west: false };
+    assert_eq!(m.cell(Point { x: 0, y: 0 }), closed);
+    assert_eq!(m.cell(Point { x: 1, y: 1 }), closed);
+    assert!(!m.passage(Point { x: 2, y: 1 }, Dir::West));
+    assert!(!m.passage(Point { x: 1, y: 2 }, Dir::North));
+    assert!(m.passage(Point { x: 2, y: 1 }, Dir::East));
+    assert!(m.passage(Point { x: 2, y: 2 }, Dir::West));
+    assert!(!m.in_maze(Point { x: 1, y: 0 }));
+
+    // Nor round the edge of a maze that wraps
+    let mut cylinder = Maze::with_topology(3, 1, Topology::Cylinder).expect("");
+    cylinder.apply_mask_from_csv("0,1,1").expect("");
+    assert_eq!(cylinder.neighbor(Point { x: 2, y: 0 }, Dir::East), None);
+    assert_eq!(cylinder.neighbor(Point { x: 1, y: 0 }, Dir::East), Some(Point { x: 2, y: 0 }));
+  }
+
+  #[test]
+  fn maze_mask_from_csv_errors_test() {
+    let mut m = Maze::new(2, 2).expect("");
+    assert_eq!(m.apply_mask_from_csv("1,1\n1,1\n1,1"), Err(ParseError::Dimensions));
+    assert_eq!(m.apply_mask_from_csv("1,1\n1"), Err(ParseError::Dimensions));
+    assert_eq!(m.apply_mask_from_csv("1,1\n1,x"), Err(ParseError::InvalidValue { row: 1, col: 1 }));
+    assert_eq!(m.apply_mask_from_csv("1,2\n1,1"), Err(ParseError::InvalidValue { row: 0, col: 1 }));
+    assert_eq!(m.apply_mask_from_csv("\"1\",\"0\"\n1,1"), Ok(()));
+    assert_eq!(m.mask().map(|mask| mask.count()), Some(3));
+    // What's left has to hang together, on top of what was masked before
+    assert_eq!(m.apply_mask_from_csv("1,1\n0,1"), Err(ParseError::Mask));
+    assert_eq!(m.apply_mask_from_csv("0,0\n0,0"), Err(ParseError::Mask));
+    assert_eq!(m.mask().map(|mask| mask.count()), Some(3));
+  }
+
+  #[test]
+  fn maze_symmetrize_test() {
+    let mut m = Maze::new(5, 4).expect("");
+    m.binary_tree(&mut StdRng::seed_from_u64(0));
+    m.symmetrize(SymmetryAxis::Horizontal);
+    for pt in m.iter() {
+      let mirror = Point { x: m.width - 1 - pt.x, y: pt.y };
+      assert_eq!(m.cell(pt), m.cell(mirror).mirrored(SymmetryAxis::Horizontal));
+    }
+
+    let mut m = Maze::new(4, 4).expect("");
+    m.binary_tree(&mut StdRng::seed_from_u64(0));
+    m.symmetrize(SymmetryAxis::Diagonal);
+    for pt in m.iter() {
+      let mirror = Point { x: pt.y, y: pt.x };
+      assert_eq!(m.cell(pt), m.cell(mirror).mirrored(SymmetryAxis::Diagonal));
+    }
+
+    let mut m = Maze::new(3, 3).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.symmetrize(SymmetryAxis::Rotational180);
+    assert!(m.passage(Point { x: 2, y: 2 }, Dir::West));
+    assert!(!m.passage(Point { x: 2, y: 0 }, Dir::West));
+  }
+
+  #[test]
+  fn maze_passage_ratio_test() {
+    let mut m = Maze::new(4, 3).expect("");
+    assert_eq!(m.total_interior_edges(), 17);
+    assert_eq!(m.passage_ratio(), 0.0);
+
+    m.binary_tree(&mut StdRng::seed_from_u64(0));
+    assert_eq!(m.passage_count(), 11);
+    assert_eq!(m.passage_ratio(), 11.0 / 17.0);
+
+    for pt in m.iter().collect::<Vec<_>>() {
+      m.carve(pt, Dir::East).ok();
+      m.carve(pt, Dir::South).ok();
+    }
+    assert_eq!(m.passage_ratio(), 1.0);
+    assert_eq!(Maze::new(1, 1).expect("").passage_ratio(), 0.0);
+  }
+
+  #[test]
+  fn maze_carve_region_test() {
+    let mut m = Maze::new(4, 4).expect("");
+    let region = [Point { x: 1, y: 1 }, Point { x: 2, y: 1 }, Point { x: 1, y: 2 }, Point { x: 2, y: 2 }];
+    m.carve_all_passages_in(&region);
+
+    assert!(m.passage(Point { x: 1, y: 1 }, Dir::East));
+    assert!(m.passage(Point { x: 1, y: 1 }, Dir::South));
+    assert!(m.passage(Point { x: 2, y: 2 }, Dir::North));
+    assert!(m.passage(Point { x: 2, y: 2 }, Dir::West));
+    assert_eq!(m.passage_count(), 4);
+  }
+
+  #[test]
+  fn maze_close_region_test() {
+    let region = [Point { x: 0, y: 0 }, Point { x: 1, y: 0 }, Point { x: 0, y: 1 }, Point { x: 1, y: 1 }];
+    let all: Vec<Point> = Maze::new(3, 3).expect("").iter().collect();
+
+    let mut m = Maze::new(3, 3).expect("");
+    m.carve_all_passages_in(&all);
+    m.close_all_passages_in(&region);
+    assert!(!m.passage(Point { x: 0, y: 0 }, Dir::East));
+    assert!(!m.passage(Point { x: 1, y: 1 }, Dir::North));
+    assert!(m.passage(Point { x: 1, y: 1 }, Dir::East));
+    assert_eq!(m.passage_count(), 8);
+
+    let mut m = Maze::new(3, 3).expect("");
+    m.carve_all_passages_in(&all);
+    m.isolate_region(&region);
+    assert!(!m.passage(Point { x: 1, y: 1 }, Dir::East));
+    assert!(!m.passage(Point { x: 1, y: 1 }, Dir::South));
+    assert!(m.passage(Point { x: 2, y: 1 }, Dir::South));
+    assert_eq!(m.passage_count(), 4);
+
+    // Cells outside the maze are ignored, not wrapped onto the next row
+    m.isolate_region(&[Point { x: 3, y: 1 }]);
+    assert_eq!(m.passage_count(), 4);
+  }
+
+  #[test]
+  fn maze_cell_at_fraction_test() {
+    let m = Maze::new(10, 4).expect("");
+    assert_eq!(m.cell_at_fraction(0.0, 0.0), Some(Point { x: 0, y: 0 }));
+    assert_eq!(m.cell_at_fraction(0.55, 0.5), Some(Point { x: 5, y: 2 }));
+    assert_eq!(m.cell_at_fraction(1.0, 1.0), Some(Point { x: 9, y: 3 }));
+    assert_eq!(m.cell_at_fraction(1.01, 0.5), None);
+    assert_eq!(m.cell_at_fraction(0.5, -0.1), None);
+    assert_eq!(m.cell_at_fraction(f64::NAN, 0.5), None);
+
+    assert_eq!(Point { x: 2, y: 1 }.to_fraction(&m), (0.25, 0.375));
+    for pt in m.iter() {
+      let (fx, fy) = pt.to_fraction(&m);
+      assert_eq!(m.cell_at_fraction(fx, fy), Some(pt));
+    }
+  }
+
+  #[test]
+  fn cylinder_test() {
+    assert!(Maze::with_topology(1, 4, Topology::Cylinder).is_err());
+    let mut m = Maze::with_topology(4, 3, Topology::Cylinder).expect("");
+    assert_eq!(m.east_walls.len(), 12);
+    let right = Point { x: 3, y: 1 };
+    assert_eq!(m.neighbor(right, Dir::East), Some(Point { x: 0, y: 1 }));
+    assert_eq!(m.neighbor(Point { x: 0, y: 1 }, Dir::West), Some(right));
+    assert_eq!(m.neighbor(Point { x: 0, y: 0 }, Dir::North), None);
+
+    m.carve(right, Dir::East).expect("");
+    assert!(m.passage(Point { x: 0, y: 1 }, Dir::West));
+    assert_eq!(m.direction_between(right, Point { x: 0, y: 1 }), Some(Dir::East));
+    assert_eq!(m.render(|_| ' ').lines().nth(3), Some("~ | | | ~"));
+    // The seam's walls are their own, one per row, and leave the others be
+    assert_eq!(m.east_walls.iter().filter(|&wall| !wall).count(), 1);
+    assert!(!m.passage(Point { x: 3, y: 0 }, Dir::East));
+    assert!(!m.passage(Point { x: 3, y: 2 }, Dir::East));
+    m.uncarve(right, Dir::East).expect("");
+    assert!(!m.passage(Point { x: 0, y: 1 }, Dir::West));
+    m.carve(right, Dir::East).expect("");
+    // Only across, where it wraps, does it tile without a break
+    let tiles = m.tiled(2, 2).expect("");
+    assert!(tiles.passage(right, Dir::East));
+    assert!(tiles.passage(Point { x: 4, y: 4 }, Dir::West));
+    assert!(!tiles.passage(Point { x: 0, y: 2 }, Dir::South));
+
+    let c = Maze::new_cylinder(6, 5, &mut StdRng::seed_from_u64(2)).expect("");
+    assert_eq!(c.topology, Topology::Cylinder);
+    assert!(c.reachable_from(Point { x: 0, y: 0 }).iter().all(|&r| r));
+    assert_eq!(c.passage_count(), 29);
+  }
+
+  #[test]
+  fn torus_test() {
+    assert!(Maze::with_topology(3, 1, Topology::Torus).is_err());
+    let empty = Maze::with_topology(4, 3, Topology::Torus).expect("");
+    assert_eq!(empty.south_walls.len(), 12);
+    for &corner in [Point { x: 0, y: 0 }, Point { x: 3, y: 0 }, Point { x: 0, y: 2 }, Point { x: 3, y: 2 }].iter() {
+      assert!(Dir::ALL.iter().all(|&dir| empty.neighbor(corner, dir).is_some()));
+    }
+    assert_eq!(empty.neighbor(Point { x: 1, y: 0 }, Dir::North), Some(Point { x: 1, y: 2 }));
+    assert_eq!(empty.neighbor(Point { x: 1, y: 2 }, Dir::South), Some(Point { x: 1, y: 0 }));
+
+    let m = Maze::new_torus(6, 5, &mut StdRng::seed_from_u64(4)).expect("");
+    assert!(m.reachable_from(Point { x: 0, y: 0 }).iter().all(|&r| r));
+    assert_eq!(m.passage_count(), 29);
+    assert_eq!(m.total_interior_edges(), 60);
+    let (from, to) = (Point { x: 0, y: 0 }, Point { x: 5, y: 4 });
+    assert!(m.solve_iter(from, to).last().expect("").path.is_some());
+  }
+
+  #[test]
+  fn tiled_test() {
+    let m = Maze::new_torus(4, 3, &mut StdRng::seed_from_u64(5)).expect("");
+    let tiles = m.tiled(3, 2).expect("");
+    assert_eq!((tiles.width, tiles.height, tiles.topology), (12, 6, Topology::Plane));
+    // Every passage turns up in every copy, except the ones over the edges
+    // of the whole thing
+    let east_edge = (0..3).filter(|&y| m.passage(Point { x: 3, y }, Dir::East)).count();
+    let south_edge = (0..4).filter(|&x| m.passage(Point { x, y: 2 }, Dir::South)).count();
+    assert_eq!(tiles.passage_count(), m.passage_count() * 6 - 2 * east_edge - 3 * south_edge);
+    for pt in tiles.iter() {
+      let local = Point { x: pt.x % 4, y: pt.y % 3 };
+      if pt.x > 0 { assert_eq!(tiles.passage(pt, Dir::West), m.passage(local, Dir::West)) }
+    }
+
+    let mobius = Maze::new_mobius(4, 3, &mut StdRng::seed_from_u64(5)).expect("");
+    let flat = mobius.tiled(2, 1).expect("");
+    // Only the middle row's passages over the edge come back to the same row
+    let seam = |y| flat.passage(Point { x: 3, y }, Dir::East);
+    assert!(!seam(0) && !seam(2));
+    assert_eq!(seam(1), mobius.passage(Point { x: 3, y: 1 }, Dir::East));
+    assert!(Maze::new(2, 2).expect("").tiled(0, 1).is_err());
+  }
+
+  #[test]
+  fn mobius_test() {
+    let empty = Maze::with_topology(5, 4, Topology::Mobius).expect("");
+    for y in 0..4 {
+      let mut pt = Point { x: 0, y };
+      for _ in 0..5 {
+        pt = empty.neighbor(pt, Dir::East).expect("");
+      }
+      assert_eq!(pt, Point { x: 0, y: 3 - y });
+    }
+    assert_eq!(empty.neighbor(Point { x: 0, y: 1 }, Dir::West), Some(Point { x: 4, y: 2 }));
+    assert_eq!(empty.neighbor(Point { x: 2, y: 0 }, Dir::North), None);
+
+    let mut m = Maze::with_topology(5, 4, Topology::Mobius).expect("");
+    m.carve(Point { x: 4, y: 0 }, Dir::East).expect("");
+    assert!(m.passage(Point { x: 0, y: 3 }, Dir::West));
+    assert!(!m.passage(Point { x: 0, y: 0 }, Dir::West));
+
+    let m = Maze::new_mobius(6, 5, &mut StdRng::seed_from_u64(9)).expect("");
+    assert_eq!(m.topology, Topology::Mobius);
+    assert!(m.reachable_from(Point { x: 0, y: 0 }).iter().all(|&r| r));
+    assert_eq!(m.passage_count(), 29);
+  }
+
+  #[test]
+  fn new_open_test() {
+    let m = Maze::new_open(4, 3).expect("");
+    assert_eq!(m.passage_count(), m.total_interior_edges());
+    assert_eq!(m.degree(Point { x: 1, y: 1 }), 4);
+    assert!(Maze::new_open(0, 3).is_err());
+  }
+
+  #[test]
+  fn open_entrance_test() {
+    let p = |x, y| Point { x, y };
+    let mut m = Maze::new(3, 2).expect("");
+    let walls = m.wall_lines().len();
+    m.open_entrance(p(0, 0), Dir::West).expect("");
+    m.open_exit(p(2, 1), Dir::South).expect("");
+    assert_eq!((m.entrance(), m.exit()), (Some(p(0, 0)), Some(p(2, 1))));
+    assert!(m.is_door(p(0, 0), Dir::West));
+    assert!(!m.passage(p(0, 0), Dir::West));
+    assert_eq!(m.wall_lines().len(), walls - 2);
+    assert_eq!(m.to_string(), "+-+-+-+\n  | | |\n+-+-+-+\n| | | |\n+-+-+ +\n");
+
+    // Only the outside wall can have a door in it
+    assert!(m.open_entrance(p(1, 0), Dir::West).is_err());
+    assert!(m.open_exit(p(1, 1), Dir::North).is_err());
+    assert!(m.open_exit(p(3, 1), Dir::East).is_err());
+    let mut torus = Maze::with_topology(3, 3, Topology::Torus).expect("");
+    assert!(torus.open_entrance(p(0, 0), Dir::North).is_err());
+  }
+}
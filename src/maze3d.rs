@@ -0,0 +1,293 @@
+//! Mazes on several levels, with stairs between them.
+
+use rand::Rng;
+use crate::{grid, BoundsError, Dir, Grid, Maze, Point};
+
+/// A cell in a Maze3D: `z` is the level, 0 at the bottom
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point3D { pub x: usize, pub y: usize, pub z: usize }
+
+/// The ways out of a cell in a Maze3D: the four of a flat maze, or up and
+/// down stairs. Kept apart from Dir so the flat mazes don't have to deal
+/// with directions they can never go.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Dir3D { North, South, East, West, Up, Down }
+
+/// A stack of `depth` mazes, each `width` by `height`, with stairs joining
+/// a cell to the one directly above it
+#[derive(Debug, Clone)]
+pub struct Maze3D {
+  pub(crate) width: usize,
+  pub(crate) height: usize,
+  // Every level's passages along it, bottom level first
+  pub(crate) levels: Vec<Maze>,
+  // Stairs up from each cell, level by level; the top level has none
+  pub(crate) stairs: Vec<bool>,
+}
+
+impl Point3D {
+  /// The same place on a flat level
+  pub fn flat(self) -> Point {
+    Point { x: self.x, y: self.y }
+  }
+}
+
+impl Dir3D {
+  pub const ALL: [Dir3D; 6] = [Dir3D::North, Dir3D::South, Dir3D::East, Dir3D::West, Dir3D::Up, Dir3D::Down];
+
+  pub fn opposite(self) -> Dir3D {
+    match self {
+      Dir3D::Up => Dir3D::Down,
+      Dir3D::Down => Dir3D::Up,
+      _ => self.flat().expect("").opposite().into()
+    }
+  }
+
+  /// The direction along a level, if it isn't up or down
+  pub fn flat(self) -> Option<Dir> {
+    match self {
+      Dir3D::North => Some(Dir::North),
+      Dir3D::South => Some(Dir::South),
+      Dir3D::East => Some(Dir::East),
+      Dir3D::West => Some(Dir::West),
+      Dir3D::Up | Dir3D::Down => None
+    }
+  }
+}
+
+impl From<Dir> for Dir3D {
+  fn from(dir: Dir) -> Dir3D {
+    match dir {
+      Dir::North => Dir3D::North,
+      Dir::South => Dir3D::South,
+      Dir::East => Dir3D::East,
+      Dir::West => Dir3D::West
+    }
+  }
+}
+
+impl Maze3D {
+  /// A maze on `depth` levels with every wall up and no stairs
+  pub fn new(width: usize, height: usize, depth: usize) -> Result<Maze3D, BoundsError> {
+    if depth == 0 { return Err(BoundsError) }
+    let level = Maze::new(width, height)?;
+    Ok(Maze3D { width, height, levels: vec![level; depth], stairs: vec![false; width * height * (depth - 1)] })
+  }
+
+  pub fn width(&self) -> usize {
+    self.width
+  }
+
+  pub fn height(&self) -> usize {
+    self.height
+  }
+
+  pub fn depth(&self) -> usize {
+    self.levels.len()
+  }
+
+  /// One level on its own, without the stairs
+  pub fn level(&self, z: usize) -> Option<&Maze> {
+    self.levels.get(z)
+  }
+
+  pub fn valid(&self, point: Point3D) -> bool {
+    point.x < self.width && point.y < self.height && point.z < self.depth()
+  }
+
+  /// Every cell, the bottom level first and row-major within each
+  pub fn iter(&self) -> impl Iterator<Item = Point3D> + '_ {
+    (0..self.depth()).flat_map(move |z| self.levels[z].iter().map(move |pt| Point3D { x: pt.x, y: pt.y, z }))
+  }
+
+  fn index(&self, point: Point3D) -> usize {
+    point.x + point.y * self.width + point.z * self.width * self.height
+  }
+
+  /// The cell on the `dir` side of this one, if it's in the maze
+  pub fn neighbor(&self, point: Point3D, dir: Dir3D) -> Option<Point3D> {
+    if !self.valid(point) { return None }
+    match dir {
+      Dir3D::Up if point.z + 1 < self.depth() => Some(Point3D { z: point.z + 1, ..point }),
+      Dir3D::Down if point.z > 0 => Some(Point3D { z: point.z - 1, ..point }),
+      Dir3D::Up | Dir3D::Down => None,
+      _ => {
+        let n = self.levels[point.z].neighbor(point.flat(), dir.flat().expect(""))?;
+        Some(Point3D { x: n.x, y: n.y, z: point.z })
+      }
+    }
+  }
+
+  pub fn passage(&self, point: Point3D, dir: Dir3D) -> bool {
+    match (self.neighbor(point, dir), dir) {
+      (None, _) => false,
+      (Some(_), Dir3D::Up) => self.stairs[self.index(point)],
+      (Some(below), Dir3D::Down) => self.stairs[self.index(below)],
+      (Some(_), _) => self.levels[point.z].passage(point.flat(), dir.flat().expect(""))
+    }
+  }
+
+  pub fn carve(&mut self, point: Point3D, dir: Dir3D) -> Result<(), BoundsError> {
+    self.set_passage(point, dir, true)
+  }
+
+  pub fn uncarve(&mut self, point: Point3D, dir: Dir3D) -> Result<(), BoundsError> {
+    self.set_passage(point, dir, false)
+  }
+
+  fn set_passage(&mut self, point: Point3D, dir: Dir3D, open: bool) -> Result<(), BoundsError> {
+    let n = self.neighbor(point, dir).ok_or(BoundsError)?;
+    match dir {
+      Dir3D::Up => { let i = self.index(point); self.stairs[i] = open },
+      Dir3D::Down => { let i = self.index(n); self.stairs[i] = open },
+      _ => self.levels[point.z].set_wall(point.flat(), dir.flat().expect(""), !open)?
+    }
+    Ok(())
+  }
+
+  /// Which way to step from `from` to reach `to`, if they're neighbors
+  pub fn direction_between(&self, from: Point3D, to: Point3D) -> Option<Dir3D> {
+    Dir3D::ALL.iter().copied().find(|&dir| self.neighbor(from, dir) == Some(to))
+  }
+
+  /// The cells joined to this one by passages or stairs
+  pub fn open_neighbors(&self, point: Point3D) -> Vec<Point3D> {
+    Dir3D::ALL.iter()
+      .filter(|&&dir| self.passage(point, dir))
+      .filter_map(|&dir| self.neighbor(point, dir))
+      .collect()
+  }
+
+  /// The recursive backtracker through all the levels at once. Going up
+  /// or down is just another way out of a cell, so with `depth` levels
+  /// there are about as many stairs as passages into any one cell's level.
+  pub fn recursive_backtracker(&mut self, rng: &mut impl Rng) {
+    grid::recursive_backtracker(self, rng)
+  }
+
+  /// A shortest route from `start` to `goal`, taking stairs as single
+  /// steps, or None if there isn't one
+  pub fn solve(&self, start: Point3D, goal: Point3D) -> Option<Vec<Point3D>> {
+    if !self.valid(start) || !self.valid(goal) { return None }
+    grid::solve(self, start, goal)
+  }
+
+  /// Draws one level as text, marking stairs going up with <, down with >,
+  /// and both ways with X
+  pub fn render_level(&self, z: usize) -> Option<String> {
+    let level = self.levels.get(z)?;
+    Some(level.render(|pt| {
+      let pt = Point3D { x: pt.x, y: pt.y, z };
+      match (self.passage(pt, Dir3D::Up), self.passage(pt, Dir3D::Down)) {
+        (true, true) => 'X',
+        (true, false) => '<',
+        (false, true) => '>',
+        (false, false) => ' '
+      }
+    }))
+  }
+
+  /// Every level drawn as text, bottom first, each under a "Level n" line
+  pub fn render(&self) -> String {
+    (0..self.depth())
+      .map(|z| format!("Level {}\n{}", z, self.render_level(z).expect("")))
+      .collect::<Vec<String>>()
+      .join("\n")
+  }
+}
+
+impl Grid for Maze3D {
+  type Cell = Point3D;
+
+  fn size(&self) -> usize {
+    self.width * self.height * self.depth()
+  }
+
+  fn index(&self, cell: Point3D) -> usize {
+    Maze3D::index(self, cell)
+  }
+
+  fn cells(&self) -> Vec<Point3D> {
+    self.iter().collect()
+  }
+
+  fn neighbors(&self, cell: Point3D) -> Vec<Point3D> {
+    Dir3D::ALL.iter().filter_map(|&dir| self.neighbor(cell, dir)).collect()
+  }
+
+  fn linked(&self, a: Point3D, b: Point3D) -> bool {
+    self.direction_between(a, b).is_some_and(|dir| self.passage(a, dir))
+  }
+
+  fn link(&mut self, a: Point3D, b: Point3D) -> Result<(), BoundsError> {
+    self.carve(a, self.direction_between(a, b).ok_or(BoundsError)?)
+  }
+
+  fn unlink(&mut self, a: Point3D, b: Point3D) -> Result<(), BoundsError> {
+    self.uncarve(a, self.direction_between(a, b).ok_or(BoundsError)?)
+  }
+
+  fn links(&self, cell: Point3D) -> Vec<Point3D> {
+    self.open_neighbors(cell)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::rngs::StdRng;
+  use rand::SeedableRng;
+
+  #[test]
+  fn maze3d_passage_test() {
+    let mut m = Maze3D::new(3, 2, 2).expect("");
+    let p = |x, y, z| Point3D { x, y, z };
+    assert!(Maze3D::new(3, 2, 0).is_err());
+    assert_eq!(m.neighbor(p(1, 1, 0), Dir3D::Up), Some(p(1, 1, 1)));
+    assert_eq!(m.neighbor(p(1, 1, 1), Dir3D::Up), None);
+    assert_eq!(m.neighbor(p(1, 1, 1), Dir3D::West), Some(p(0, 1, 1)));
+    assert_eq!(Dir3D::Up.opposite(), Dir3D::Down);
+    assert_eq!(Dir3D::from(Dir::East).opposite(), Dir3D::West);
+
+    m.carve(p(1, 1, 1), Dir3D::Down).expect("");
+    assert!(m.passage(p(1, 1, 0), Dir3D::Up));
+    assert!(m.carve(p(0, 0, 0), Dir3D::Down).is_err());
+    m.carve(p(0, 0, 1), Dir3D::East).expect("");
+    assert!(m.level(1).expect("").passage(Point { x: 1, y: 0 }, Dir::West));
+    assert!(!m.level(0).expect("").passage(Point { x: 1, y: 0 }, Dir::West));
+    assert_eq!(m.open_neighbors(p(1, 1, 1)), vec![p(1, 1, 0)]);
+  }
+
+  #[test]
+  fn maze3d_generate_solve_test() {
+    let mut m = Maze3D::new(6, 5, 3).expect("");
+    m.recursive_backtracker(&mut StdRng::seed_from_u64(6));
+    let cells = m.iter().count();
+    let passages: usize = m.levels.iter().map(|level| level.passage_count()).sum::<usize>() +
+      m.stairs.iter().filter(|&&open| open).count();
+    assert_eq!(passages, cells - 1);
+    assert!(m.stairs.iter().any(|&open| open));
+
+    let (start, goal) = (Point3D { x: 0, y: 0, z: 0 }, Point3D { x: 5, y: 4, z: 2 });
+    let path = m.solve(start, goal).expect("");
+    assert_eq!(path.first(), Some(&start));
+    assert_eq!(path.last(), Some(&goal));
+    for step in path.windows(2) {
+      assert!(m.open_neighbors(step[0]).contains(&step[1]));
+    }
+    assert_eq!(Maze3D::new(2, 2, 2).expect("").solve(start, Point3D { x: 0, y: 0, z: 1 }), None);
+  }
+
+  #[test]
+  fn maze3d_render_test() {
+    let mut m = Maze3D::new(2, 1, 3).expect("");
+    m.carve(Point3D { x: 0, y: 0, z: 0 }, Dir3D::Up).expect("");
+    m.carve(Point3D { x: 0, y: 0, z: 1 }, Dir3D::Up).expect("");
+    m.carve(Point3D { x: 0, y: 0, z: 1 }, Dir3D::East).expect("");
+    assert_eq!(m.render_level(1), Some("+-+-+\n|X  |\n+-+-+\n".to_string()));
+    assert_eq!(m.render(), "Level 0\n+-+-+\n|<| |\n+-+-+\n\n\
+                            Level 1\n+-+-+\n|X  |\n+-+-+\n\n\
+                            Level 2\n+-+-+\n|>| |\n+-+-+\n");
+    assert_eq!(m.render_level(3), None);
+  }
+}
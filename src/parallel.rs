@@ -0,0 +1,97 @@
+//! Generating huge mazes on every core, with the "rayon" feature.
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use crate::{Algorithm, BoundsError, Dir, Maze, Point, UnionFind};
+
+impl Maze {
+  /// The recursive backtracker split up to run in parallel: the maze is cut
+  /// into `tile` by `tile` squares, each generated on its own, and then a
+  /// random spanning tree of the squares picks which neighboring ones to
+  /// join with a single passage. It's still a perfect maze, with the
+  /// backtracker's long corridors inside each square but only one way
+  /// between any two. Each square gets its own seed from `rng` up front, so
+  /// the same seed makes the same maze however many threads there are.
+  ///
+  /// A masked maze's squares can come apart inside, so it just gets the
+  /// ordinary backtracker. Edges that wrap aren't joined up.
+  pub fn parallel_backtracker(&mut self, tile: usize, rng: &mut impl Rng) -> Result<(), BoundsError> {
+    if tile == 0 { return Err(BoundsError) }
+    if self.mask.is_some() {
+      self.recursive_backtracker(rng);
+      return Ok(())
+    }
+
+    let (across, down) = (self.width.div_ceil(tile), self.height.div_ceil(tile));
+    let seeds: Vec<(Point, u64)> = (0..down)
+      .flat_map(|ty| (0..across).map(move |tx| Point { x: tx, y: ty }))
+      .map(|t| (t, rng.gen()))
+      .collect();
+    let (width, height) = (self.width, self.height);
+    let tiles: Vec<(Point, Maze)> = seeds.into_par_iter().map(|(t, seed)| {
+      let origin = Point { x: t.x * tile, y: t.y * tile };
+      let mut part = Maze::new(tile.min(width - origin.x), tile.min(height - origin.y)).expect("");
+      part.recursive_backtracker(&mut StdRng::seed_from_u64(seed));
+      (origin, part)
+    }).collect();
+
+    for (origin, part) in tiles.iter() {
+      for pt in part.iter() {
+        let at = Point { x: origin.x + pt.x, y: origin.y + pt.y };
+        for &dir in [Dir::East, Dir::South].iter() {
+          if part.passage(pt, dir) { self.carve(at, dir)? }
+        }
+      }
+    }
+
+    // Kruskal's over the squares, each join a passage somewhere along
+    // the edge the two share
+    let mut joins: Vec<(Point, Dir)> = Vec::new();
+    for ty in 0..down {
+      for tx in 0..across {
+        if tx + 1 < across { joins.push((Point { x: tx, y: ty }, Dir::East)) }
+        if ty + 1 < down { joins.push((Point { x: tx, y: ty }, Dir::South)) }
+      }
+    }
+    joins.shuffle(rng);
+    let mut sets = UnionFind::new(across * down);
+    for (t, dir) in joins {
+      let n = t.translate(dir).expect("");
+      if !sets.union(t.x + t.y * across, n.x + n.y * across) { continue }
+      let cell = match dir {
+        Dir::East => Point { x: n.x * tile - 1, y: rng.gen_range(t.y * tile, (t.y * tile + tile).min(height)) },
+        _ => Point { x: rng.gen_range(t.x * tile, (t.x * tile + tile).min(width)), y: n.y * tile - 1 }
+      };
+      self.carve(cell, dir)?;
+    }
+    self.algorithm = Some(Algorithm::RecursiveBacktracker);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parallel_backtracker_test() {
+    let mut m = Maze::new(150, 70).expect("");
+    m.parallel_backtracker(32, &mut StdRng::seed_from_u64(3)).expect("");
+    assert_eq!(m.passage_count(), 150 * 70 - 1);
+    assert!(m.are_all_cells_mutually_reachable());
+    assert_eq!(m.algorithm(), Some(Algorithm::RecursiveBacktracker));
+
+    let mut again = Maze::new(150, 70).expect("");
+    again.parallel_backtracker(32, &mut StdRng::seed_from_u64(3)).expect("");
+    assert_eq!(again.east_walls, m.east_walls);
+    assert_eq!(again.south_walls, m.south_walls);
+
+    // A tile bigger than the maze is just the backtracker
+    let mut small = Maze::new(5, 4).expect("");
+    small.parallel_backtracker(10, &mut StdRng::seed_from_u64(1)).expect("");
+    assert_eq!(small.passage_count(), 19);
+    assert!(Maze::new(5, 4).expect("").parallel_backtracker(0, &mut StdRng::seed_from_u64(1)).is_err());
+  }
+}
@@ -0,0 +1,113 @@
+//! The play subcommand: walking a maze in the terminal with the arrow keys.
+
+use std::io::{self, Write};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::Print;
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, execute, queue};
+use maze::{Dir, InteractiveSolver, Maze, RenderStyle};
+
+// Raw mode on the alternate screen for as long as it's held, so the
+// terminal is put back however the game ends
+struct Screen;
+
+impl Screen {
+  fn enter(out: &mut impl Write) -> io::Result<Screen> {
+    terminal::enable_raw_mode()?;
+    execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+    Ok(Screen)
+  }
+}
+
+impl Drop for Screen {
+  fn drop(&mut self) {
+    let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+  }
+}
+
+/// Plays the maze full-screen from its entrance until the player reaches
+/// its exit, returning how many moves it took, or None if they gave up
+pub fn play(m: &Maze, style: RenderStyle) -> io::Result<Option<usize>> {
+  let mut player = InteractiveSolver::new(m, m.entrance().expect(""));
+  let mut out = io::stdout();
+  let _screen = Screen::enter(&mut out)?;
+  let mut message = "arrow keys to move, q to quit".to_string();
+  while !player.is_at_exit() {
+    draw(&mut out, &frame(m, &player, style), &message)?;
+    let key = match event::read()? {
+      Event::Key(key) if key.kind != KeyEventKind::Release => key,
+      _ => continue
+    };
+    match key.code {
+      KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+      KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(None),
+      code => if let Some(dir) = direction(code) {
+        message = match player.move_to(dir) {
+          Ok(_) => format!("moves: {}", player.steps_taken()),
+          Err(e) => e.to_string()
+        }
+      }
+    }
+  }
+
+  let message = format!("out in {} moves! any key to finish", player.steps_taken());
+  draw(&mut out, &frame(m, &player, style), &message)?;
+  while !matches!(event::read()?, Event::Key(key) if key.kind != KeyEventKind::Release) {}
+  Ok(Some(player.steps_taken()))
+}
+
+fn draw(out: &mut impl Write, frame: &str, message: &str) -> io::Result<()> {
+  queue!(out, cursor::MoveTo(0, 0), terminal::Clear(ClearType::All))?;
+  // Raw mode doesn't go back to the start of the line on its own
+  for line in frame.lines() {
+    queue!(out, Print(line), Print("\r\n"))?;
+  }
+  queue!(out, Print(message))?;
+  out.flush()
+}
+
+// The maze with the player as @, the exit as E, and everywhere they've
+// been dotted
+fn frame(m: &Maze, player: &InteractiveSolver, style: RenderStyle) -> String {
+  let mut marks = m.cell_data(' ');
+  for &pt in player.visited_cells() { marks[pt] = '·' }
+  if let Some(exit) = m.exit() { marks[exit] = 'E' }
+  marks[player.current_position()] = '@';
+  m.render_data(style, &marks, |&c| Some(c))
+}
+
+// Arrow keys, or vi's hjkl for anyone who'd rather
+fn direction(code: KeyCode) -> Option<Dir> {
+  match code {
+    KeyCode::Up | KeyCode::Char('k') => Some(Dir::North),
+    KeyCode::Down | KeyCode::Char('j') => Some(Dir::South),
+    KeyCode::Left | KeyCode::Char('h') => Some(Dir::West),
+    KeyCode::Right | KeyCode::Char('l') => Some(Dir::East),
+    _ => None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use maze::Point;
+
+  #[test]
+  fn frame_test() {
+    let mut m = Maze::new(3, 1).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::East).expect("");
+    m.open_entrance(Point { x: 0, y: 0 }, Dir::West).expect("");
+    m.open_exit(Point { x: 2, y: 0 }, Dir::East).expect("");
+
+    let mut player = InteractiveSolver::new(&m, Point { x: 0, y: 0 });
+    assert_eq!(frame(&m, &player, RenderStyle::Ascii), "+-+-+-+\n @   E \n+-+-+-+\n");
+    player.move_to(direction(KeyCode::Right).expect("")).expect("");
+    assert_eq!(frame(&m, &player, RenderStyle::Ascii), "+-+-+-+\n · @ E \n+-+-+-+\n");
+    assert!(player.move_to(direction(KeyCode::Char('k')).expect("")).is_err());
+    player.move_to(Dir::East).expect("");
+    assert!(player.is_at_exit());
+    assert_eq!(direction(KeyCode::Char('x')), None);
+  }
+}
@@ -0,0 +1,128 @@
+//! Points, directions and cells: the small value types everything else is built from.
+
+use crate::Maze;
+
+/// A cell's position: x counts columns from the left, y rows from the top
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Point { pub x: usize, pub y: usize }
+
+/// A walk through the maze, one cell per step
+pub type Path = Vec<Point>;
+
+/// Which sides of a cell are open
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cell { pub north: bool, pub east: bool, pub south: bool, pub west: bool }
+
+/// The four ways out of a cell
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Dir { North, South, East, West }
+
+/// Horizontal mirrors left onto right, Vertical top onto bottom, Diagonal
+/// swaps x and y
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SymmetryAxis { Horizontal, Vertical, Rotational180, Diagonal }
+
+impl Cell {
+  /// The same cell seen in a mirror: a passage that leads `dir` here leads
+  /// `dir.mirrored(axis)` in the mirrored cell
+  pub fn mirrored(&self, axis: SymmetryAxis) -> Cell {
+    let open = |dir: Dir| match dir {
+      Dir::North => self.north,
+      Dir::South => self.south,
+      Dir::East => self.east,
+      Dir::West => self.west
+    };
+    Cell {
+      north: open(Dir::North.mirrored(axis)),
+      south: open(Dir::South.mirrored(axis)),
+      east: open(Dir::East.mirrored(axis)),
+      west: open(Dir::West.mirrored(axis))
+    }
+  }
+}
+
+impl Dir {
+  pub const ALL: [Dir; 4] = [Dir::North, Dir::South, Dir::East, Dir::West];
+
+  pub fn opposite(self) -> Dir {
+    match self {
+      Dir::North => Dir::South,
+      Dir::South => Dir::North,
+      Dir::East => Dir::West,
+      Dir::West => Dir::East
+    }
+  }
+
+  pub fn right(self) -> Dir {
+    match self {
+      Dir::North => Dir::East,
+      Dir::East => Dir::South,
+      Dir::South => Dir::West,
+      Dir::West => Dir::North
+    }
+  }
+
+  pub fn left(self) -> Dir {
+    self.right().opposite()
+  }
+
+  pub fn mirrored(self, axis: SymmetryAxis) -> Dir {
+    match (axis, self) {
+      (SymmetryAxis::Horizontal, Dir::East | Dir::West) => self.opposite(),
+      (SymmetryAxis::Vertical, Dir::North | Dir::South) => self.opposite(),
+      (SymmetryAxis::Rotational180, _) => self.opposite(),
+      (SymmetryAxis::Diagonal, Dir::North) => Dir::West,
+      (SymmetryAxis::Diagonal, Dir::West) => Dir::North,
+      (SymmetryAxis::Diagonal, Dir::South) => Dir::East,
+      (SymmetryAxis::Diagonal, Dir::East) => Dir::South,
+      _ => self
+    }
+  }
+}
+
+impl Point {
+  /// Which way to step to get to an adjacent point
+  pub fn direction_to(&self, other: Point) -> Option<Dir> {
+    Dir::ALL.iter().copied().find(|&dir| self.translate(dir) == Some(other))
+  }
+
+  /// Where the middle of this cell is, relative to the size of the maze:
+  /// the inverse of Maze::cell_at_fraction
+  pub fn to_fraction(self, maze: &Maze) -> (f64, f64) {
+    ((self.x as f64 + 0.5) / maze.width as f64, (self.y as f64 + 0.5) / maze.height as f64)
+  }
+
+  /// Manhattan distance, ignoring walls
+  pub fn distance(&self, other: Point) -> usize {
+    self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
+  }
+
+  pub fn translate(&self, dir: Dir) -> Option<Point> {
+    match dir {
+      Dir::North => Some(Point { x: self.x, y: self.y.checked_sub(1)? }),
+      Dir::South => Some(Point { x: self.x, y: self.y + 1 }),
+      Dir::East => Some(Point { x: self.x + 1, y: self.y }),
+      Dir::West => Some(Point { x: self.x.checked_sub(1)?, y: self.y })
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn point_translate_test() {
+    let p = Point { x: 1, y: 1 };
+
+    assert_eq!(p.translate(Dir::North), Some(Point { x: 1, y: 0 }));
+    assert_eq!(p.translate(Dir::South), Some(Point { x: 1, y: 2 }));
+    assert_eq!(p.translate(Dir::East), Some(Point { x: 2, y: 1 }));
+    assert_eq!(p.translate(Dir::West), Some(Point { x: 0, y: 1 }));
+
+    let p2 = Point { x: 0, y: 0 };
+    assert_eq!(p2.translate(Dir::North), None);
+  }
+}
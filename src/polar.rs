@@ -0,0 +1,275 @@
+//! Polar mazes: rings of cells around a middle one, like a target.
+
+use std::f64::consts::PI;
+use rand::Rng;
+use crate::{grid, BoundsError, Grid, Point};
+
+/// The ways out of a cell in a polar maze. Inward is toward the middle,
+/// and going clockwise counts up around a ring.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PolarDir { Inward, Outward, Clockwise, CounterClockwise }
+
+/// A maze of concentric rings. Cells are Points with `y` the ring, 0 being
+/// the single cell in the middle, and `x` the place around it. Each ring
+/// has as many cells as keeps them roughly square, so going outward a cell
+/// can have two (or more) neighbors where a ring splits.
+#[derive(Debug, Clone)]
+pub struct PolarMaze {
+  counts: Vec<usize>,
+  // Where each ring's cells start, in the two lists below
+  starts: Vec<usize>,
+  // Open passages from each cell to the one inward of it, and to the next
+  // one clockwise; the other directions are those of a neighbor
+  inward: Vec<bool>,
+  clockwise: Vec<bool>,
+}
+
+impl PolarDir {
+  pub const ALL: [PolarDir; 4] = [PolarDir::Inward, PolarDir::Outward, PolarDir::Clockwise, PolarDir::CounterClockwise];
+}
+
+impl PolarMaze {
+  /// A polar maze `rings` deep, counting the middle cell, with every wall up
+  pub fn new(rings: usize) -> Result<PolarMaze, BoundsError> {
+    if rings == 0 { return Err(BoundsError) }
+    let mut counts = vec![1];
+    for ring in 1..rings {
+      // A cell as wide as the last ring's would be, in ring widths, rounded
+      // to a whole number of splits
+      let circumference = 2.0 * PI * ring as f64;
+      let split = (circumference / counts[ring - 1] as f64).round() as usize;
+      counts.push(counts[ring - 1] * split.max(1));
+    }
+    let starts = counts.iter().scan(0, |total, &n| { *total += n; Some(*total - n) }).collect();
+    let cells = counts.iter().sum();
+    Ok(PolarMaze { counts, starts, inward: vec![false; cells], clockwise: vec![false; cells] })
+  }
+
+  pub fn rings(&self) -> usize {
+    self.counts.len()
+  }
+
+  /// How many cells are in a ring
+  pub fn ring_len(&self, ring: usize) -> usize {
+    self.counts.get(ring).copied().unwrap_or(0)
+  }
+
+  pub fn valid(&self, point: Point) -> bool {
+    point.x < self.ring_len(point.y)
+  }
+
+  /// Every cell, a ring at a time from the middle outward
+  pub fn iter(&self) -> impl Iterator<Item = Point> + '_ {
+    self.counts.iter().enumerate().flat_map(|(y, &n)| (0..n).map(move |x| Point { x, y }))
+  }
+
+  fn index(&self, point: Point) -> usize {
+    self.starts[point.y] + point.x
+  }
+
+  /// The cells on the `dir` side of this one. Outward there can be more
+  /// than one; every other way there's at most one.
+  pub fn neighbors(&self, point: Point, dir: PolarDir) -> Vec<Point> {
+    if !self.valid(point) { return vec![] }
+    let Point { x, y } = point;
+    let n = self.counts[y];
+    match dir {
+      PolarDir::Inward if y > 0 => vec![Point { x: x / (n / self.counts[y - 1]), y: y - 1 }],
+      PolarDir::Outward if y + 1 < self.rings() => {
+        let split = self.counts[y + 1] / n;
+        (x * split..(x + 1) * split).map(|x| Point { x, y: y + 1 }).collect()
+      },
+      // The middle cell has nothing beside it
+      PolarDir::Clockwise if n > 1 => vec![Point { x: (x + 1) % n, y }],
+      PolarDir::CounterClockwise if n > 1 => vec![Point { x: (x + n - 1) % n, y }],
+      _ => vec![]
+    }
+  }
+
+  /// Every cell next to this one, whether or not there's a wall between
+  pub fn all_neighbors(&self, point: Point) -> Vec<Point> {
+    PolarDir::ALL.iter().flat_map(|&dir| self.neighbors(point, dir)).collect()
+  }
+
+  // Which passage flag covers the wall between two cells, if they're
+  // next to each other
+  fn passage_index(&self, a: Point, b: Point) -> Option<(bool, usize)> {
+    if self.neighbors(a, PolarDir::Inward).contains(&b) { return Some((true, self.index(a))) }
+    if self.neighbors(b, PolarDir::Inward).contains(&a) { return Some((true, self.index(b))) }
+    if self.neighbors(a, PolarDir::Clockwise).contains(&b) { return Some((false, self.index(a))) }
+    if self.neighbors(b, PolarDir::Clockwise).contains(&a) { return Some((false, self.index(b))) }
+    None
+  }
+
+  /// Whether there's a passage between two cells
+  pub fn linked(&self, a: Point, b: Point) -> bool {
+    match self.passage_index(a, b) {
+      Some((true, i)) => self.inward[i],
+      Some((false, i)) => self.clockwise[i],
+      None => false
+    }
+  }
+
+  /// Knocks down the wall between two neighboring cells
+  pub fn carve(&mut self, a: Point, b: Point) -> Result<(), BoundsError> {
+    self.set_passage(a, b, true)
+  }
+
+  /// Puts the wall between two neighboring cells back up
+  pub fn uncarve(&mut self, a: Point, b: Point) -> Result<(), BoundsError> {
+    self.set_passage(a, b, false)
+  }
+
+  fn set_passage(&mut self, a: Point, b: Point, open: bool) -> Result<(), BoundsError> {
+    match self.passage_index(a, b).ok_or(BoundsError)? {
+      (true, i) => self.inward[i] = open,
+      (false, i) => self.clockwise[i] = open
+    }
+    Ok(())
+  }
+
+  /// The cells joined to this one by passages
+  pub fn open_neighbors(&self, point: Point) -> Vec<Point> {
+    self.all_neighbors(point).into_iter().filter(|&n| self.linked(point, n)).collect()
+  }
+
+  /// The recursive backtracker, as on a rectangular maze
+  pub fn recursive_backtracker(&mut self, rng: &mut impl Rng) {
+    grid::recursive_backtracker(self, rng)
+  }
+
+  /// Draws the maze as SVG, each ring `ring_px` wide: an arc for every wall
+  /// toward the middle, a line out from the middle for every wall between
+  /// cells in a ring, and a circle round the outside
+  pub fn to_svg(&self, ring_px: f64) -> String {
+    let radius = ring_px * self.rings() as f64;
+    let size = 2.0 * radius + 4.0;
+    let c = size / 2.0;
+    let at = |r: f64, angle: f64| (c + r * angle.cos(), c + r * angle.sin());
+
+    let mut d = String::new();
+    for pt in self.iter().filter(|pt| pt.y > 0) {
+      let theta = 2.0 * PI / self.counts[pt.y] as f64;
+      let (inner, outer) = (ring_px * pt.y as f64, ring_px * (pt.y + 1) as f64);
+      let (start, end) = (theta * pt.x as f64, theta * (pt.x + 1) as f64);
+      if !self.inward[self.index(pt)] {
+        let ((x1, y1), (x2, y2)) = (at(inner, start), at(inner, end));
+        d.push_str(&format!("M{:.2},{:.2} A{:.2},{:.2} 0 0 1 {:.2},{:.2} ", x1, y1, inner, inner, x2, y2));
+      }
+      if self.counts[pt.y] > 1 && !self.clockwise[self.index(pt)] {
+        let ((x1, y1), (x2, y2)) = (at(inner, end), at(outer, end));
+        d.push_str(&format!("M{:.2},{:.2} L{:.2},{:.2} ", x1, y1, x2, y2));
+      }
+    }
+
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.2}\" height=\"{:.2}\" viewBox=\"0 0 {:.2} {:.2}\">\n",
+                          size, size, size, size);
+    svg.push_str(&format!("<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"none\" stroke=\"black\" stroke-width=\"2\"/>\n",
+                          c, c, radius));
+    if !d.is_empty() {
+      svg.push_str(&format!("<path d=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"2\"/>\n", d.trim_end()));
+    }
+    svg.push_str("</svg>\n");
+    svg
+  }
+}
+
+impl Grid for PolarMaze {
+  type Cell = Point;
+
+  fn size(&self) -> usize {
+    self.inward.len()
+  }
+
+  fn index(&self, cell: Point) -> usize {
+    PolarMaze::index(self, cell)
+  }
+
+  fn cells(&self) -> Vec<Point> {
+    self.iter().collect()
+  }
+
+  fn neighbors(&self, cell: Point) -> Vec<Point> {
+    self.all_neighbors(cell)
+  }
+
+  fn linked(&self, a: Point, b: Point) -> bool {
+    PolarMaze::linked(self, a, b)
+  }
+
+  fn link(&mut self, a: Point, b: Point) -> Result<(), BoundsError> {
+    self.carve(a, b)
+  }
+
+  fn unlink(&mut self, a: Point, b: Point) -> Result<(), BoundsError> {
+    self.uncarve(a, b)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::rngs::StdRng;
+  use rand::SeedableRng;
+
+  #[test]
+  fn polar_rings_test() {
+    let m = PolarMaze::new(5).expect("");
+    let counts: Vec<usize> = (0..5).map(|ring| m.ring_len(ring)).collect();
+    assert_eq!(counts, vec![1, 6, 12, 24, 24]);
+    assert_eq!(m.iter().count(), 67);
+    assert!(PolarMaze::new(0).is_err());
+
+    let p = |x, y| Point { x, y };
+    assert_eq!(m.neighbors(p(0, 0), PolarDir::Outward).len(), 6);
+    assert_eq!(m.neighbors(p(0, 0), PolarDir::Clockwise), vec![]);
+    assert_eq!(m.neighbors(p(5, 1), PolarDir::Outward), vec![p(10, 2), p(11, 2)]);
+    assert_eq!(m.neighbors(p(11, 2), PolarDir::Clockwise), vec![p(0, 2)]);
+    assert_eq!(m.neighbors(p(0, 2), PolarDir::CounterClockwise), vec![p(11, 2)]);
+    assert_eq!(m.neighbors(p(7, 3), PolarDir::Outward), vec![p(7, 4)]);
+    assert_eq!(m.neighbors(p(7, 4), PolarDir::Outward), vec![]);
+
+    // Neighbors both ways round
+    for a in m.iter() {
+      for b in m.all_neighbors(a) {
+        assert!(m.all_neighbors(b).contains(&a), "{:?} {:?}", a, b);
+      }
+    }
+  }
+
+  #[test]
+  fn polar_backtracker_test() {
+    let mut m = PolarMaze::new(6).expect("");
+    let p = |x, y| Point { x, y };
+    assert!(m.carve(p(0, 0), p(0, 2)).is_err());
+    m.recursive_backtracker(&mut StdRng::seed_from_u64(4));
+
+    let cells = m.iter().count();
+    let passages = m.inward.iter().chain(m.clockwise.iter()).filter(|&&open| open).count();
+    assert_eq!(passages, cells - 1);
+    let mut reached = vec![p(0, 0)];
+    let mut i = 0;
+    while i < reached.len() {
+      for n in m.open_neighbors(reached[i]) {
+        if !reached.contains(&n) { reached.push(n) }
+      }
+      i += 1;
+    }
+    assert_eq!(reached.len(), cells);
+  }
+
+  #[test]
+  fn polar_svg_test() {
+    let single = PolarMaze::new(1).expect("");
+    assert_eq!(single.to_svg(10.0), "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24.00\" height=\"24.00\" viewBox=\"0 0 24.00 24.00\">\n\
+                                     <circle cx=\"12.00\" cy=\"12.00\" r=\"10.00\" fill=\"none\" stroke=\"black\" stroke-width=\"2\"/>\n\
+                                     </svg>\n");
+
+    let mut m = PolarMaze::new(2).expect("");
+    let closed = m.to_svg(10.0);
+    assert_eq!(closed.matches('A').count(), 6);
+    assert_eq!(closed.matches('L').count(), 6);
+    m.carve(Point { x: 0, y: 0 }, Point { x: 3, y: 1 }).expect("");
+    assert_eq!(m.to_svg(10.0).matches('A').count(), 5);
+  }
+}
@@ -0,0 +1,377 @@
+//! Drawing mazes as images and animations, and reading masks from them,
+//! behind the "image" feature.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::codecs::png::PngEncoder;
+use image::error::{ParameterError, ParameterErrorKind};
+use image::{ColorType, Delay, Frame, ImageEncoder, ImageError, ImageResult, Rgba, RgbaImage};
+use rand::Rng;
+use crate::{Algorithm, Dir, GenerationEvent, Heatmap, Mask, Maze, Point};
+
+/// Sizes and colors for Maze::to_image_styled. Sizes are in pixels; walls
+/// are drawn between cells, so a maze w cells wide comes out
+/// w * cell_px + (w + 1) * wall_px pixels wide.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ImageStyle {
+  pub cell_px: u32,
+  pub wall_px: u32,
+  pub wall: Rgba<u8>,
+  pub background: Rgba<u8>,
+}
+
+impl Default for ImageStyle {
+  // Black walls on white, about right for printing a maze on a page
+  fn default() -> Self {
+    ImageStyle { cell_px: 16, wall_px: 2, wall: Rgba([0, 0, 0, 255]), background: Rgba([255, 255, 255, 255]) }
+  }
+}
+
+/// How Mask::from_image_with reads a picture: each cell of the mask is a
+/// square `cell_px` pixels across, and is on if its pixels are darker on
+/// average than `threshold`, from 0 for black to 255 for white.
+/// Transparent pixels count as white.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ImageMaskOptions {
+  pub cell_px: u32,
+  pub threshold: u8,
+}
+
+impl Default for ImageMaskOptions {
+  // A cell per pixel, and anything darker than mid-gray in the maze
+  fn default() -> Self {
+    ImageMaskOptions { cell_px: 1, threshold: 128 }
+  }
+}
+
+/// How Maze::record_generation_gif animates: each frame drawn in `style`,
+/// `carves_per_frame` wall changes apart and shown for `frame_ms`, and the
+/// finished maze held for `last_frame_ms` before it loops
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GifOptions {
+  pub style: ImageStyle,
+  pub carves_per_frame: usize,
+  pub frame_ms: u32,
+  pub last_frame_ms: u32,
+}
+
+impl Default for GifOptions {
+  // Every carve, at 25 frames a second, then two seconds to look at it
+  fn default() -> Self {
+    GifOptions { style: ImageStyle::default(), carves_per_frame: 1, frame_ms: 40, last_frame_ms: 2000 }
+  }
+}
+
+impl Mask {
+  /// A mask from a picture, a cell per pixel, with the dark pixels on, so a
+  /// black silhouette on white becomes the shape of a maze
+  pub fn from_image(path: impl AsRef<std::path::Path>) -> ImageResult<Mask> {
+    Mask::from_image_with(path, &ImageMaskOptions::default())
+  }
+
+  /// A mask from a picture, scaled and thresholded as `options` says
+  pub fn from_image_with(path: impl AsRef<std::path::Path>, options: &ImageMaskOptions) -> ImageResult<Mask> {
+    Mask::from_rgba_image(&image::open(path)?.to_rgba8(), options)
+  }
+
+  /// A mask from a picture already in memory. Pixels left over past the
+  /// last whole cell on the right and bottom are ignored.
+  pub fn from_rgba_image(img: &RgbaImage, options: &ImageMaskOptions) -> ImageResult<Mask> {
+    let cell_px = options.cell_px.max(1);
+    let (width, height) = ((img.width() / cell_px) as usize, (img.height() / cell_px) as usize);
+    let mut mask = Mask::new(width, height).map_err(|_| {
+      ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::DimensionMismatch))
+    })?;
+
+    for pt in mask.points() {
+      let (left, top) = (pt.x as u32 * cell_px, pt.y as u32 * cell_px);
+      let mut total = 0.0;
+      for py in top..(top + cell_px) {
+        for px in left..(left + cell_px) {
+          let Rgba([r, g, b, a]) = *img.get_pixel(px, py);
+          let luma = 0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64;
+          // Over a white background
+          let alpha = a as f64 / 255.0;
+          total += luma * alpha + 255.0 * (1.0 - alpha);
+        }
+      }
+      let average = total / (cell_px * cell_px) as f64;
+      mask.set(pt, average < options.threshold as f64).expect("");
+    }
+    Ok(mask)
+  }
+}
+
+impl Maze {
+  /// Draws the maze as black walls on white, `cell_px` pixels across each
+  /// cell and `wall_px` thick
+  pub fn to_image(&self, cell_px: u32, wall_px: u32) -> RgbaImage {
+    self.to_image_styled(&ImageStyle { cell_px, wall_px, ..ImageStyle::default() })
+  }
+
+  /// Draws the maze with the sizes and colors in `style`. A passage off the
+  /// edge of a wrapping maze, or a door, is drawn as a gap in the border.
+  pub fn to_image_styled(&self, style: &ImageStyle) -> RgbaImage {
+    self.draw_image(style, |_| None)
+  }
+
+  /// Like to_image_styled, with each cell filled in by its distance from
+  /// the heatmap's root
+  pub fn to_image_heatmap(&self, style: &ImageStyle, heatmap: &Heatmap) -> RgbaImage {
+    let colors = heatmap.colors(self);
+    self.draw_image(style, |pt| colors[pt].map(|[r, g, b]| Rgba([r, g, b, 255])))
+  }
+
+  /// Like to_image_styled, with a path drawn over the maze as a red line
+  /// through the middle of each cell along it, as thick as the walls. A step
+  /// that wraps round the edge breaks the line rather than crossing the
+  /// whole maze.
+  pub fn to_image_with_solution(&self, style: &ImageStyle, path: &[Point]) -> RgbaImage {
+    let mut img = self.to_image_styled(style);
+    let step = style.cell_px + style.wall_px;
+    let thick = style.wall_px.max(1);
+    // The top left of the line's square in the middle of a cell
+    let corner = |pt: Point| {
+      let mid = |n: usize| n as u32 * step + style.wall_px + style.cell_px / 2;
+      (mid(pt.x).saturating_sub(thick / 2), mid(pt.y).saturating_sub(thick / 2))
+    };
+    let red = Rgba([255, 0, 0, 255]);
+    for (i, &pt) in path.iter().enumerate() {
+      let (x, y) = corner(pt);
+      let (mut left, mut top, mut right, mut bottom) = (x, y, x + thick, y + thick);
+      // Stretched back to the last cell, if they're side by side
+      if i > 0 && path[i - 1].distance(pt) == 1 {
+        let (px, py) = corner(path[i - 1]);
+        left = left.min(px);
+        top = top.min(py);
+        right = right.max(px + thick);
+        bottom = bottom.max(py + thick);
+      }
+      for py in top..bottom.min(img.height()) {
+        for px in left..right.min(img.width()) {
+          img.put_pixel(px, py, red);
+        }
+      }
+    }
+    img
+  }
+
+  // Draws the maze with any cells `fill_color` gives a color to filled in,
+  // under the walls
+  fn draw_image(&self, style: &ImageStyle, fill_color: impl Fn(Point) -> Option<Rgba<u8>>) -> RgbaImage {
+    let step = style.cell_px + style.wall_px;
+    let width = self.width as u32 * step + style.wall_px;
+    let height = self.height as u32 * step + style.wall_px;
+    let mut img = RgbaImage::from_pixel(width, height, style.background);
+
+    let mut fill = |left: u32, top: u32, w: u32, h: u32, color: Rgba<u8>| {
+      for py in top..(top + h) {
+        for px in left..(left + w) {
+          img.put_pixel(px, py, color);
+        }
+      }
+    };
+    let long = step + style.wall_px;
+    // A cell's color runs over its sides too, so open passages get filled
+    // in; the walls go on top
+    for pt in self.iter() {
+      if let Some(color) = fill_color(pt) { fill(pt.x as u32 * step, pt.y as u32 * step, long, long, color) }
+    }
+    // Each wall runs the whole side of its cell and over the corners at
+    // either end, so walls that meet join up
+    let wall = style.wall;
+    for pt in self.iter() {
+      let (left, top) = (pt.x as u32 * step, pt.y as u32 * step);
+      if !self.open_side(pt, Dir::North) { fill(left, top, long, style.wall_px, wall) }
+      if !self.open_side(pt, Dir::West) { fill(left, top, style.wall_px, long, wall) }
+      if pt.x + 1 == self.width && !self.open_side(pt, Dir::East) { fill(left + step, top, style.wall_px, long, wall) }
+      if pt.y + 1 == self.height && !self.open_side(pt, Dir::South) { fill(left, top + step, long, style.wall_px, wall) }
+    }
+    img
+  }
+
+  /// Saves the maze as a PNG in the default style
+  pub fn save_png(&self, path: impl AsRef<std::path::Path>) -> ImageResult<()> {
+    self.to_image_styled(&ImageStyle::default()).save_with_format(path, image::ImageFormat::Png)
+  }
+
+  /// Writes the maze to `w` as a PNG in the default style
+  pub fn write_png<W: Write>(&self, w: &mut W) -> ImageResult<()> {
+    let img = self.to_image_styled(&ImageStyle::default());
+    PngEncoder::new(w).write_image(img.as_raw(), img.width(), img.height(), ColorType::Rgba8)
+  }
+
+  /// Generates the maze with `algorithm` and saves an animated GIF of it
+  /// happening, from the maze as it was before to the finished one, looping
+  pub fn record_generation_gif(&mut self, algorithm: Algorithm, path: impl AsRef<std::path::Path>,
+                               options: &GifOptions, rng: &mut impl Rng) -> ImageResult<()> {
+    let file = BufWriter::new(File::create(path)?);
+    self.write_generation_gif(algorithm, file, options, rng)
+  }
+
+  /// Like record_generation_gif, writing the GIF to `w`
+  pub fn write_generation_gif<W: Write>(&mut self, algorithm: Algorithm, w: W, options: &GifOptions,
+                                        rng: &mut impl Rng) -> ImageResult<()> {
+    let mut shown = self.clone();
+    let mut events = Vec::new();
+    self.generate_observed(algorithm, rng, |event| events.push(event));
+
+    // Two colors don't need the encoder's slow, careful palettes
+    let mut encoder = GifEncoder::new_with_speed(w, 30);
+    encoder.set_repeat(Repeat::Infinite)?;
+    let frame = |m: &Maze, last: bool| {
+      let ms = if last { options.last_frame_ms } else { options.frame_ms };
+      Frame::from_parts(m.to_image_styled(&options.style), 0, 0, Delay::from_numer_denom_ms(ms, 1))
+    };
+    encoder.encode_frame(frame(&shown, events.is_empty()))?;
+    let mut chunks = events.chunks(options.carves_per_frame.max(1)).peekable();
+    while let Some(chunk) = chunks.next() {
+      for &event in chunk {
+        match event {
+          GenerationEvent::Carve(pt, dir) => shown.carve(pt, dir).expect(""),
+          GenerationEvent::Wall(pt, dir) => shown.uncarve(pt, dir).expect(""),
+          _ => {}
+        }
+      }
+      encoder.encode_frame(frame(&shown, chunks.peek().is_none()))?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn maze_to_image_test() {
+    let mut m = Maze::new(3, 2).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    let style = ImageStyle { cell_px: 10, wall_px: 2, ..ImageStyle::default() };
+    let img = m.to_image(10, 2);
+    assert_eq!(img.dimensions(), (38, 26));
+
+    let white = Rgba([255, 255, 255, 255]);
+    let black = Rgba([0, 0, 0, 255]);
+    // The middle of (0, 0)
+    let (cx, cy) = (7, 7);
+    assert_eq!(*img.get_pixel(cx, cy), white);
+    // The passage east out of (0, 0) is open, the wall east of (1, 0) isn't
+    assert_eq!(*img.get_pixel(12, cy), white);
+    assert_eq!(*img.get_pixel(24, cy), black);
+    // All four corners of the border
+    for &(x, y) in [(0, 0), (37, 0), (0, 25), (37, 25)].iter() {
+      assert_eq!(*img.get_pixel(x, y), black);
+    }
+
+    // An open grid has no posts standing on their own in the middle
+    let open = Maze::new_open(2, 2).expect("");
+    assert_eq!(*open.to_image(10, 2).get_pixel(12, 12), white);
+
+    let red = Rgba([255, 0, 0, 255]);
+    let styled = m.to_image_styled(&ImageStyle { wall: red, ..style });
+    assert_eq!(*styled.get_pixel(0, 0), red);
+  }
+
+  #[test]
+  fn maze_image_heatmap_test() {
+    let mut m = Maze::new(3, 1).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    let heatmap = Heatmap::new(Point { x: 0, y: 0 });
+    let img = m.to_image_heatmap(&ImageStyle { cell_px: 10, wall_px: 2, ..ImageStyle::default() }, &heatmap);
+    let [r, g, b] = heatmap.far;
+    // Both ends of the passage, and the way between, in their colors;
+    // the cell walled off on its own stays white
+    assert_eq!(*img.get_pixel(7, 7), Rgba([255, 255, 204, 255]));
+    assert_eq!(*img.get_pixel(19, 7), Rgba([r, g, b, 255]));
+    assert_eq!(*img.get_pixel(12, 7), Rgba([r, g, b, 255]));
+    assert_eq!(*img.get_pixel(31, 7), Rgba([255, 255, 255, 255]));
+    assert_eq!(*img.get_pixel(24, 7), Rgba([0, 0, 0, 255]));
+    assert_eq!(m.to_image_heatmap(&ImageStyle::default(), &Heatmap { root: Point { x: 9, y: 9 }, ..heatmap }),
+               m.to_image_styled(&ImageStyle::default()));
+  }
+
+  #[test]
+  fn maze_image_with_solution_test() {
+    let mut m = Maze::new(3, 1).expect("");
+    let p = |x, y| Point { x, y };
+    m.carve(p(0, 0), Dir::East).expect("");
+    let style = ImageStyle { cell_px: 10, wall_px: 2, ..ImageStyle::default() };
+    let img = m.to_image_with_solution(&style, &[p(0, 0), p(1, 0)]);
+    let red = Rgba([255, 0, 0, 255]);
+    // Down the middle of both cells and through the passage between
+    for &x in [6, 7, 12, 19].iter() {
+      assert_eq!(*img.get_pixel(x, 7), red);
+    }
+    assert_eq!(*img.get_pixel(7, 5), Rgba([255, 255, 255, 255]));
+    assert_eq!(*img.get_pixel(24, 7), Rgba([0, 0, 0, 255]));
+    assert_eq!(m.to_image_with_solution(&style, &[]), m.to_image_styled(&style));
+  }
+
+  #[test]
+  fn maze_save_png_test() {
+    let m = Maze::new_seeded_square(4, 1).expect("");
+    let path = std::env::temp_dir().join(format!("maze-save-png-{}.png", std::process::id()));
+    m.save_png(&path).expect("");
+    let read = image::open(&path).expect("").to_rgba8();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(read, m.to_image_styled(&ImageStyle::default()));
+
+    let mut bytes = Vec::new();
+    m.write_png(&mut bytes).expect("");
+    assert_eq!(image::load_from_memory(&bytes).expect("").to_rgba8(), read);
+  }
+
+  #[test]
+  fn mask_from_image_test() {
+    // A dark gray square in the top left quarter, and a see-through black
+    // one in the bottom right
+    let mut img = RgbaImage::from_pixel(8, 8, Rgba([255, 255, 255, 255]));
+    for y in 0..8 {
+      for x in 0..8 {
+        if x < 4 && y < 4 { img.put_pixel(x, y, Rgba([60, 60, 60, 255])) }
+        if x >= 4 && y >= 4 { img.put_pixel(x, y, Rgba([0, 0, 0, 20])) }
+      }
+    }
+
+    let mask = Mask::from_rgba_image(&img, &ImageMaskOptions::default()).expect("");
+    assert_eq!((mask.width(), mask.height(), mask.count()), (8, 8, 16));
+    assert!(mask.get(Point { x: 3, y: 3 }));
+    assert!(!mask.get(Point { x: 7, y: 7 }));
+
+    let scaled = Mask::from_rgba_image(&img, &ImageMaskOptions { cell_px: 4, threshold: 128 }).expect("");
+    assert_eq!(scaled.points(), vec![Point { x: 0, y: 0 }]);
+    let dark = Mask::from_rgba_image(&img, &ImageMaskOptions { cell_px: 4, threshold: 50 }).expect("");
+    assert_eq!(dark.count(), 0);
+    assert!(Mask::from_rgba_image(&img, &ImageMaskOptions { cell_px: 9, threshold: 128 }).is_err());
+
+    let path = std::env::temp_dir().join(format!("maze-mask-{}.png", std::process::id()));
+    img.save(&path).expect("");
+    let read = Mask::from_image_with(&path, &ImageMaskOptions { cell_px: 2, threshold: 128 });
+    std::fs::remove_file(&path).ok();
+    assert_eq!(read.expect("").count(), 4);
+  }
+
+  #[test]
+  fn maze_generation_gif_test() {
+    use image::AnimationDecoder;
+    use image::codecs::gif::GifDecoder;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let options = GifOptions { carves_per_frame: 4, ..GifOptions::default() };
+    let mut bytes = Vec::new();
+    let mut m = Maze::new(4, 3).expect("");
+    m.write_generation_gif(Algorithm::Kruskal, &mut bytes, &options, &mut StdRng::seed_from_u64(1)).expect("");
+    assert_eq!(m.passage_count(), 11);
+
+    // The blank maze, then a frame for each four of the eleven carves
+    let frames = GifDecoder::new(&bytes[..]).expect("").into_frames().collect_frames().expect("");
+    assert_eq!(frames.len(), 4);
+    assert_eq!(frames[0].buffer(), &Maze::new(4, 3).expect("").to_image_styled(&options.style));
+    assert_eq!(frames[3].buffer(), &m.to_image_styled(&options.style));
+    assert_eq!(frames[0].delay(), Delay::from_numer_denom_ms(40, 1));
+    assert_eq!(frames[3].delay(), Delay::from_numer_denom_ms(2000, 1));
+  }
+}
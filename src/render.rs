@@ -0,0 +1,373 @@
+//! Text rendering.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::thread;
+use std::time::Duration;
+use crate::analysis::distance_char;
+use crate::{Dir, LabelMap, Maze, Path, Point};
+
+/// How render_styled draws walls: `+`, `-` and `|`, or box-drawing lines
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RenderStyle { Ascii, Unicode }
+
+impl Maze {
+  pub(crate) fn char(&self, point: Point, dir: Dir) -> &'static str {
+    if self.is_door(point, dir) {
+      " "
+    } else if self.passage(point, dir) {
+      // A passage off the side of the grid wraps around to the other side
+      if point.translate(dir).is_some_and(|n| self.valid(n)) { " " } else { "~" }
+    } else {
+      match dir {
+        Dir::North | Dir::South => "-",
+        Dir::East | Dir::West => "|"
+      }
+    }
+  }
+
+  /// Prints the maze as text to stdout
+  pub fn print(&self) {
+    print!("{}", self)
+  }
+
+  /// The maze as text, with walls drawn in +, - and | and empty cells; the
+  /// same as its Display
+  pub fn render_ascii(&self) -> String {
+    self.render(|_| ' ')
+  }
+
+  /// Draws the maze as text, asking `label` what to put inside each cell
+  pub fn render(&self, label: impl Fn(Point) -> char) -> String {
+    self.render_with(label, |pt, dir| self.char(pt, dir))
+  }
+
+  /// Like render, but `wall` also picks what to draw on each side of a cell
+  pub fn render_with(&self, label: impl Fn(Point) -> char, wall: impl Fn(Point, Dir) -> &'static str) -> String {
+    let mut out = Vec::new();
+    self.write_with(&mut out, label, wall, |_, _| '+').expect("");
+    String::from_utf8(out).expect("")
+  }
+
+  /// Draws the maze as text in the given style, with empty cells
+  pub fn render_styled(&self, style: RenderStyle) -> String {
+    let mut out = Vec::new();
+    self.write_styled(&mut out, style).expect("");
+    String::from_utf8(out).expect("")
+  }
+
+  /// Draws the maze as text in the given style, with whatever `glyph` makes
+  /// of each cell's data in it. Cells it gives None for, and any the map
+  /// doesn't cover, are left empty.
+  pub fn render_data<T>(&self, style: RenderStyle, data: &LabelMap<T>, glyph: impl Fn(&T) -> Option<char>) -> String {
+    let mut out = Vec::new();
+    self.write_styled_with(&mut out, style, |pt| data.get(pt).and_then(&glyph).unwrap_or(' '), |_, _| false)
+      .expect("");
+    String::from_utf8(out).expect("")
+  }
+
+  /// Draws the maze as text in the given style with a path through it:
+  /// `*` in each cell along it and the passages between them, or `•` in
+  /// box-drawing style
+  pub fn render_with_solution(&self, style: RenderStyle, path: &[Point]) -> String {
+    let mark = match style { RenderStyle::Ascii => '*', RenderStyle::Unicode => '•' };
+    let cells: HashSet<Point> = path.iter().copied().collect();
+    // Both sides of each passage taken, since which side gets drawn
+    // depends on where it is
+    let sides: HashSet<(Point, Dir)> = path.windows(2)
+      .filter_map(|step| self.direction_between(step[0], step[1]).map(|dir| (step, dir)))
+      .flat_map(|(step, dir)| vec![(step[0], dir), (step[1], dir.opposite())])
+      .filter(|&(pt, dir)| self.passage(pt, dir))
+      .collect();
+    let mut out = Vec::new();
+    self.write_styled_with(&mut out, style, |pt| if cells.contains(&pt) { mark } else { ' ' },
+                           |pt, dir| sides.contains(&(pt, dir))).expect("");
+    String::from_utf8(out).expect("")
+  }
+
+  /// Writes render_styled's text to `w` a line at a time, like write_ascii
+  pub fn write_styled<W: Write>(&self, w: &mut W, style: RenderStyle) -> io::Result<()> {
+    self.write_styled_with(w, style, |_| ' ', |_, _| false)
+  }
+
+  // Writes the maze in `style` with `label` in each cell, and the sides
+  // `on_path` picks out marked like render_with_solution's path
+  fn write_styled_with<W: Write>(&self, w: &mut W, style: RenderStyle, label: impl Fn(Point) -> char,
+                                 on_path: impl Fn(Point, Dir) -> bool) -> io::Result<()> {
+    match style {
+      RenderStyle::Ascii => self.write_with(w, label, |pt, dir| {
+        if on_path(pt, dir) { "*" } else { self.char(pt, dir) }
+      }, |_, _| '+'),
+      RenderStyle::Unicode => self.write_with(w, label, |pt, dir| {
+        if on_path(pt, dir) { return "•" }
+        match (self.open_side(pt, dir), dir) {
+          (true, _) => " ",
+          (false, Dir::North) | (false, Dir::South) => "─",
+          (false, _) => "│"
+        }
+      }, |cx, cy| self.box_corner(cx, cy))
+    }
+  }
+
+  // The box-drawing character joining up whichever of the four walls that
+  // meet at the corner (cx, cy) are there. Corners are numbered like cells,
+  // (0, 0) being the top left corner of the top left cell, so they run to
+  // (width, height).
+  fn box_corner(&self, cx: usize, cy: usize) -> char {
+    // Across the top of cell (x, cy), or the bottom of the last row
+    let across = |x: usize| if cy < self.height {
+      !self.open_side(Point { x, y: cy }, Dir::North)
+    } else {
+      !self.open_side(Point { x, y: self.height - 1 }, Dir::South)
+    };
+    // Down the left side of cell (cx, y), or the right of the last column
+    let down = |y: usize| if cx < self.width {
+      !self.open_side(Point { x: cx, y }, Dir::West)
+    } else {
+      !self.open_side(Point { x: self.width - 1, y }, Dir::East)
+    };
+
+    let up = cy > 0 && down(cy - 1);
+    let below = cy < self.height && down(cy);
+    let left = cx > 0 && across(cx - 1);
+    let right = cx < self.width && across(cx);
+    match (up, below, left, right) {
+      (false, false, false, false) => ' ',
+      (true, false, false, false) => '╵',
+      (false, true, false, false) => '╷',
+      (false, false, true, false) => '╴',
+      (false, false, false, true) => '╶',
+      (true, true, false, false) => '│',
+      (false, false, true, true) => '─',
+      (false, true, false, true) => '┌',
+      (false, true, true, false) => '┐',
+      (true, false, false, true) => '└',
+      (true, false, true, false) => '┘',
+      (true, true, false, true) => '├',
+      (true, true, true, false) => '┤',
+      (false, true, true, true) => '┬',
+      (true, false, true, true) => '┴',
+      (true, true, true, true) => '┼'
+    }
+  }
+
+  /// Writes the same text as render_ascii to `w`, a line at a time, so a
+  /// huge maze never has to be held in memory as one string
+  pub fn write_ascii<W: Write>(&self, w: &mut W) -> io::Result<()> {
+    self.write_with(w, |_| ' ', |pt, dir| self.char(pt, dir), |_, _| '+')
+  }
+
+  // Writes the maze as text: `label` for what's in each cell, `wall` for
+  // each side of one, and `corner` for where walls meet, given as corner
+  // numbers like box_corner's
+  fn write_with<W: Write>(&self, w: &mut W, label: impl Fn(Point) -> char,
+                          wall: impl Fn(Point, Dir) -> &'static str,
+                          corner: impl Fn(usize, usize) -> char) -> io::Result<()> {
+    let mut line = String::new();
+
+    // First a line of norths
+    for x in 0..(self.width) {
+      line.push(corner(x, 0));
+      line.push_str(wall(Point{x, y: 0}, Dir::North))
+    }
+    line.push(corner(self.width, 0));
+    line.push('\n');
+    w.write_all(line.as_bytes())?;
+
+    // Then a loop for each row...
+    for y in 0..(self.height) {
+      // with the first west, then all easts
+      line.clear();
+      line.push_str(wall(Point{x: 0, y}, Dir::West));
+      for x in 0..(self.width) {
+        line.push(label(Point{x, y}));
+        line.push_str(wall(Point{x, y}, Dir::East));
+      }
+      line.push('\n');
+      // Then all souths
+      for x in 0..(self.width) {
+        line.push(corner(x, y + 1));
+        line.push_str(wall(Point{x, y}, Dir::South));
+      }
+      line.push(corner(self.width, y + 1));
+      line.push('\n');
+      w.write_all(line.as_bytes())?;
+    }
+    Ok(())
+  }
+
+  /// One frame per step of a breadth-first search from `from` to `to`, with
+  /// visited cells marked '·', the frontier '!', and the path once found '*'
+  pub fn solution_step_frames(&self, from: Point, to: Point) -> Vec<String> {
+    self.solve_iter(from, to).map(|step| {
+      self.render(|pt| {
+        if step.path.as_ref().is_some_and(|path| path.contains(&pt)) { '*' }
+        else if step.frontier.contains(&pt) { '!' }
+        else if step.visited.contains(&pt) { '·' }
+        else { ' ' }
+      })
+    }).collect()
+  }
+
+  /// Animates the search in place on a terminal; anywhere else (a pipe or
+  /// a file) the frames are printed one after another, separated by "---"
+  pub fn print_solution_steps(&self, from: Point, to: Point) {
+    let tty = io::stdout().is_terminal();
+    for (n, frame) in self.solution_step_frames(from, to).iter().enumerate() {
+      if n > 0 {
+        if tty {
+          print!("\x1b[{}A", frame.lines().count());
+        } else {
+          println!("---");
+        }
+      }
+      print!("{}", frame);
+      if tty {
+        io::stdout().flush().ok();
+        thread::sleep(Duration::from_millis(100));
+      }
+    }
+  }
+
+  /// Draws the maze with arrows along the path showing which way to go next,
+  /// and a star on the last cell
+  pub fn render_solution_as_arrows(&self, path: &Path) -> String {
+    let mut arrows = HashMap::new();
+    for step in path.windows(2) {
+      if let Some(dir) = self.direction_between(step[0], step[1]) {
+        arrows.insert(step[0], match dir {
+          Dir::North => '↑',
+          Dir::South => '↓',
+          Dir::East => '→',
+          Dir::West => '←'
+        });
+      }
+    }
+    if let Some(&last) = path.last() {
+      arrows.insert(last, '★');
+    }
+    self.render(|pt| *arrows.get(&pt).unwrap_or(&' '))
+  }
+
+  /// Prints render_solution_as_arrows to stdout
+  pub fn print_solution_as_arrows(&self, path: &Path) {
+    print!("{}", self.render_solution_as_arrows(path))
+  }
+
+  /// Draws the maze with each cell's distance from `from` in it, the same
+  /// way DistanceMap displays them
+  pub fn print_distances(&self, from: Point) {
+    let distances = self.distances(from);
+    print!("{}", self.render(|pt| distance_char(distances[pt])))
+  }
+}
+
+impl fmt::Display for Maze {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(&self.render_ascii())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn maze_render_test() {
+    let mut m = Maze::new(2, 2).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::South).expect("");
+    assert_eq!(m.render(|pt| if pt.x == pt.y { 'o' } else { ' ' }),
+               "+-+-+\n|o  |\n+-+ +\n| |o|\n+-+-+\n");
+  }
+
+  #[test]
+  fn maze_solution_arrows_test() {
+    let mut m = Maze::new(2, 2).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::South).expect("");
+    m.carve(Point { x: 1, y: 1 }, Dir::West).expect("");
+
+    let path = vec![Point { x: 0, y: 0 }, Point { x: 1, y: 0 }, Point { x: 1, y: 1 }, Point { x: 0, y: 1 }];
+    assert_eq!(m.render_solution_as_arrows(&path), "+-+-+\n|→ ↓|\n+-+ +\n|★ ←|\n+-+-+\n");
+  }
+
+  #[test]
+  fn maze_display_test() {
+    let mut m = Maze::new(3, 1).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    assert_eq!(m.render_ascii(), "+-+-+-+\n|   | |\n+-+-+-+\n");
+    assert_eq!(m.to_string(), m.render_ascii());
+    assert_eq!(format!("{}", m), m.render(|_| ' '));
+  }
+
+  #[test]
+  fn maze_write_ascii_test() {
+    let mut m = Maze::new(4, 3).expect("");
+    m.carve(Point { x: 1, y: 1 }, Dir::South).expect("");
+    let mut out = Vec::new();
+    m.write_ascii(&mut out).expect("");
+    assert_eq!(String::from_utf8(out).expect(""), m.render_ascii());
+
+    // A writer that gives up partway through passes its error on
+    struct Full(usize);
+    impl Write for Full {
+      fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.0 < buf.len() { return Err(io::Error::new(io::ErrorKind::WriteZero, "full")) }
+        self.0 -= buf.len();
+        Ok(buf.len())
+      }
+      fn flush(&mut self) -> io::Result<()> { Ok(()) }
+    }
+    assert!(m.write_ascii(&mut Full(20)).is_err());
+    assert!(m.write_ascii(&mut Full(1000)).is_ok());
+  }
+
+  #[test]
+  fn maze_render_unicode_test() {
+    let p = |x, y| Point { x, y };
+    let mut m = Maze::new(3, 2).expect("");
+    m.carve(p(0, 0), Dir::East).expect("");
+    m.carve(p(1, 0), Dir::East).expect("");
+    m.carve(p(1, 0), Dir::South).expect("");
+    m.carve(p(0, 0), Dir::South).expect("");
+    assert_eq!(m.render_styled(RenderStyle::Unicode),
+               "┌─────┐\n│     │\n│ ╷ ┌─┤\n│ │ │ │\n└─┴─┴─┘\n");
+    assert_eq!(m.render_styled(RenderStyle::Ascii), m.render_ascii());
+
+    let mut out = Vec::new();
+    m.write_styled(&mut out, RenderStyle::Unicode).expect("");
+    assert_eq!(String::from_utf8(out).expect(""), m.render_styled(RenderStyle::Unicode));
+  }
+
+  #[test]
+  fn maze_render_data_test() {
+    let mut m = Maze::new(3, 1).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    let mut monsters: LabelMap<Option<&str>> = m.cell_data(None);
+    monsters[Point { x: 0, y: 0 }] = Some("goblin");
+    monsters[Point { x: 2, y: 0 }] = Some("orc");
+    let glyph = |monster: &Option<&str>| monster.and_then(|name| name.chars().next());
+    assert_eq!(m.render_data(RenderStyle::Ascii, &monsters, glyph), "+-+-+-+\n|g  |o|\n+-+-+-+\n");
+    assert_eq!(m.render_data(RenderStyle::Unicode, &monsters, glyph), "┌───┬─┐\n│g  │o│\n└───┴─┘\n");
+    // A map too small for the maze leaves the rest empty
+    let small = LabelMap::new(1, 1, 'x');
+    assert_eq!(m.render_data(RenderStyle::Ascii, &small, |&c| Some(c)), "+-+-+-+\n|x  | |\n+-+-+-+\n");
+  }
+
+  #[test]
+  fn maze_render_with_solution_test() {
+    let mut m = Maze::new(2, 2).expect("");
+    let p = |x, y| Point { x, y };
+    m.carve(p(0, 0), Dir::East).expect("");
+    m.carve(p(1, 0), Dir::South).expect("");
+    m.carve(p(0, 1), Dir::East).expect("");
+    let path = m.solve(p(0, 0), p(0, 1)).expect("");
+    assert_eq!(m.render_with_solution(RenderStyle::Ascii, &path), "+-+-+\n|***|\n+-+*+\n|***|\n+-+-+\n");
+    assert_eq!(m.render_with_solution(RenderStyle::Unicode, &path), "┌───┐\n│•••│\n├─╴•│\n│•••│\n└───┘\n");
+    // A jump between cells that aren't joined only marks the cells
+    assert_eq!(m.render_with_solution(RenderStyle::Ascii, &[p(0, 0), p(0, 1)]), "+-+-+\n|*  |\n+-+ +\n|*  |\n+-+-+\n");
+    assert_eq!(m.render_with_solution(RenderStyle::Ascii, &[]), m.render_styled(RenderStyle::Ascii));
+  }
+}
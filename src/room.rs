@@ -0,0 +1,160 @@
+//! Rooms: open areas in a maze, joined up by corridors, for dungeons.
+
+use rand::Rng;
+use crate::{BoundsError, Dir, Maze, Point};
+
+/// A rectangle of cells with no walls inside it, `origin` being its top left
+/// cell
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Room {
+  pub origin: Point,
+  pub width: usize,
+  pub height: usize,
+}
+
+impl Room {
+  pub fn new(origin: Point, width: usize, height: usize) -> Room {
+    Room { origin, width, height }
+  }
+
+  pub fn contains(&self, point: Point) -> bool {
+    point.x >= self.origin.x && point.x < self.origin.x + self.width &&
+      point.y >= self.origin.y && point.y < self.origin.y + self.height
+  }
+
+  /// Whether the two rooms share any cells
+  pub fn overlaps(&self, other: &Room) -> bool {
+    self.origin.x < other.origin.x + other.width && other.origin.x < self.origin.x + self.width &&
+      self.origin.y < other.origin.y + other.height && other.origin.y < self.origin.y + self.height
+  }
+
+  /// Every cell in the room, in row-major order
+  pub fn cells(&self) -> impl Iterator<Item = Point> + '_ {
+    (self.origin.y..self.origin.y + self.height)
+      .flat_map(move |y| (self.origin.x..self.origin.x + self.width).map(move |x| Point { x, y }))
+  }
+
+  /// The ways into the room through its walls in `maze`: the cell inside it
+  /// and the side the passage is on
+  pub fn doorways(&self, maze: &Maze) -> Vec<(Point, Dir)> {
+    self.cells()
+      .flat_map(|pt| Dir::ALL.iter().map(move |&dir| (pt, dir)))
+      .filter(|&(pt, dir)| maze.passage(pt, dir) && maze.neighbor(pt, dir).is_some_and(|n| !self.contains(n)))
+      .collect()
+  }
+}
+
+impl Maze {
+  /// Knocks down every wall inside a room. The room has to fit in the maze,
+  /// with every cell in its mask if it has one.
+  pub fn carve_room(&mut self, room: &Room) -> Result<(), BoundsError> {
+    if !self.room_fits(room) { return Err(BoundsError) }
+    for pt in room.cells() {
+      for &dir in [Dir::East, Dir::South].iter() {
+        if self.neighbor(pt, dir).is_some_and(|n| room.contains(n)) { self.carve(pt, dir)? }
+      }
+    }
+    Ok(())
+  }
+
+  // Whether carve_room would take the room: not empty, and all in the maze
+  fn room_fits(&self, room: &Room) -> bool {
+    room.width > 0 && room.height > 0 && room.cells().all(|pt| self.in_maze(pt))
+  }
+
+  /// A dungeon: carves out the rooms, then fills the rest of the maze with
+  /// corridors using Kruskal's. Each room counts as a single cell to it, so
+  /// everything ends up joined with no loops outside the rooms, and a room
+  /// gets a doorway for every corridor that reaches it. Rooms can touch but
+  /// not overlap. If any room doesn't fit, the maze is left as it was.
+  pub fn rooms_and_corridors(&mut self, rooms: &[Room], rng: &mut impl Rng) -> Result<(), BoundsError> {
+    for (i, room) in rooms.iter().enumerate() {
+      if !self.room_fits(room) || rooms[..i].iter().any(|other| other.overlaps(room)) { return Err(BoundsError) }
+    }
+    for room in rooms {
+      self.carve_room(room)?;
+    }
+    self.kruskal(rng);
+    Ok(())
+  }
+
+  /// Up to `count` rooms between `min` and `max` cells on a side, placed at
+  /// random where they fit without touching each other, for passing to
+  /// rooms_and_corridors. Gives up on a room after a few tries, so a
+  /// crowded maze can get fewer.
+  pub fn random_rooms(&self, count: usize, min: usize, max: usize, rng: &mut impl Rng) -> Vec<Room> {
+    let mut rooms: Vec<Room> = Vec::new();
+    if min == 0 || min > max { return rooms }
+    for _ in 0..count * 10 {
+      if rooms.len() == count { break }
+      let (width, height) = (rng.gen_range(min, max + 1), rng.gen_range(min, max + 1));
+      if width > self.width || height > self.height { continue }
+      let origin = Point { x: rng.gen_range(0, self.width - width + 1), y: rng.gen_range(0, self.height - height + 1) };
+      let room = Room { origin, width, height };
+      // A cell's gap all round, so corridors can get between them
+      let padded = Room {
+        origin: Point { x: origin.x.saturating_sub(1), y: origin.y.saturating_sub(1) },
+        width: width + 2,
+        height: height + 2,
+      };
+      if room.cells().all(|pt| self.in_maze(pt)) && !rooms.iter().any(|other| other.overlaps(&padded)) {
+        rooms.push(room);
+      }
+    }
+    rooms
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::rngs::StdRng;
+  use rand::SeedableRng;
+
+  #[test]
+  fn room_test() {
+    let p = |x, y| Point { x, y };
+    let room = Room::new(p(1, 1), 3, 2);
+    assert!(room.contains(p(3, 2)));
+    assert!(!room.contains(p(4, 2)));
+    assert!(!room.contains(p(0, 1)));
+    assert_eq!(room.cells().count(), 6);
+    assert!(room.overlaps(&Room::new(p(3, 0), 2, 2)));
+    assert!(!room.overlaps(&Room::new(p(4, 1), 2, 2)));
+
+    let mut m = Maze::new(5, 4).expect("");
+    m.carve_room(&room).expect("");
+    assert_eq!(m.passage_count(), 7);
+    assert!(room.doorways(&m).is_empty());
+    m.carve(p(1, 1), Dir::North).expect("");
+    assert_eq!(room.doorways(&m), vec![(p(1, 1), Dir::North)]);
+    assert!(m.carve_room(&Room::new(p(3, 3), 3, 1)).is_err());
+    assert!(m.carve_room(&Room::new(p(0, 0), 0, 1)).is_err());
+  }
+
+  #[test]
+  fn rooms_and_corridors_test() {
+    let mut rng = StdRng::seed_from_u64(5);
+    let mut m = Maze::new(20, 15).expect("");
+    let rooms = m.random_rooms(4, 2, 4, &mut rng);
+    assert_eq!(rooms.len(), 4);
+    m.rooms_and_corridors(&rooms, &mut rng).expect("");
+
+    // A tree once each room is squashed down to one cell
+    let room_cells: usize = rooms.iter().map(|room| room.width * room.height).sum();
+    let room_passages: usize = rooms.iter().map(|room| 2 * room.width * room.height - room.width - room.height).sum();
+    assert_eq!(m.passage_count() - room_passages, 20 * 15 - room_cells + rooms.len() - 1);
+    assert!(m.are_all_cells_mutually_reachable());
+    for room in rooms.iter() {
+      assert!(!room.doorways(&m).is_empty());
+    }
+
+    let overlapping = [Room::new(Point { x: 0, y: 0 }, 3, 3), Room::new(Point { x: 2, y: 2 }, 3, 3)];
+    assert!(Maze::new(10, 10).expect("").rooms_and_corridors(&overlapping, &mut rng).is_err());
+    // Nothing is carved if a later room doesn't fit
+    let mut untouched = Maze::new(10, 10).expect("");
+    let off_the_edge = [Room::new(Point { x: 0, y: 0 }, 3, 3), Room::new(Point { x: 8, y: 8 }, 3, 3)];
+    assert!(untouched.rooms_and_corridors(&off_the_edge, &mut rng).is_err());
+    assert_eq!(untouched.passage_count(), 0);
+  }
+}
@@ -0,0 +1,684 @@
+//! Finding routes through a maze, and rating them.
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use crate::{BoundsError, Dir, Maze, MazeError, Path, Point};
+
+/// Why an InteractiveSolver couldn't make a move
+#[derive(Debug, PartialEq)]
+pub enum MoveError {
+  WallInTheWay,
+  OutOfBounds,
+}
+
+/// A player walking through a maze one move at a time, for games and for
+/// testing solvers that only get to see where they are. It remembers every
+/// cell it's stood on and how many moves it's made; bumping into a wall
+/// doesn't count as a move.
+pub struct InteractiveSolver<'a> {
+  maze: &'a Maze,
+  position: Point,
+  visited: HashSet<Point>,
+  steps: usize,
+}
+
+/// Breadth-first search, a step at a time: each step moves the whole
+/// frontier into the visited set and finds the cells one passage beyond them
+pub struct SolveIter<'a> {
+  maze: &'a Maze,
+  goal: Point,
+  parents: Vec<Option<Point>>,
+  visited: Vec<bool>,
+  frontier: Vec<Point>,
+  done: bool,
+}
+
+/// One layer of a SolveIter search: everything visited so far, the cells
+/// just reached, and the path once the goal has been found
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolveStep {
+  pub visited: Vec<Point>,
+  pub frontier: Vec<Point>,
+  pub path: Option<Vec<Point>>,
+}
+
+/// How hard a route is to follow; see Maze::score_solution_path
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolutionScore {
+  pub length: usize,
+  pub turns: usize,
+  pub dead_ends_encountered: usize,
+  pub backtrack_count: usize,
+  pub difficulty_index: f64,
+}
+
+/// One move of a depth-first search: every cell reached so far, every cell
+/// abandoned as a wrong turn so far, and where the search is now
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolveFrame {
+  pub visited: Vec<Point>,
+  pub backtracked: Vec<Point>,
+  pub current: Option<Point>,
+}
+
+impl Maze {
+  /// A breadth-first search from `from` to `to`, a layer of cells at a time
+  pub fn solve_iter(&self, from: Point, to: Point) -> SolveIter<'_> {
+    SolveIter {
+      maze: self,
+      goal: to,
+      parents: vec![None; self.width * self.height],
+      visited: vec![false; self.width * self.height],
+      frontier: if self.valid(from) { vec![from] } else { vec![] },
+      done: !self.valid(to),
+    }
+  }
+
+  /// Solves like solve, by the same search as solve_iter, showing `observer`
+  /// each layer of it on the way
+  pub fn solve_observed(&self, from: Point, to: Point, mut observer: impl FnMut(&SolveStep)) -> Option<Path> {
+    for step in self.solve_iter(from, to) {
+      observer(&step);
+      if step.path.is_some() { return step.path }
+    }
+    None
+  }
+
+  /// Walks the maze from a cell always taking the rightmost open passage
+  /// (right, then straight, then left, turning back only at a dead end),
+  /// returning the cells in the order they're first reached. In a perfect
+  /// maze this visits everything; with loops it may not. The walk stops as
+  /// soon as it's back in a cell facing a way it has already faced there.
+  pub fn spiral_path_from(&self, start: Point) -> Option<Vec<Point>> {
+    if !self.valid(start) { return None }
+
+    let mut order = vec![start];
+    let mut seen = vec![false; self.width * self.height];
+    let mut states = HashSet::new();
+    let mut pt = start;
+    let mut facing = Dir::North;
+    seen[self.index(start)] = true;
+
+    while states.insert((pt, facing)) {
+      let choices = [facing.right(), facing, facing.left(), facing.opposite()];
+      if let Some(&dir) = choices.iter().find(|&&dir| self.passage(pt, dir)) {
+        pt = self.neighbor(pt, dir)?;
+        facing = dir;
+        if !seen[self.index(pt)] {
+          seen[self.index(pt)] = true;
+          order.push(pt);
+        }
+      } else {
+        break
+      }
+    }
+    Some(order)
+  }
+
+  /// A depth-first search from `from` to `to`, recorded one move at a time:
+  /// a frame for every step forward and every step back out of a dead end.
+  /// The last frame's `current` is `to`, or None if it can't be reached.
+  pub fn solve_with_backtracking_visualization(&self, from: Point, to: Point) -> Vec<SolveFrame> {
+    let mut frames = Vec::new();
+    if !self.valid(from) || !self.valid(to) { return frames }
+
+    let mut seen = vec![false; self.width * self.height];
+    let mut visited = vec![from];
+    let mut backtracked = Vec::new();
+    let mut stack = vec![from];
+    seen[self.index(from)] = true;
+    frames.push(SolveFrame { visited: visited.clone(), backtracked: vec![], current: Some(from) });
+
+    while let Some(&pt) = stack.last() {
+      if pt == to { break }
+
+      if let Some(n) = self.open_neighbors(pt).into_iter().find(|&n| !seen[self.index(n)]) {
+        seen[self.index(n)] = true;
+        visited.push(n);
+        stack.push(n);
+      } else {
+        backtracked.push(pt);
+        stack.pop();
+      }
+      frames.push(SolveFrame {
+        visited: visited.clone(),
+        backtracked: backtracked.clone(),
+        current: stack.last().copied(),
+      });
+    }
+    frames
+  }
+
+  /// Breadth-first distance from a cell to every cell, in row-major order,
+  /// None where it can't be reached, which is everywhere from a point
+  /// outside the maze
+  pub fn bfs_distances(&self, from: Point) -> Vec<Option<usize>> {
+    let mut distances = vec![None; self.width * self.height];
+    if !self.valid(from) { return distances }
+    let mut queue = VecDeque::new();
+    distances[self.index(from)] = Some(0);
+    queue.push_back(from);
+
+    while let Some(pt) = queue.pop_front() {
+      let d = distances[self.index(pt)].expect("");
+      for n in self.open_neighbors(pt) {
+        if distances[self.index(n)].is_none() {
+          distances[self.index(n)] = Some(d + 1);
+          queue.push_back(n);
+        }
+      }
+    }
+    distances
+  }
+
+  /// Every cell reachable from `from` with its distance, nearest first and
+  /// in row-major order among cells at the same distance
+  pub fn cells_sorted_by_distance_from(&self, from: Point) -> Result<Vec<(Point, usize)>, BoundsError> {
+    if !self.valid(from) { return Err(BoundsError) }
+    let distances = self.bfs_distances(from);
+    let mut cells: Vec<(Point, usize)> = self.iter()
+      .filter_map(|pt| Some((pt, distances[self.index(pt)]?)))
+      .collect();
+    cells.sort_by_key(|&(_, d)| d);
+    Ok(cells)
+  }
+
+  /// The shortest route from `start` to `goal`, both ends included, found
+  /// by breadth-first search. None if there isn't one or either end is
+  /// outside the maze.
+  pub fn solve(&self, start: Point, goal: Point) -> Option<Path> {
+    if !self.valid(start) || !self.valid(goal) { return None }
+    let mut parents = vec![None; self.width * self.height];
+    let mut seen = vec![false; self.width * self.height];
+    let mut queue = VecDeque::new();
+    seen[self.index(start)] = true;
+    queue.push_back(start);
+
+    while let Some(pt) = queue.pop_front() {
+      if pt == goal { return Some(self.path_to(&parents, goal)) }
+      for n in self.open_neighbors(pt) {
+        if !seen[self.index(n)] {
+          seen[self.index(n)] = true;
+          parents[self.index(n)] = Some(pt);
+          queue.push_back(n);
+        }
+      }
+    }
+    None
+  }
+
+  /// The two cells farthest apart and the route between them, found by
+  /// going to the farthest cell from the top left corner (or a masked
+  /// maze's first cell) and then to the farthest cell from there. In a
+  /// perfect maze that's the longest route there is, which makes its ends a
+  /// good entrance and exit; with loops it is only a good guess. Only the
+  /// corner's own part of a maze in pieces is looked at.
+  pub fn longest_path(&self) -> (Point, Point, Path) {
+    let (from, _) = self.distances(self.first_cell()).max().expect("");
+    let (to, _) = self.distances(from).max().expect("");
+    (from, to, self.solve(from, to).expect(""))
+  }
+
+  /// Opens an entrance and an exit in the outside wall as far apart as they
+  /// can be: the ends of the longest path between cells on the edge, found
+  /// the same way as longest_path. Returns where they went, or a
+  /// BoundsError for a maze with no outside wall, like a torus.
+  pub fn place_entrance_and_exit(&mut self) -> Result<(Point, Point), BoundsError> {
+    let edge: Vec<(Point, Dir)> = self.iter()
+      .filter_map(|pt| Dir::ALL.iter().find(|&&dir| self.faces_outside(pt, dir)).map(|&dir| (pt, dir)))
+      .collect();
+    let farthest = |from: Point| {
+      let distances = self.distances(from);
+      edge.iter().copied().filter_map(|(pt, dir)| distances[pt].map(|d| (d, pt, dir)))
+        .fold(None, |best: Option<(usize, Point, Dir)>, next| match best {
+          Some((b, _, _)) if b >= next.0 => best,
+          _ => Some(next)
+        })
+    };
+    let &(first, _) = edge.first().ok_or(BoundsError)?;
+    let (_, from, from_dir) = farthest(first).ok_or(BoundsError)?;
+    let (_, to, to_dir) = farthest(from).ok_or(BoundsError)?;
+    self.open_entrance(from, from_dir)?;
+    self.open_exit(to, to_dir)?;
+    Ok((from, to))
+  }
+
+  // Follows parent links back from `goal` to the cell that has none
+  pub(crate) fn path_to(&self, parents: &[Option<Point>], goal: Point) -> Path {
+    let mut path = vec![goal];
+    while let Some(parent) = parents[self.index(path[path.len() - 1])] {
+      path.push(parent);
+    }
+    path.reverse();
+    path
+  }
+
+  /// Whether `to` can be reached from `from`
+  pub fn path_exists(&self, from: Point, to: Point) -> bool {
+    self.valid(from) && self.valid(to) && self.reachable_from(from)[self.index(to)]
+  }
+
+  /// Whether the entrance and exit are both set and connected
+  pub fn is_solvable(&self) -> bool {
+    self.assert_solvable().is_ok()
+  }
+
+  /// Checks there's an entrance and an exit with a path between them,
+  /// saying which is missing if not
+  pub fn assert_solvable(&self) -> Result<(), MazeError> {
+    let entrance = self.entrance.ok_or(MazeError::MissingEntrance)?;
+    let exit = self.exit.ok_or(MazeError::MissingExit)?;
+    if self.path_exists(entrance, exit) { Ok(()) } else { Err(MazeError::NoPath) }
+  }
+
+  /// Rates how hard a route through the maze is to find. `length` counts
+  /// steps, `turns` changes of direction, `dead_ends_encountered` the side
+  /// passages leading off the route, and `backtrack_count` how many steps
+  /// longer it is than the shortest route between its ends. The difficulty
+  /// index weighs these up as length + turns / 2 + 2 * dead ends +
+  /// backtracks, divided by the number of cells in the maze.
+  pub fn score_solution_path(&self, path: &Path) -> SolutionScore {
+    let length = path.len().saturating_sub(1);
+    let dirs: Vec<Option<Dir>> = path.windows(2).map(|w| self.direction_between(w[0], w[1])).collect();
+    let turns = dirs.windows(2).filter(|w| w[0] != w[1]).count();
+
+    let on_path: HashSet<Point> = path.iter().copied().collect();
+    let dead_ends_encountered = on_path.iter()
+      .map(|&pt| self.open_neighbors(pt).into_iter().filter(|n| !on_path.contains(n)).count())
+      .sum();
+
+    let backtrack_count = match (path.first(), path.last()) {
+      (Some(&from), Some(&to)) if self.valid(from) && self.valid(to) => {
+        self.bfs_distances(from)[self.index(to)].map_or(0, |shortest| length.saturating_sub(shortest))
+      },
+      _ => 0
+    };
+
+    let difficulty_index = (length as f64 + turns as f64 / 2.0 + 2.0 * dead_ends_encountered as f64 +
+                            backtrack_count as f64) / (self.width * self.height) as f64;
+    SolutionScore { length, turns, dead_ends_encountered, backtrack_count, difficulty_index }
+  }
+
+  /// Wanders the maze from `start` trying to reach every cell, returning
+  /// them in the order first reached. Each step goes to an unvisited
+  /// neighbor if there is one, picking the one with the fewest unvisited
+  /// neighbors of its own (Warnsdorff's rule, ties broken at random), and
+  /// otherwise to a random neighbor. Stops once everything reachable has
+  /// been visited or after `max_steps` steps.
+  pub fn random_hamiltonian_walk(&self, start: Point, rng: &mut impl Rng, max_steps: usize) -> Vec<Point> {
+    if !self.valid(start) { return vec![] }
+    let reachable = self.reachable_from(start).iter().filter(|&&r| r).count();
+    let mut visited = vec![false; self.width * self.height];
+    let mut order = vec![start];
+    let mut pt = start;
+    visited[self.index(start)] = true;
+
+    for _ in 0..max_steps {
+      if order.len() == reachable { break }
+      let onward = |n: Point| self.open_neighbors(n).iter().filter(|&&m| !visited[self.index(m)]).count();
+      let unvisited: Vec<Point> = self.open_neighbors(pt).into_iter().filter(|&n| !visited[self.index(n)]).collect();
+
+      pt = if let Some(fewest) = unvisited.iter().map(|&n| onward(n)).min() {
+        let best: Vec<Point> = unvisited.into_iter().filter(|&n| onward(n) == fewest).collect();
+        *best.choose(rng).expect("")
+      } else {
+        *self.open_neighbors(pt).choose(rng).expect("")
+      };
+
+      if !visited[self.index(pt)] {
+        visited[self.index(pt)] = true;
+        order.push(pt);
+      }
+    }
+    order
+  }
+
+  /// Somebody standing at `from`, ready to walk the maze a step at a time
+  pub fn solve_interactive(&self, from: Point) -> InteractiveSolver<'_> {
+    InteractiveSolver::new(self, from)
+  }
+}
+
+impl<'a> InteractiveSolver<'a> {
+  pub fn new(maze: &'a Maze, start: Point) -> Self {
+    let mut visited = HashSet::new();
+    visited.insert(start);
+    InteractiveSolver { maze, position: start, visited, steps: 0 }
+  }
+
+  /// Steps one cell `dir`-ward, returning the new position
+  pub fn move_to(&mut self, dir: Dir) -> Result<Point, MoveError> {
+    let next = self.maze.neighbor(self.position, dir).ok_or(MoveError::OutOfBounds)?;
+    if !self.maze.passage(self.position, dir) {
+      return Err(MoveError::WallInTheWay)
+    }
+    self.position = next;
+    self.visited.insert(next);
+    self.steps += 1;
+    Ok(next)
+  }
+
+  pub fn current_position(&self) -> Point {
+    self.position
+  }
+
+  pub fn visited_cells(&self) -> &HashSet<Point> {
+    &self.visited
+  }
+
+  /// Whether we're standing on the maze's exit; never, if it hasn't got one
+  pub fn is_at_exit(&self) -> bool {
+    self.maze.exit == Some(self.position)
+  }
+
+  pub fn steps_taken(&self) -> usize {
+    self.steps
+  }
+}
+
+impl fmt::Display for MoveError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      MoveError::WallInTheWay => write!(f, "there's a wall in the way"),
+      MoveError::OutOfBounds => write!(f, "that would leave the maze")
+    }
+  }
+}
+
+impl<'a> Iterator for SolveIter<'a> {
+  type Item = SolveStep;
+  fn next(&mut self) -> Option<SolveStep> {
+    if self.done || self.frontier.is_empty() { return None }
+
+    let maze = self.maze;
+    let mut next = Vec::new();
+    for &pt in self.frontier.iter() {
+      self.visited[maze.index(pt)] = true;
+    }
+    for &pt in self.frontier.iter() {
+      for n in maze.open_neighbors(pt) {
+        if !self.visited[maze.index(n)] && self.parents[maze.index(n)].is_none() {
+          self.parents[maze.index(n)] = Some(pt);
+          next.push(n);
+        }
+      }
+    }
+    self.frontier = next;
+
+    let path = if self.visited[maze.index(self.goal)] {
+      self.done = true;
+      Some(maze.path_to(&self.parents, self.goal))
+    } else {
+      None
+    };
+
+    Some(SolveStep {
+      visited: maze.iter().filter(|&pt| self.visited[maze.index(pt)]).collect(),
+      frontier: self.frontier.clone(),
+      path,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Mask;
+  use rand::rngs::StdRng;
+  use rand::SeedableRng;
+
+  #[test]
+  fn maze_spiral_path_test() {
+    let mut m = Maze::new(3, 2).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::West).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::South).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::South).expect("");
+    m.carve(Point { x: 2, y: 0 }, Dir::South).expect("");
+
+    assert_eq!(m.spiral_path_from(Point { x: 1, y: 0 }),
+               Some(vec![Point { x: 1, y: 0 }, Point { x: 2, y: 0 }, Point { x: 2, y: 1 },
+                         Point { x: 0, y: 0 }, Point { x: 0, y: 1 }, Point { x: 1, y: 1 }]));
+    assert_eq!(m.spiral_path_from(Point { x: 3, y: 0 }), None);
+
+    let m = Maze::new(2, 2).expect("");
+    assert_eq!(m.spiral_path_from(Point { x: 0, y: 0 }), Some(vec![Point { x: 0, y: 0 }]));
+  }
+
+  #[test]
+  fn maze_solve_iter_test() {
+    let mut m = Maze::new(3, 1).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::East).expect("");
+
+    let steps: Vec<SolveStep> = m.solve_iter(Point { x: 0, y: 0 }, Point { x: 2, y: 0 }).collect();
+    assert_eq!(steps.len(), 3);
+    let mut observed = Vec::new();
+    let path = m.solve_observed(Point { x: 0, y: 0 }, Point { x: 2, y: 0 }, |step| observed.push(step.clone()));
+    assert_eq!((observed, path), (steps.clone(), m.solve(Point { x: 0, y: 0 }, Point { x: 2, y: 0 })));
+    assert_eq!(steps[0].visited, vec![Point { x: 0, y: 0 }]);
+    assert_eq!(steps[0].frontier, vec![Point { x: 1, y: 0 }]);
+    assert_eq!(steps[0].path, None);
+    assert_eq!(steps[2].path,
+               Some(vec![Point { x: 0, y: 0 }, Point { x: 1, y: 0 }, Point { x: 2, y: 0 }]));
+
+    let frames = m.solution_step_frames(Point { x: 0, y: 0 }, Point { x: 2, y: 0 });
+    assert_eq!(frames[0], "+-+-+-+\n|· !  |\n+-+-+-+\n");
+    assert_eq!(frames[2], "+-+-+-+\n|* * *|\n+-+-+-+\n");
+
+    // Unreachable goals just run out of frontier
+    m.uncarve(Point { x: 1, y: 0 }, Dir::East).expect("");
+    let steps: Vec<SolveStep> = m.solve_iter(Point { x: 0, y: 0 }, Point { x: 2, y: 0 }).collect();
+    assert_eq!(steps.len(), 2);
+    assert!(steps.iter().all(|step| step.path.is_none()));
+  }
+
+  #[test]
+  fn maze_backtracking_visualization_test() {
+    // A T shape: the search tries the dead end to the south before going east
+    let mut m = Maze::new(3, 2).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::South).expect("");
+
+    let frames = m.solve_with_backtracking_visualization(Point { x: 0, y: 0 }, Point { x: 2, y: 0 });
+    let currents: Vec<Option<Point>> = frames.iter().map(|f| f.current).collect();
+    assert_eq!(currents, vec![Some(Point { x: 0, y: 0 }), Some(Point { x: 1, y: 0 }),
+                              Some(Point { x: 1, y: 1 }), Some(Point { x: 1, y: 0 }),
+                              Some(Point { x: 2, y: 0 })]);
+    assert_eq!(frames[4].backtracked, vec![Point { x: 1, y: 1 }]);
+    assert_eq!(frames[4].visited.len(), 4);
+
+    let frames = m.solve_with_backtracking_visualization(Point { x: 0, y: 0 }, Point { x: 0, y: 1 });
+    assert_eq!(frames.last().expect("").current, None);
+    assert_eq!(frames.last().expect("").backtracked.len(), 4);
+  }
+
+  #[test]
+  fn maze_cells_sorted_by_distance_test() {
+    let mut m = Maze::new(3, 2).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::West).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::South).expect("");
+    m.carve(Point { x: 2, y: 0 }, Dir::South).expect("");
+
+    assert_eq!(m.cells_sorted_by_distance_from(Point { x: 1, y: 0 }).expect(""),
+               vec![(Point { x: 1, y: 0 }, 0), (Point { x: 0, y: 0 }, 1), (Point { x: 2, y: 0 }, 1),
+                    (Point { x: 1, y: 1 }, 1), (Point { x: 2, y: 1 }, 2)]);
+    assert!(m.cells_sorted_by_distance_from(Point { x: 0, y: 2 }).is_err());
+    assert_eq!(m.bfs_distances(Point { x: 0, y: 2 }), vec![None; 6]);
+
+    let big = Maze::new_seeded_square(8, 5).expect("");
+    let cells = big.cells_sorted_by_distance_from(Point { x: 3, y: 3 }).expect("");
+    assert_eq!(cells[0], (Point { x: 3, y: 3 }, 0));
+    assert_eq!(cells.len(), 64);
+    assert!(cells.windows(2).all(|w| w[0].1 < w[1].1 ||
+                                     (w[0].1 == w[1].1 && (w[0].0.y, w[0].0.x) < (w[1].0.y, w[1].0.x))));
+  }
+
+  #[test]
+  fn maze_solvable_test() {
+    let mut m = Maze::new(3, 3).expect("");
+    assert!(!m.is_solvable());
+    assert_eq!(m.assert_solvable(), Err(MazeError::MissingEntrance));
+
+    m.set_entrance(Point { x: 0, y: 0 }).expect("");
+    assert_eq!(m.assert_solvable(), Err(MazeError::MissingExit));
+    assert!(m.set_exit(Point { x: 3, y: 3 }).is_err());
+
+    m.set_exit(Point { x: 2, y: 2 }).expect("");
+    assert_eq!(m.assert_solvable(), Err(MazeError::NoPath));
+    assert_eq!(MazeError::NoPath.to_string(), "there is no path from the entrance to the exit");
+
+    m.recursive_backtracker(&mut StdRng::seed_from_u64(2));
+    assert!(m.is_solvable());
+    assert_eq!(m.assert_solvable(), Ok(()));
+  }
+
+  #[test]
+  fn maze_score_solution_path_test() {
+    // An S bend with one side passage off it
+    let mut m = Maze::new(3, 2).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::South).expect("");
+    m.carve(Point { x: 1, y: 1 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::South).expect("");
+
+    let path = vec![Point { x: 0, y: 0 }, Point { x: 1, y: 0 }, Point { x: 1, y: 1 }, Point { x: 2, y: 1 }];
+    let score = m.score_solution_path(&path);
+    assert_eq!((score.length, score.turns, score.dead_ends_encountered, score.backtrack_count), (3, 2, 2, 0));
+    assert_eq!(score.difficulty_index, 8.0 / 6.0);
+
+    let detour = vec![Point { x: 0, y: 0 }, Point { x: 1, y: 0 }, Point { x: 2, y: 0 },
+                      Point { x: 1, y: 0 }, Point { x: 1, y: 1 }];
+    assert_eq!(m.score_solution_path(&detour).backtrack_count, 2);
+    assert_eq!(m.score_solution_path(&vec![]).length, 0);
+  }
+
+  #[test]
+  fn maze_random_hamiltonian_walk_test() {
+    let mut rng = StdRng::seed_from_u64(4);
+    let m = Maze::new_seeded_square(6, 9).expect("");
+    let walk = m.random_hamiltonian_walk(Point { x: 2, y: 2 }, &mut rng, 10_000);
+    assert_eq!(walk.len(), 36);
+    assert_eq!(walk[0], Point { x: 2, y: 2 });
+    assert_eq!(walk.iter().collect::<HashSet<_>>().len(), 36);
+
+    assert_eq!(m.random_hamiltonian_walk(Point { x: 2, y: 2 }, &mut rng, 3).len(), 4);
+    assert!(m.random_hamiltonian_walk(Point { x: 6, y: 2 }, &mut rng, 3).is_empty());
+
+    // A corridor is walked straight down
+    let mut m = Maze::new(4, 1).expect("");
+    let all: Vec<Point> = m.iter().collect();
+    m.carve_all_passages_in(&all);
+    assert_eq!(m.random_hamiltonian_walk(Point { x: 0, y: 0 }, &mut rng, 100), all);
+    assert_eq!(Maze::new(2, 2).expect("").random_hamiltonian_walk(Point { x: 0, y: 0 }, &mut rng, 100),
+               vec![Point { x: 0, y: 0 }]);
+  }
+
+  #[test]
+  fn interactive_solver_test() {
+    let mut m = Maze::new(2, 2).expect("");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    m.carve(Point { x: 1, y: 0 }, Dir::South).expect("");
+    m.carve(Point { x: 1, y: 1 }, Dir::West).expect("");
+    m.set_exit(Point { x: 0, y: 1 }).expect("");
+
+    let mut player = m.solve_interactive(Point { x: 0, y: 0 });
+    assert_eq!(player.move_to(Dir::North), Err(MoveError::OutOfBounds));
+    assert_eq!(player.move_to(Dir::South), Err(MoveError::WallInTheWay));
+    assert_eq!(player.steps_taken(), 0);
+
+    assert_eq!(player.move_to(Dir::East), Ok(Point { x: 1, y: 0 }));
+    assert_eq!(player.move_to(Dir::West), Ok(Point { x: 0, y: 0 }));
+    assert_eq!(player.move_to(Dir::East), Ok(Point { x: 1, y: 0 }));
+    assert_eq!(player.move_to(Dir::South), Ok(Point { x: 1, y: 1 }));
+    assert!(!player.is_at_exit());
+    assert_eq!(player.move_to(Dir::West), Ok(Point { x: 0, y: 1 }));
+    assert!(player.is_at_exit());
+    assert_eq!(player.current_position(), Point { x: 0, y: 1 });
+    assert_eq!(player.steps_taken(), 5);
+    assert_eq!(player.visited_cells().len(), 4);
+  }
+
+  #[test]
+  fn maze_solve_test() {
+    let mut m = Maze::new(3, 3).expect("");
+    let p = |x, y| Point { x, y };
+    // A loop round the outside, and a dead end into the middle
+    for x in 0..2 {
+      m.carve(p(x, 0), Dir::East).expect("");
+      m.carve(p(x, 2), Dir::East).expect("");
+    }
+    for y in 0..2 {
+      m.carve(p(0, y), Dir::South).expect("");
+      m.carve(p(2, y), Dir::South).expect("");
+    }
+    m.carve(p(1, 0), Dir::South).expect("");
+
+    assert_eq!(m.solve(p(0, 0), p(2, 1)), Some(vec![p(0, 0), p(1, 0), p(2, 0), p(2, 1)]));
+    assert_eq!(m.solve(p(0, 1), p(1, 1)), Some(vec![p(0, 1), p(0, 0), p(1, 0), p(1, 1)]));
+    assert_eq!(m.solve(p(2, 2), p(2, 2)), Some(vec![p(2, 2)]));
+    assert_eq!(m.solve(p(0, 0), p(3, 0)), None);
+
+    let mut big = Maze::new(12, 9).expect("");
+    big.recursive_backtracker(&mut StdRng::seed_from_u64(5));
+    let path = big.solve(p(0, 0), p(11, 8)).expect("");
+    assert_eq!(path.len() - 1, big.bfs_distances(p(0, 0))[big.index(p(11, 8))].expect(""));
+
+    let closed = Maze::new(2, 2).expect("");
+    assert_eq!(closed.solve(p(0, 0), p(1, 1)), None);
+  }
+
+  #[test]
+  fn longest_path_test() {
+    let p = |x, y| Point { x, y };
+    // An S bend, longest from one end to the other
+    let mut m = Maze::new(3, 2).expect("");
+    m.carve(p(2, 0), Dir::West).expect("");
+    m.carve(p(1, 0), Dir::West).expect("");
+    m.carve(p(0, 0), Dir::South).expect("");
+    m.carve(p(0, 1), Dir::East).expect("");
+    let (from, to, path) = m.longest_path();
+    assert_eq!((from, to), (p(2, 0), p(1, 1)));
+    assert_eq!(path, vec![p(2, 0), p(1, 0), p(0, 0), p(0, 1), p(1, 1)]);
+
+    // Nothing in any perfect maze is farther apart
+    let mut m = Maze::new(8, 6).expect("");
+    m.kruskal(&mut StdRng::seed_from_u64(3));
+    let (_, _, path) = m.longest_path();
+    let farthest = m.iter().map(|pt| m.distances(pt).max().expect("").1).max();
+    assert_eq!(Some(path.len() - 1), farthest);
+
+    assert_eq!(Maze::new(1, 1).expect("").longest_path(), (p(0, 0), p(0, 0), vec![p(0, 0)]));
+    let mut masked = Maze::new_masked(Mask::from_text("X....\n.....\n").expect("")).expect("");
+    masked.recursive_backtracker(&mut StdRng::seed_from_u64(1));
+    let (from, to, path) = masked.longest_path();
+    assert!(masked.in_maze(from) && masked.in_maze(to));
+    let farthest = masked.iter().filter(|&pt| masked.in_maze(pt)).map(|pt| masked.distances(pt).max().expect("").1).max();
+    assert_eq!(Some(path.len() - 1), farthest);
+  }
+
+  #[test]
+  fn place_entrance_and_exit_test() {
+    let mut m = Maze::new(8, 6).expect("");
+    m.kruskal(&mut StdRng::seed_from_u64(3));
+    let (from, to) = m.place_entrance_and_exit().expect("");
+    assert_eq!((m.entrance(), m.exit()), (Some(from), Some(to)));
+    assert!(Dir::ALL.iter().any(|&dir| m.is_door(from, dir)));
+    assert!(Dir::ALL.iter().any(|&dir| m.is_door(to, dir)));
+    // No two edge cells are farther apart
+    let on_edge = |pt: Point| pt.x == 0 || pt.y == 0 || pt.x == 7 || pt.y == 5;
+    let farthest = m.iter().filter(|&pt| on_edge(pt))
+      .flat_map(|a| m.iter().filter(|&pt| on_edge(pt)).map(move |b| (a, b)))
+      .map(|(a, b)| m.solve(a, b).expect("").len())
+      .max();
+    assert_eq!(Some(m.solve(from, to).expect("").len()), farthest);
+
+    let mut torus = Maze::with_topology(4, 4, crate::Topology::Torus).expect("");
+    assert!(torus.place_entrance_and_exit().is_err());
+  }
+}
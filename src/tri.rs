@@ -0,0 +1,298 @@
+//! Triangle mazes: rows of triangles pointing alternately up and down.
+
+use std::fmt;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use crate::{grid, Algorithm, BoundsError, Dir, Grid, Maze, Point, UnionFind};
+
+/// A maze of triangles, `width` across and `height` rows down. The triangle
+/// at (0, 0) points up, and the rest alternate like a checkerboard, so
+/// every cell has neighbors east and west, and either one south (pointing
+/// up, sharing its base) or one north (pointing down).
+#[derive(Debug, Clone)]
+pub struct TriMaze {
+  // The triangles' sides are a subset of a rectangular grid's: all of the
+  // east-west ones, and the north-south ones under each upward triangle
+  pub(crate) maze: Maze,
+}
+
+/// An algorithm that can't run on a grid other than the rectangular one,
+/// because it relies on the rows and columns lining up
+#[derive(Debug, PartialEq)]
+pub struct UnsupportedAlgorithm(pub Algorithm);
+
+impl fmt::Display for UnsupportedAlgorithm {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} only works on a rectangular grid", self.0.name())
+  }
+}
+
+impl TriMaze {
+  /// A triangle maze with every wall up
+  pub fn new(width: usize, height: usize) -> Result<TriMaze, BoundsError> {
+    Ok(TriMaze { maze: Maze::new(width, height)? })
+  }
+
+  /// The rectangular maze the passages are kept in
+  pub fn maze(&self) -> &Maze {
+    &self.maze
+  }
+
+  pub fn width(&self) -> usize {
+    self.maze.width
+  }
+
+  pub fn height(&self) -> usize {
+    self.maze.height
+  }
+
+  pub fn is_upward(point: Point) -> bool {
+    (point.x + point.y).is_multiple_of(2)
+  }
+
+  /// The cell on the `dir` side of this one, if there's one there
+  pub fn neighbor(&self, point: Point, dir: Dir) -> Option<Point> {
+    match dir {
+      Dir::North if TriMaze::is_upward(point) => None,
+      Dir::South if !TriMaze::is_upward(point) => None,
+      _ => self.maze.neighbor(point, dir)
+    }
+  }
+
+  pub fn neighbors(&self, point: Point) -> Vec<Point> {
+    Dir::ALL.iter().filter_map(|&dir| self.neighbor(point, dir)).collect()
+  }
+
+  pub fn passage(&self, point: Point, dir: Dir) -> bool {
+    self.neighbor(point, dir).is_some() && self.maze.passage(point, dir)
+  }
+
+  pub fn carve(&mut self, point: Point, dir: Dir) -> Result<(), BoundsError> {
+    self.neighbor(point, dir).ok_or(BoundsError)?;
+    self.maze.carve(point, dir)
+  }
+
+  /// The cells joined to this one by passages
+  pub fn open_neighbors(&self, point: Point) -> Vec<Point> {
+    self.maze.open_neighbors(point)
+  }
+
+  /// Runs a generator on the triangles. The ones that only step from cell
+  /// to neighboring cell work; the others need a rectangular grid.
+  pub fn generate(&mut self, algorithm: Algorithm, rng: &mut impl Rng) -> Result<(), UnsupportedAlgorithm> {
+    match algorithm {
+      Algorithm::RecursiveBacktracker => self.recursive_backtracker(rng),
+      Algorithm::Wilsons => self.wilsons(rng),
+      Algorithm::Kruskal => self.kruskal(rng),
+      Algorithm::Prim => self.prim(rng),
+      _ => return Err(UnsupportedAlgorithm(algorithm))
+    }
+    self.maze.algorithm = Some(algorithm);
+    Ok(())
+  }
+
+  /// The recursive backtracker, as on a rectangular maze, with three ways
+  /// out of each cell
+  pub fn recursive_backtracker(&mut self, rng: &mut impl Rng) {
+    grid::recursive_backtracker(self, rng)
+  }
+
+  /// Wilson's algorithm, every spanning tree of the triangles equally likely
+  pub fn wilsons(&mut self, rng: &mut impl Rng) {
+    grid::wilsons(self, rng)
+  }
+
+  /// Kruskal's, knocking down walls in a random order wherever they
+  /// separate cells that aren't joined yet
+  pub fn kruskal(&mut self, rng: &mut impl Rng) {
+    let mut sets = UnionFind::new(self.width() * self.height());
+    let mut walls: Vec<(Point, Dir, Point)> = self.maze.iter()
+      .flat_map(|pt| vec![(pt, Dir::East), (pt, Dir::South)])
+      .filter_map(|(pt, dir)| self.neighbor(pt, dir).map(|n| (pt, dir, n)))
+      .collect();
+
+    walls.shuffle(rng);
+    for (pt, dir, n) in walls {
+      if sets.union(self.maze.index(pt), self.maze.index(n)) {
+        self.carve(pt, dir).expect("");
+      }
+    }
+  }
+
+  /// Simplified Prim's, growing out from a random cell
+  pub fn prim(&mut self, rng: &mut impl Rng) {
+    let mut in_maze = vec![false; self.width() * self.height()];
+    let start = Point { x: rng.gen_range(0, self.width()), y: rng.gen_range(0, self.height()) };
+    let mut frontier: Vec<(Point, Dir)> = Vec::new();
+    in_maze[self.maze.index(start)] = true;
+    frontier.extend(Dir::ALL.iter().filter(|&&dir| self.neighbor(start, dir).is_some()).map(|&dir| (start, dir)));
+
+    while !frontier.is_empty() {
+      let (pt, dir) = frontier.swap_remove(rng.gen_range(0, frontier.len()));
+      let n = self.neighbor(pt, dir).expect("");
+      if in_maze[self.maze.index(n)] { continue }
+      self.carve(pt, dir).expect("");
+      in_maze[self.maze.index(n)] = true;
+      for &d in Dir::ALL.iter() {
+        if self.neighbor(n, d).is_some_and(|m| !in_maze[self.maze.index(m)]) {
+          frontier.push((n, d));
+        }
+      }
+    }
+  }
+
+  /// Draws the maze as text, an upward triangle as
+  ///
+  /// ```text
+  ///  /\
+  /// /__\
+  /// ```
+  ///
+  /// and the downward ones fitting in between, with spaces for passages
+  pub fn render(&self) -> String {
+    let mut text = vec![vec![' '; 2 * self.width() + 2]; 2 * self.height() + 1];
+    for pt in self.maze.iter() {
+      let (col, top) = (2 * pt.x, 2 * pt.y + 1);
+      let mut draw = |dir: Dir, marks: &[(usize, usize)], c: char| {
+        if !self.passage(pt, dir) {
+          for &(row, at) in marks { text[row][at] = c }
+        }
+      };
+      if TriMaze::is_upward(pt) {
+        draw(Dir::West, &[(top, col + 1), (top + 1, col)], '/');
+        draw(Dir::East, &[(top, col + 2), (top + 1, col + 3)], '\\');
+        draw(Dir::South, &[(top + 1, col + 1), (top + 1, col + 2)], '_');
+      } else {
+        draw(Dir::North, &[(top - 1, col + 1), (top - 1, col + 2)], '_');
+        draw(Dir::West, &[(top, col), (top + 1, col + 1)], '\\');
+        draw(Dir::East, &[(top, col + 3), (top + 1, col + 2)], '/');
+      }
+    }
+    let lines: Vec<String> = text.iter().map(|line| line.iter().collect::<String>().trim_end().to_string()).collect();
+    // The top line is empty unless a downward triangle is in the top row
+    let skip = if lines[0].is_empty() { 1 } else { 0 };
+    lines[skip..].iter().map(|line| format!("{}\n", line)).collect()
+  }
+
+  /// Draws the walls as SVG, each triangle's sides `side` long
+  pub fn to_svg(&self, side: f64) -> String {
+    let h = side * 3f64.sqrt() / 2.0;
+    let (width, height) = (side * (self.width() + 1) as f64 / 2.0, h * self.height() as f64);
+    let mut d = Vec::new();
+    let mut line = |(x1, y1): (f64, f64), (x2, y2): (f64, f64)| {
+      d.push(format!("M {:.2} {:.2} L {:.2} {:.2}", x1, y1, x2, y2));
+    };
+    for pt in self.maze.iter() {
+      let (left, mid, right) = (side * pt.x as f64 / 2.0, side * (pt.x + 1) as f64 / 2.0, side * (pt.x + 2) as f64 / 2.0);
+      let (top, bottom) = (h * pt.y as f64, h * (pt.y + 1) as f64);
+      // A slanted wall is drawn by the cell east of it, as that cell's West
+      // side, with the last column drawing its East side too; a flat one by
+      // the upward cell of the two
+      let east = pt.x + 1 == self.width() && !self.passage(pt, Dir::East);
+      if TriMaze::is_upward(pt) {
+        if !self.passage(pt, Dir::West) { line((left, bottom), (mid, top)) }
+        if east { line((mid, top), (right, bottom)) }
+        if !self.passage(pt, Dir::South) { line((left, bottom), (right, bottom)) }
+      } else {
+        if !self.passage(pt, Dir::West) { line((left, top), (mid, bottom)) }
+        if east { line((mid, bottom), (right, top)) }
+        if pt.y == 0 { line((left, top), (right, top)) }
+      }
+    }
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.2}\" height=\"{:.2}\" viewBox=\"-1 -1 {:.2} {:.2}\">\n",
+                          width + 2.0, height + 2.0, width + 2.0, height + 2.0);
+    svg.push_str(&format!("<path d=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"2\" stroke-linecap=\"round\"/>\n",
+                          d.join(" ")));
+    svg.push_str("</svg>\n");
+    svg
+  }
+}
+
+impl Grid for TriMaze {
+  type Cell = Point;
+
+  fn size(&self) -> usize {
+    self.width() * self.height()
+  }
+
+  fn index(&self, cell: Point) -> usize {
+    self.maze.index(cell)
+  }
+
+  fn cells(&self) -> Vec<Point> {
+    self.maze.iter().collect()
+  }
+
+  fn neighbors(&self, cell: Point) -> Vec<Point> {
+    TriMaze::neighbors(self, cell)
+  }
+
+  fn linked(&self, a: Point, b: Point) -> bool {
+    self.maze.direction_between(a, b).is_some_and(|dir| self.passage(a, dir))
+  }
+
+  fn link(&mut self, a: Point, b: Point) -> Result<(), BoundsError> {
+    self.carve(a, self.maze.direction_between(a, b).ok_or(BoundsError)?)
+  }
+
+  fn unlink(&mut self, a: Point, b: Point) -> Result<(), BoundsError> {
+    let dir = self.maze.direction_between(a, b).ok_or(BoundsError)?;
+    self.neighbor(a, dir).ok_or(BoundsError)?;
+    self.maze.uncarve(a, dir)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::rngs::StdRng;
+  use rand::SeedableRng;
+
+  #[test]
+  fn tri_neighbor_test() {
+    let mut m = TriMaze::new(4, 3).expect("");
+    let p = |x, y| Point { x, y };
+    assert_eq!(m.neighbors(p(0, 0)), vec![p(0, 1), p(1, 0)]);
+    assert_eq!(m.neighbors(p(1, 0)), vec![p(2, 0), p(0, 0)]);
+    assert_eq!(m.neighbors(p(1, 1)), vec![p(1, 2), p(2, 1), p(0, 1)]);
+    assert_eq!(m.neighbors(p(2, 1)), vec![p(2, 0), p(3, 1), p(1, 1)]);
+    for pt in m.maze.iter() {
+      assert!(m.neighbors(pt).len() <= 3);
+      for n in m.neighbors(pt) { assert!(m.neighbors(n).contains(&pt)) }
+    }
+
+    assert!(m.carve(p(0, 0), Dir::North).is_err());
+    assert!(m.carve(p(1, 0), Dir::South).is_err());
+    m.carve(p(1, 1), Dir::South).expect("");
+    assert!(m.passage(p(1, 2), Dir::North));
+  }
+
+  #[test]
+  fn tri_generate_test() {
+    for &algorithm in [Algorithm::RecursiveBacktracker, Algorithm::Wilsons, Algorithm::Kruskal, Algorithm::Prim].iter() {
+      let mut m = TriMaze::new(9, 5).expect("");
+      m.generate(algorithm, &mut StdRng::seed_from_u64(3)).expect("");
+      assert!(m.maze.is_perfect(), "{:?}", algorithm);
+      for pt in m.maze.iter() {
+        for n in m.open_neighbors(pt) { assert!(m.neighbors(pt).contains(&n), "{:?}", algorithm) }
+      }
+    }
+
+    let mut m = TriMaze::new(3, 3).expect("");
+    assert_eq!(m.generate(Algorithm::BinaryTree, &mut StdRng::seed_from_u64(3)),
+               Err(UnsupportedAlgorithm(Algorithm::BinaryTree)));
+  }
+
+  #[test]
+  fn tri_render_test() {
+    let mut m = TriMaze::new(2, 1).expect("");
+    assert_eq!(m.render(), "   __\n /\\  /\n/__\\/\n");
+    m.carve(Point { x: 0, y: 0 }, Dir::East).expect("");
+    assert_eq!(m.render(), "   __\n /   /\n/__ /\n");
+    assert_eq!(TriMaze::new(1, 1).expect("").render(), " /\\\n/__\\\n");
+
+    let svg = m.to_svg(10.0);
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"17.00\" height=\"10.66\""));
+    assert_eq!(svg.matches('M').count(), 4);
+  }
+}
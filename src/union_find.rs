@@ -0,0 +1,91 @@
+//! A disjoint-set forest, for generators that join cells into ever larger
+//! connected pieces.
+
+/// Sets of the numbers 0..n, each starting on its own, that can be merged
+/// and asked whether two numbers have ended up together. Uses union by rank
+/// and path compression, so both are as good as constant time.
+#[derive(Debug, Clone)]
+pub struct UnionFind {
+  parent: Vec<usize>,
+  rank: Vec<u8>,
+  sets: usize,
+}
+
+impl UnionFind {
+  /// `n` sets of one element each
+  pub fn new(n: usize) -> UnionFind {
+    UnionFind { parent: (0..n).collect(), rank: vec![0; n], sets: n }
+  }
+
+  /// The representative of the set `x` is in. Two elements are in the same
+  /// set exactly when they have the same representative.
+  pub fn find(&mut self, x: usize) -> usize {
+    let mut root = x;
+    while self.parent[root] != root {
+      root = self.parent[root];
+    }
+    let mut x = x;
+    while self.parent[x] != root {
+      let next = self.parent[x];
+      self.parent[x] = root;
+      x = next;
+    }
+    root
+  }
+
+  /// Merges the sets holding `a` and `b`. Returns false if they were
+  /// already the same set.
+  pub fn union(&mut self, a: usize, b: usize) -> bool {
+    let (a, b) = (self.find(a), self.find(b));
+    if a == b { return false }
+    if self.rank[a] < self.rank[b] {
+      self.parent[a] = b;
+    } else {
+      self.parent[b] = a;
+      if self.rank[a] == self.rank[b] { self.rank[a] += 1 }
+    }
+    self.sets -= 1;
+    true
+  }
+
+  pub fn connected(&mut self, a: usize, b: usize) -> bool {
+    self.find(a) == self.find(b)
+  }
+
+  /// How many separate sets there are
+  pub fn set_count(&self) -> usize {
+    self.sets
+  }
+
+  /// How many elements there are, in all the sets together
+  pub fn len(&self) -> usize {
+    self.parent.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.parent.is_empty()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn union_find_test() {
+    let mut uf = UnionFind::new(6);
+    assert_eq!((uf.len(), uf.set_count()), (6, 6));
+    assert!(!uf.connected(0, 1));
+
+    assert!(uf.union(0, 1));
+    assert!(uf.union(2, 3));
+    assert!(uf.union(1, 3));
+    assert!(!uf.union(0, 2));
+    assert_eq!(uf.set_count(), 3);
+    assert!(uf.connected(0, 3));
+    assert!(!uf.connected(0, 4));
+    assert_eq!(uf.find(2), uf.find(1));
+
+    assert!(UnionFind::new(0).is_empty());
+  }
+}
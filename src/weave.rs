@@ -0,0 +1,380 @@
+//! Weave mazes, whose passages can cross over and under each other.
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::collections::{HashMap, VecDeque};
+use crate::{BoundsError, Dir, Maze, Path, Point, UnionFind};
+
+/// A maze whose corridors can cross over and under each other. A crossing
+/// cell has all four passages open, but the north-south and east-west
+/// corridors through it don't meet: walking into one you have to carry on
+/// straight out the other side.
+#[derive(Debug)]
+pub struct WeaveMaze {
+  pub(crate) maze: Maze,
+  // Crossings where the north-south corridor runs over the east-west one
+  pub(crate) over_passages: Vec<bool>,
+  // Crossings where the north-south corridor runs underneath
+  pub(crate) under_passages: Vec<bool>,
+}
+
+impl WeaveMaze {
+  pub fn new(width: usize, height: usize) -> Result<WeaveMaze, BoundsError> {
+    let maze = Maze::new(width, height)?;
+    Ok(WeaveMaze {
+      maze,
+      over_passages: vec![false; width * height],
+      under_passages: vec![false; width * height],
+    })
+  }
+
+  /// The plain maze underneath, without its crossings
+  pub fn maze(&self) -> &Maze {
+    &self.maze
+  }
+
+  pub fn is_crossing(&self, point: Point) -> bool {
+    self.maze.valid(point) &&
+      (self.over_passages[self.maze.index(point)] || self.under_passages[self.maze.index(point)])
+  }
+
+  /// Makes a crossing with the north-south corridor bridging over the
+  /// east-west one. Crossings can't go on the edge of the maze.
+  pub fn carve_over(&mut self, point: Point) -> Result<(), BoundsError> {
+    self.carve_crossing(point)?;
+    self.over_passages[self.maze.index(point)] = true;
+    Ok(())
+  }
+
+  /// Makes a crossing with the north-south corridor tunneling under the
+  /// east-west one
+  pub fn carve_under(&mut self, point: Point) -> Result<(), BoundsError> {
+    self.carve_crossing(point)?;
+    self.under_passages[self.maze.index(point)] = true;
+    Ok(())
+  }
+
+  fn carve_crossing(&mut self, point: Point) -> Result<(), BoundsError> {
+    if !self.maze.valid(point) || self.maze.edge(point) {
+      return Err(BoundsError)
+    }
+    for &dir in Dir::ALL.iter() {
+      self.maze.carve(point, dir)?;
+    }
+    Ok(())
+  }
+
+  /// Whether the corridor running `dir` through this crossing is the top one
+  pub fn over_along(&self, point: Point, dir: Dir) -> bool {
+    if !self.is_crossing(point) { return false }
+    let vertical = self.over_passages[self.maze.index(point)];
+    match dir {
+      Dir::North | Dir::South => vertical,
+      Dir::East | Dir::West => !vertical
+    }
+  }
+
+  /// Recursive backtracker that, when it comes up against a straight
+  /// corridor it has already carved, may tunnel underneath it to an
+  /// unvisited cell on the far side, with the given probability (0.0 to
+  /// 1.0; anything outside that is clamped)
+  pub fn recursive_backtracker(&mut self, rng: &mut impl Rng, crossing_probability: f64) {
+    let maze = &self.maze;
+    let mut visited = vec![false; maze.width * maze.height];
+    let start = Point { x: rng.gen_range(0, maze.width), y: rng.gen_range(0, maze.height) };
+    let mut stack = vec![start];
+    visited[maze.index(start)] = true;
+
+    while let Some(&pt) = stack.last() {
+      let mut plain = Vec::new();
+      let mut tunnels = Vec::new();
+      for &dir in Dir::ALL.iter() {
+        if let Some(n) = self.maze.neighbor(pt, dir) {
+          if !visited[self.maze.index(n)] {
+            plain.push((dir, n));
+          } else if let Some(beyond) = self.maze.neighbor(n, dir) {
+            let corridor = self.maze.passage(n, dir.right()) && self.maze.passage(n, dir.left()) &&
+              !self.maze.passage(n, dir) && !self.maze.passage(n, dir.opposite());
+            if corridor && !self.is_crossing(n) && !visited[self.maze.index(beyond)] {
+              tunnels.push((dir, beyond));
+            }
+          }
+        }
+      }
+
+      if !tunnels.is_empty() && rng.gen_bool(crossing_probability.clamp(0.0, 1.0)) {
+        let &(dir, beyond) = tunnels.choose(rng).expect("");
+        let under = self.maze.neighbor(pt, dir).expect("");
+        self.maze.carve(pt, dir).expect("");
+        self.maze.carve(under, dir).expect("");
+        match dir {
+          Dir::North | Dir::South => self.under_passages[self.maze.index(under)] = true,
+          Dir::East | Dir::West => self.over_passages[self.maze.index(under)] = true
+        }
+        visited[self.maze.index(beyond)] = true;
+        stack.push(beyond);
+      } else if let Some(&(dir, n)) = plain.choose(rng) {
+        self.maze.carve(pt, dir).expect("");
+        visited[self.maze.index(n)] = true;
+        stack.push(n);
+      } else {
+        stack.pop();
+      }
+    }
+  }
+
+  /// Kruskal's with crossings: first lays down crossings at random, each
+  /// interior cell getting one with the given probability (0.0 to 1.0,
+  /// clamped) as long as the cell and its four neighbors aren't joined to
+  /// each other yet and no neighbor is already a crossing, then knocks down
+  /// walls wherever they separate cells that aren't joined.
+  pub fn kruskal(&mut self, rng: &mut impl Rng, crossing_probability: f64) {
+    let mut sets = UnionFind::new(self.maze.width * self.maze.height);
+    let mut cells: Vec<Point> = self.maze.iter().filter(|&pt| !self.maze.edge(pt)).collect();
+    cells.shuffle(rng);
+
+    for pt in cells {
+      if !rng.gen_bool(crossing_probability.clamp(0.0, 1.0)) { continue }
+      let around: Vec<Point> = Dir::ALL.iter().filter_map(|&dir| self.maze.neighbor(pt, dir)).collect();
+      let mut roots: Vec<usize> = around.iter().chain([pt].iter()).map(|&p| sets.find(self.maze.index(p))).collect();
+      roots.sort_unstable();
+      roots.dedup();
+      if roots.len() < 5 || around.iter().any(|&n| self.is_crossing(n)) { continue }
+
+      // The crossing cell goes along with the corridor on top
+      let over = rng.gen_bool(0.5);
+      if over { self.carve_over(pt).expect("") } else { self.carve_under(pt).expect("") }
+      let (top, bottom) = if over { ([Dir::North, Dir::South], [Dir::East, Dir::West]) }
+                          else { ([Dir::East, Dir::West], [Dir::North, Dir::South]) };
+      for &dir in top.iter() {
+        sets.union(self.maze.index(pt), self.maze.index(self.maze.neighbor(pt, dir).expect("")));
+      }
+      let ends: Vec<usize> = bottom.iter().map(|&dir| self.maze.index(self.maze.neighbor(pt, dir).expect(""))).collect();
+      sets.union(ends[0], ends[1]);
+    }
+
+    let mut walls = Vec::new();
+    for (pt, dir) in self.maze.interior_edges() {
+      if !self.maze.passage(pt, dir) { walls.push((pt, dir, self.maze.neighbor(pt, dir).expect(""))) }
+    }
+    walls.shuffle(rng);
+    for (pt, dir, n) in walls {
+      if sets.union(self.maze.index(pt), self.maze.index(n)) {
+        self.maze.carve(pt, dir).expect("");
+      }
+    }
+  }
+
+  /// Follows a passage out of a cell, carrying straight on through any
+  /// crossings, and returns every cell passed through on the way
+  pub fn corridor(&self, point: Point, dir: Dir) -> Option<Vec<Point>> {
+    if !self.maze.passage(point, dir) { return None }
+    let mut cells = vec![self.maze.neighbor(point, dir)?];
+    while self.is_crossing(cells[cells.len() - 1]) {
+      cells.push(self.maze.neighbor(cells[cells.len() - 1], dir)?);
+    }
+    Some(cells)
+  }
+
+  /// Shortest path that never turns at a crossing. Starting on a crossing
+  /// cell lets you leave by either corridor.
+  pub fn solve(&self, from: Point, to: Point) -> Option<Path> {
+    if !self.maze.valid(from) || !self.maze.valid(to) { return None }
+    if from == to { return Some(vec![from]) }
+
+    let mut parents: HashMap<Point, (Point, Vec<Point>)> = HashMap::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(from);
+
+    while let Some(pt) = queue.pop_front() {
+      for &dir in Dir::ALL.iter() {
+        if let Some(cells) = self.corridor(pt, dir) {
+          if let Some(i) = cells.iter().position(|&c| c == to) {
+            let mut path = cells[..=i].to_vec();
+            let mut at = pt;
+            while at != from {
+              let (parent, cells) = &parents[&at];
+              path.splice(0..0, cells.iter().copied());
+              at = *parent;
+            }
+            path.insert(0, from);
+            return Some(path)
+          }
+          let end = cells[cells.len() - 1];
+          if end != from && !parents.contains_key(&end) {
+            parents.insert(end, (pt, cells));
+            queue.push_back(end);
+          }
+        }
+      }
+    }
+    None
+  }
+
+  /// Crossings are drawn '+', with '=' on either side of the corridor on top
+  pub fn render(&self) -> String {
+    self.maze.render_with(|pt| if self.is_crossing(pt) { '+' } else { ' ' }, |pt, dir| {
+      let bridge = self.over_along(pt, dir) ||
+        self.maze.neighbor(pt, dir).is_some_and(|n| self.over_along(n, dir));
+      if bridge { "=" } else { self.maze.char(pt, dir) }
+    })
+  }
+
+  /// Draws the maze as SVG with every cell inset from its edges by `inset`
+  /// (a fraction of `cell_size`, up to a half), so passages are corridors
+  /// with walls either side and a crossing shows the corridor underneath
+  /// going in one side and out the other with the top one over it
+  pub fn to_svg_inset(&self, cell_size: usize, inset: f64) -> String {
+    let size = cell_size as f64;
+    let gap = size * inset.clamp(0.0, 0.5);
+    let (w, h) = (self.maze.width as f64 * size, self.maze.height as f64 * size);
+    let mut d = Vec::new();
+    let mut line = |(x1, y1): (f64, f64), (x2, y2): (f64, f64)| {
+      d.push(format!("M {} {} L {} {}", x1, y1, x2, y2));
+    };
+
+    for pt in self.maze.iter() {
+      // Across and down: the cell's edge, where the inside starts and ends,
+      // and its other edge
+      let (left, top) = (pt.x as f64 * size, pt.y as f64 * size);
+      let xs = [left, left + gap, left + size - gap, left + size];
+      let ys = [top, top + gap, top + size - gap, top + size];
+      if self.is_crossing(pt) {
+        let under = if self.over_along(pt, Dir::North) {
+          line((xs[1], ys[0]), (xs[1], ys[3]));
+          line((xs[2], ys[0]), (xs[2], ys[3]));
+          [Dir::East, Dir::West]
+        } else {
+          line((xs[0], ys[1]), (xs[3], ys[1]));
+          line((xs[0], ys[2]), (xs[3], ys[2]));
+          [Dir::North, Dir::South]
+        };
+        for &dir in under.iter() { stub(&mut line, &xs, &ys, dir) }
+        continue
+      }
+      for &dir in Dir::ALL.iter() {
+        if self.maze.passage(pt, dir) {
+          stub(&mut line, &xs, &ys, dir);
+        } else {
+          match dir {
+            Dir::North => line((xs[1], ys[1]), (xs[2], ys[1])),
+            Dir::South => line((xs[1], ys[2]), (xs[2], ys[2])),
+            Dir::West => line((xs[1], ys[1]), (xs[1], ys[2])),
+            Dir::East => line((xs[2], ys[1]), (xs[2], ys[2]))
+          }
+        }
+      }
+    }
+
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+                          w, h, w, h);
+    svg.push_str(&format!("<rect x=\"0\" y=\"0\" width=\"{}\" height=\"{}\" fill=\"white\"/>\n", w, h));
+    svg.push_str(&format!("<path d=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"2\" stroke-linecap=\"square\"/>\n",
+                          d.join(" ")));
+    svg.push_str("</svg>\n");
+    svg
+  }
+}
+
+// The two walls of a passage leading `dir` out of an inset cell, from its
+// inside to its edge
+fn stub(line: &mut impl FnMut((f64, f64), (f64, f64)), xs: &[f64; 4], ys: &[f64; 4], dir: Dir) {
+  match dir {
+    Dir::North => { line((xs[1], ys[0]), (xs[1], ys[1])); line((xs[2], ys[0]), (xs[2], ys[1])) },
+    Dir::South => { line((xs[1], ys[2]), (xs[1], ys[3])); line((xs[2], ys[2]), (xs[2], ys[3])) },
+    Dir::West => { line((xs[0], ys[1]), (xs[1], ys[1])); line((xs[0], ys[2]), (xs[1], ys[2])) },
+    Dir::East => { line((xs[2], ys[1]), (xs[3], ys[1])); line((xs[2], ys[2]), (xs[3], ys[2])) }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::rngs::StdRng;
+  use rand::SeedableRng;
+
+  #[test]
+  fn weave_crossing_test() {
+    let mut w = WeaveMaze::new(3, 3).expect("");
+    assert!(w.carve_over(Point { x: 0, y: 1 }).is_err());
+    w.carve_under(Point { x: 1, y: 1 }).expect("");
+    assert!(w.is_crossing(Point { x: 1, y: 1 }));
+    assert_eq!(w.render(), "+-+-+-+\n| | | |\n+-+ +-+\n| =+= |\n+-+ +-+\n| | | |\n+-+-+-+\n");
+
+    // The corridors through a crossing don't connect
+    assert_eq!(w.solve(Point { x: 0, y: 1 }, Point { x: 2, y: 1 }),
+               Some(vec![Point { x: 0, y: 1 }, Point { x: 1, y: 1 }, Point { x: 2, y: 1 }]));
+    assert_eq!(w.solve(Point { x: 0, y: 1 }, Point { x: 1, y: 2 }), None);
+
+    w.maze.carve(Point { x: 0, y: 1 }, Dir::South).expect("");
+    w.maze.carve(Point { x: 0, y: 2 }, Dir::East).expect("");
+    assert_eq!(w.solve(Point { x: 2, y: 1 }, Point { x: 1, y: 0 }),
+               Some(vec![Point { x: 2, y: 1 }, Point { x: 1, y: 1 }, Point { x: 0, y: 1 },
+                         Point { x: 0, y: 2 }, Point { x: 1, y: 2 }, Point { x: 1, y: 1 },
+                         Point { x: 1, y: 0 }]));
+  }
+
+  #[test]
+  fn weave_backtracker_test() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut w = WeaveMaze::new(12, 12).expect("");
+    w.recursive_backtracker(&mut rng, 1.0);
+
+    assert!(w.maze.iter().any(|pt| w.is_crossing(pt)));
+    let start = Point { x: 0, y: 0 };
+    for pt in w.maze.iter().filter(|&pt| !w.is_crossing(pt)) {
+      assert!(w.solve(start, pt).is_some(), "can't reach {:?}", pt);
+    }
+
+    let mut plain = WeaveMaze::new(6, 6).expect("");
+    plain.recursive_backtracker(&mut StdRng::seed_from_u64(7), -1.0);
+    assert!(plain.maze.is_perfect());
+    let mut over = WeaveMaze::new(12, 12).expect("");
+    over.recursive_backtracker(&mut StdRng::seed_from_u64(7), 2.0);
+    assert_eq!(over.over_passages, w.over_passages);
+  }
+
+  #[test]
+  fn weave_kruskal_test() {
+    let mut w = WeaveMaze::new(12, 12).expect("");
+    w.kruskal(&mut StdRng::seed_from_u64(3), 0.5);
+
+    let crossings: Vec<Point> = w.maze.iter().filter(|&pt| w.is_crossing(pt)).collect();
+    assert!(!crossings.is_empty());
+    for &pt in crossings.iter() {
+      assert!(!w.maze.edge(pt));
+      assert!(Dir::ALL.iter().all(|&dir| !w.is_crossing(w.maze.neighbor(pt, dir).expect(""))));
+    }
+    // A tree once each crossing counts as two cells, one per corridor
+    assert_eq!(w.maze.passage_count(), 144 + crossings.len() - 1);
+    let start = Point { x: 0, y: 0 };
+    for pt in w.maze.iter().filter(|&pt| !w.is_crossing(pt)) {
+      assert!(w.solve(start, pt).is_some(), "can't reach {:?}", pt);
+    }
+
+    let mut plain = WeaveMaze::new(6, 6).expect("");
+    plain.kruskal(&mut StdRng::seed_from_u64(3), 0.0);
+    assert!(plain.maze.is_perfect());
+    let mut under = WeaveMaze::new(6, 6).expect("");
+    under.kruskal(&mut StdRng::seed_from_u64(3), -0.5);
+    assert!(under.maze.is_perfect());
+    let mut over = WeaveMaze::new(12, 12).expect("");
+    over.kruskal(&mut StdRng::seed_from_u64(3), 1.5);
+    assert!(over.maze.iter().any(|pt| over.is_crossing(pt)));
+  }
+
+  #[test]
+  fn weave_inset_svg_test() {
+    let mut w = WeaveMaze::new(3, 3).expect("");
+    w.carve_over(Point { x: 1, y: 1 }).expect("");
+    let svg = w.to_svg_inset(10, 0.25);
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"30\" height=\"30\""));
+    // The bridge runs the whole height of the middle cell, and the corridor
+    // underneath stops at its sides
+    assert!(svg.contains("M 12.5 10 L 12.5 20"));
+    assert!(svg.contains("M 10 12.5 L 12.5 12.5"));
+    assert!(!svg.contains("M 12.5 12.5 L 17.5 12.5"));
+    // A closed side is a single wall across the inside of the cell
+    assert!(svg.contains("M 2.5 2.5 L 7.5 2.5"));
+  }
+}